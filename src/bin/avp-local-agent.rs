@@ -0,0 +1,19 @@
+//! The `avp-local-agent` command-line tool, see `avp_local_agent::public::cli`.
+use std::io;
+use std::process::ExitCode;
+
+use avp_local_agent::public::cli::Cli;
+use clap::Parser;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let client = cli.client().await;
+    match cli.run(client, &mut io::stdout()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! This module contains the implementation of the identity source cache.
+use aws_sdk_verifiedpermissions::operation::get_identity_source::GetIdentitySourceOutput;
+use aws_sdk_verifiedpermissions::types::IdentitySourceItem;
+use std::collections::hash_map::Iter;
+use std::collections::HashMap;
+use tracing::{debug, instrument};
+
+use crate::private::sources::{Cache, CacheChange};
+use crate::private::types::aliases::IdentitySourceCache;
+use crate::private::types::identity_source_id::IdentitySourceId;
+
+/// An implementation of the identity source cache. This caches the raw `GetIdentitySourceOutput`
+/// structs from AVP `GetIdentitySource` calls.
+#[derive(Debug)]
+pub struct GetIdentitySourceOutputCache {
+    /// Identity source cache of `IdentitySourceId`, `GetIdentitySourceOutput`
+    identity_source_cache: IdentitySourceCache<GetIdentitySourceOutput>,
+}
+
+/// An `IntoIterator` implementation for the identity source cache. This enables iteration of cache
+/// values without the need to clone the whole cache
+impl<'a> IntoIterator for &'a GetIdentitySourceOutputCache {
+    type Item = (&'a IdentitySourceId, &'a GetIdentitySourceOutput);
+    type IntoIter = Iter<'a, IdentitySourceId, GetIdentitySourceOutput>;
+
+    fn into_iter(self) -> Iter<'a, IdentitySourceId, GetIdentitySourceOutput> {
+        self.identity_source_cache.iter()
+    }
+}
+
+impl Cache for GetIdentitySourceOutputCache {
+    type Key = IdentitySourceId;
+    type Value = GetIdentitySourceOutput;
+    type LoadedItems = HashMap<Self::Key, IdentitySourceItem>;
+    type PendingUpdates = HashMap<Self::Key, CacheChange>;
+
+    fn new() -> Self {
+        Self {
+            identity_source_cache: HashMap::new(),
+        }
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
+        self.identity_source_cache.get(key)
+    }
+
+    #[instrument(level = "trace", skip(self, value))]
+    fn put(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
+        self.identity_source_cache.insert(key, value)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
+        self.identity_source_cache.remove(key)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    fn get_pending_updates(&self, ids_map: &Self::LoadedItems) -> Self::PendingUpdates {
+        let mut identity_source_updates: Self::PendingUpdates = HashMap::new();
+
+        for identity_source_id in self.identity_source_cache.keys() {
+            if !ids_map.contains_key(identity_source_id) {
+                identity_source_updates.insert(identity_source_id.clone(), CacheChange::Deleted);
+            }
+        }
+
+        for (identity_source_id, identity_source_item) in ids_map {
+            match self.identity_source_cache.get(identity_source_id) {
+                None => {
+                    identity_source_updates
+                        .insert(identity_source_id.clone(), CacheChange::Created);
+                }
+                Some(cached)
+                    if identity_source_item.last_updated_date > cached.last_updated_date =>
+                {
+                    identity_source_updates
+                        .insert(identity_source_id.clone(), CacheChange::Updated);
+                }
+                Some(_) => {}
+            }
+        }
+        debug!("Identity Source Cache Pending Updates: identity_source_pending_updates={identity_source_updates:?}");
+        identity_source_updates
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::private::sources::cache::identity_source::GetIdentitySourceOutputCache;
+    use crate::private::sources::{Cache, CacheChange};
+    use crate::private::types::identity_source_id::IdentitySourceId;
+    use aws_sdk_verifiedpermissions::operation::get_identity_source::GetIdentitySourceOutput;
+    use aws_sdk_verifiedpermissions::types::IdentitySourceItem;
+    use aws_smithy_types::DateTime;
+    use std::collections::HashMap;
+
+    const IDENTITY_SOURCE_ID: &str = "is-1";
+
+    fn create_get_identity_source_output(last_updated_date: DateTime) -> GetIdentitySourceOutput {
+        GetIdentitySourceOutput::builder()
+            .policy_store_id("ps-1")
+            .identity_source_id(IDENTITY_SOURCE_ID)
+            .principal_entity_type("User")
+            .created_date(DateTime::from_secs(0))
+            .last_updated_date(last_updated_date)
+            .build()
+            .unwrap()
+    }
+
+    fn create_identity_source_item(last_updated_date: DateTime) -> IdentitySourceItem {
+        IdentitySourceItem::builder()
+            .policy_store_id("ps-1")
+            .identity_source_id(IDENTITY_SOURCE_ID)
+            .principal_entity_type("User")
+            .created_date(DateTime::from_secs(0))
+            .last_updated_date(last_updated_date)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn get_pending_updates_detects_created_updated_and_deleted_identity_sources() {
+        let id = IdentitySourceId(IDENTITY_SOURCE_ID.to_string());
+        let mut cache = GetIdentitySourceOutputCache::new();
+
+        let loaded = HashMap::from([(
+            id.clone(),
+            create_identity_source_item(DateTime::from_secs(1)),
+        )]);
+        assert_eq!(
+            cache.get_pending_updates(&loaded),
+            HashMap::from([(id.clone(), CacheChange::Created)])
+        );
+
+        cache.put(
+            id.clone(),
+            create_get_identity_source_output(DateTime::from_secs(1)),
+        );
+        assert!(cache.get_pending_updates(&loaded).is_empty());
+
+        let updated = HashMap::from([(
+            id.clone(),
+            create_identity_source_item(DateTime::from_secs(2)),
+        )]);
+        assert_eq!(
+            cache.get_pending_updates(&updated),
+            HashMap::from([(id.clone(), CacheChange::Updated)])
+        );
+
+        assert_eq!(
+            cache.get_pending_updates(&HashMap::new()),
+            HashMap::from([(id, CacheChange::Deleted)])
+        );
+    }
+}
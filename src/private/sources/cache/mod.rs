@@ -1,4 +1,6 @@
 //! A module that implements the caches required to store data retrieved from Amazon Verified
 //! Permissions.
+#[cfg(feature = "token")]
+pub mod identity_source;
 pub mod policy;
 pub mod template;
@@ -0,0 +1,248 @@
+//! Exposes the identity sources of a policy store, read with Verified Permissions API calls.
+use std::collections::HashMap;
+
+use aws_sdk_verifiedpermissions::operation::get_identity_source::GetIdentitySourceOutput;
+use aws_sdk_verifiedpermissions::Client;
+use tracing::{debug, instrument};
+
+use crate::private::sources::cache::identity_source::GetIdentitySourceOutputCache;
+use crate::private::sources::identity_source::error::IdentitySourceException;
+use crate::private::sources::identity_source::loader::ListIdentitySources;
+use crate::private::sources::identity_source::reader::{GetIdentitySource, GetIdentitySourceInput};
+use crate::private::sources::retry::BackoffStrategy;
+use crate::private::sources::{Cache, CacheChange, Load, Read};
+use crate::private::types::identity_source_id::IdentitySourceId;
+use crate::private::types::policy_selector::PolicySelector;
+
+/// The `VerifiedPermissionsIdentitySource` caches the most recent state of the remote verified
+/// permissions identity sources, only reading the identity sources that changed since the last
+/// `fetch`.
+#[derive(Debug)]
+pub struct VerifiedPermissionsIdentitySource {
+    /// A loader to list Identity Source Ids.
+    loader: ListIdentitySources,
+
+    /// A reader to fetch an Identity Source.
+    reader: GetIdentitySource,
+
+    /// A cache used to minimize API calls through `GetIdentitySource`.
+    cache: GetIdentitySourceOutputCache,
+}
+
+impl VerifiedPermissionsIdentitySource {
+    /// Constructs a new `VerifiedPermissionsIdentitySource` from a `Client`.
+    pub fn from(client: Client) -> Self {
+        Self {
+            loader: ListIdentitySources::new(client.clone()),
+            reader: GetIdentitySource::new(client, BackoffStrategy::default()),
+            cache: GetIdentitySourceOutputCache::new(),
+        }
+    }
+
+    /// Calls the AVP APIs `ListIdentitySources` and `GetIdentitySource` for the identity sources
+    /// that have been modified, and returns every identity source of the policy store.
+    #[instrument(skip(self), err(Debug))]
+    pub async fn fetch(
+        &mut self,
+        policy_selector: PolicySelector,
+    ) -> Result<HashMap<IdentitySourceId, GetIdentitySourceOutput>, IdentitySourceException> {
+        let identity_source_cache_diff_map = self
+            .cache
+            .get_pending_updates(&self.loader.load(policy_selector.clone()).await?);
+        for (identity_source_id, cache_change) in identity_source_cache_diff_map {
+            if cache_change == CacheChange::Deleted {
+                self.cache.remove(&identity_source_id);
+                debug!(
+                    "Removed Identity Source from Cache: identity_source_id={identity_source_id:?}"
+                );
+            } else {
+                let read_input = GetIdentitySourceInput::new(
+                    policy_selector.clone(),
+                    identity_source_id.clone(),
+                );
+                let identity_source_output = self.reader.read(read_input).await?;
+
+                self.cache
+                    .put(identity_source_id.clone(), identity_source_output);
+                debug!(
+                    "Updated Identity Source in Cache: identity_source_id={identity_source_id:?}"
+                );
+            }
+        }
+
+        Ok(self
+            .cache
+            .into_iter()
+            .map(|(identity_source_id, output)| (identity_source_id.clone(), output.clone()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::private::sources::identity_source::core::VerifiedPermissionsIdentitySource;
+    use crate::private::sources::test::{build_client, build_event, StatusCode};
+    use crate::private::types::identity_source_id::IdentitySourceId;
+    use crate::private::types::policy_selector::PolicySelector;
+
+    const LAST_UPDATED_DATE: &str = "2024-01-01T00:00:00Z";
+
+    pub const USER_POOL_ARN: &str =
+        "arn:aws:cognito-idp:us-east-1:123456789012:userpool/us-east-1_example";
+
+    // https://docs.aws.amazon.com/verifiedpermissions/latest/apireference/API_ListIdentitySources.html
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ListIdentitySourcesRequest {
+        #[serde(rename = "policyStoreId")]
+        pub policy_store_id: String,
+        #[serde(rename = "nextToken")]
+        pub next_token: Option<String>,
+    }
+
+    // https://docs.aws.amazon.com/verifiedpermissions/latest/apireference/API_ListIdentitySources.html
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ListIdentitySourcesResponse {
+        #[serde(rename = "nextToken")]
+        pub next_token: Option<String>,
+        #[serde(rename = "identitySources")]
+        pub identity_sources: Vec<IdentitySourceItemRaw>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct IdentitySourceDetailsRaw {
+        #[serde(rename = "clientIds")]
+        pub client_ids: Vec<String>,
+        #[serde(rename = "userPoolArn")]
+        pub user_pool_arn: Option<String>,
+        #[serde(rename = "openIdIssuer")]
+        pub open_id_issuer: Option<String>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct IdentitySourceItemRaw {
+        #[serde(rename = "createdDate")]
+        created_date: String,
+        details: IdentitySourceDetailsRaw,
+        #[serde(rename = "identitySourceId")]
+        identity_source_id: String,
+        #[serde(rename = "lastUpdatedDate")]
+        pub last_updated_date: String,
+        #[serde(rename = "policyStoreId")]
+        policy_store_id: String,
+        #[serde(rename = "principalEntityType")]
+        principal_entity_type: String,
+    }
+
+    // https://docs.aws.amazon.com/verifiedpermissions/latest/apireference/API_GetIdentitySource.html
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct GetIdentitySourceRequest {
+        #[serde(rename = "policyStoreId")]
+        pub policy_store_id: String,
+        #[serde(rename = "identitySourceId")]
+        pub identity_source_id: String,
+    }
+
+    // https://docs.aws.amazon.com/verifiedpermissions/latest/apireference/API_GetIdentitySource.html
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct GetIdentitySourceResponse {
+        #[serde(rename = "createdDate")]
+        created_date: String,
+        pub details: IdentitySourceDetailsRaw,
+        #[serde(rename = "identitySourceId")]
+        identity_source_id: String,
+        #[serde(rename = "lastUpdatedDate")]
+        pub last_updated_date: String,
+        #[serde(rename = "policyStoreId")]
+        policy_store_id: String,
+        #[serde(rename = "principalEntityType")]
+        pub principal_entity_type: String,
+    }
+
+    fn build_details() -> IdentitySourceDetailsRaw {
+        IdentitySourceDetailsRaw {
+            client_ids: vec!["client-1".to_string()],
+            user_pool_arn: Some(USER_POOL_ARN.to_string()),
+            open_id_issuer: Some("COGNITO".to_string()),
+        }
+    }
+
+    pub fn build_identity_source_item(
+        policy_selector: &PolicySelector,
+        identity_source_id: &IdentitySourceId,
+    ) -> IdentitySourceItemRaw {
+        IdentitySourceItemRaw {
+            created_date: LAST_UPDATED_DATE.to_string(),
+            details: build_details(),
+            identity_source_id: identity_source_id.to_string(),
+            last_updated_date: LAST_UPDATED_DATE.to_string(),
+            policy_store_id: policy_selector.id().to_string(),
+            principal_entity_type: "MyApp::User".to_string(),
+        }
+    }
+
+    pub fn build_get_identity_source_response(
+        policy_selector: &PolicySelector,
+        identity_source_id: &IdentitySourceId,
+    ) -> GetIdentitySourceResponse {
+        GetIdentitySourceResponse {
+            created_date: LAST_UPDATED_DATE.to_string(),
+            details: build_details(),
+            identity_source_id: identity_source_id.to_string(),
+            last_updated_date: LAST_UPDATED_DATE.to_string(),
+            policy_store_id: policy_selector.id().to_string(),
+            principal_entity_type: "MyApp::User".to_string(),
+        }
+    }
+
+    /// Builds the `ListIdentitySources` and `GetIdentitySource` events of a policy store with a
+    /// single Cognito identity source.
+    pub fn build_identity_source_events(
+        policy_selector: &PolicySelector,
+        identity_source_id: &IdentitySourceId,
+    ) -> Vec<aws_smithy_runtime::client::http::test_util::ReplayEvent> {
+        let list_request = ListIdentitySourcesRequest {
+            policy_store_id: policy_selector.id().to_string(),
+            next_token: None,
+        };
+        let list_response = ListIdentitySourcesResponse {
+            next_token: None,
+            identity_sources: vec![build_identity_source_item(
+                policy_selector,
+                identity_source_id,
+            )],
+        };
+        let get_request = GetIdentitySourceRequest {
+            policy_store_id: policy_selector.id().to_string(),
+            identity_source_id: identity_source_id.to_string(),
+        };
+        let get_response = build_get_identity_source_response(policy_selector, identity_source_id);
+
+        vec![
+            build_event(&list_request, &list_response, StatusCode::OK),
+            build_event(&get_request, &get_response, StatusCode::OK),
+        ]
+    }
+
+    #[tokio::test]
+    async fn fetch_reads_only_changed_identity_sources() {
+        let policy_selector = PolicySelector::from("ps-1".to_string());
+        let identity_source_id = IdentitySourceId("is-1".to_string());
+
+        let mut events = build_identity_source_events(&policy_selector, &identity_source_id);
+        // The second fetch only lists the unchanged identity source.
+        events.push(build_identity_source_events(&policy_selector, &identity_source_id).remove(0));
+        let client = build_client(events);
+
+        let mut source = VerifiedPermissionsIdentitySource::from(client);
+        for _ in 0..2 {
+            let identity_sources = source.fetch(policy_selector.clone()).await.unwrap();
+            assert_eq!(identity_sources.len(), 1);
+            assert_eq!(
+                identity_sources[&identity_source_id].principal_entity_type(),
+                "MyApp::User"
+            );
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Defines the enum for errors returned by the AWS Verified Permissions identity source reader and
+//! loader.
+use aws_sdk_verifiedpermissions::operation::get_identity_source::GetIdentitySourceError;
+use aws_sdk_verifiedpermissions::operation::list_identity_sources::ListIdentitySourcesError;
+use thiserror::Error;
+
+use crate::private::sources::identity_source::error::IdentitySourceException::{
+    AccessDenied, ResourceNotFound, Retryable, Unhandled, Validation,
+};
+
+/// The enum for errors returned by the AWS Verified Permissions identity source reader and loader.
+#[derive(Error, Debug)]
+pub enum IdentitySourceException {
+    /// The request failed because the user did not have the required permissions to perform
+    /// the action.
+    #[error("Amazon Verified Permissions Access Denied exception: {0}")]
+    AccessDenied(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// The request failed because one or more input parameters don't satisfy their constraint
+    /// requirements.
+    #[error("Invalid Input Exception: {0}")]
+    Validation(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// The request failed because the identity source does not exist in AVP.
+    #[error("Identity Source Id not found exception: {0}")]
+    ResourceNotFound(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// The request failed because an internal error occurred, or it exceeded a throttling quota.
+    /// Try again.
+    #[error("Retryable Exception: {0}")]
+    Retryable(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// An unexpected error occurred.
+    #[error("Internal Exception, something uncaught occurred: {0}")]
+    Unhandled(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+impl From<ListIdentitySourcesError> for IdentitySourceException {
+    fn from(error: ListIdentitySourcesError) -> Self {
+        match error {
+            ListIdentitySourcesError::ResourceNotFoundException(error) => {
+                ResourceNotFound(Box::new(error))
+            }
+            ListIdentitySourcesError::AccessDeniedException(error) => AccessDenied(Box::new(error)),
+            ListIdentitySourcesError::InternalServerException(error) => Retryable(Box::new(error)),
+            ListIdentitySourcesError::ThrottlingException(error) => Retryable(Box::new(error)),
+            ListIdentitySourcesError::ValidationException(error) => Validation(Box::new(error)),
+            _ => Unhandled(Box::new(error)),
+        }
+    }
+}
+
+impl From<GetIdentitySourceError> for IdentitySourceException {
+    fn from(error: GetIdentitySourceError) -> Self {
+        match error {
+            GetIdentitySourceError::ResourceNotFoundException(error) => {
+                ResourceNotFound(Box::new(error))
+            }
+            GetIdentitySourceError::AccessDeniedException(error) => AccessDenied(Box::new(error)),
+            GetIdentitySourceError::InternalServerException(error) => Retryable(Box::new(error)),
+            GetIdentitySourceError::ThrottlingException(error) => Retryable(Box::new(error)),
+            GetIdentitySourceError::ValidationException(error) => Validation(Box::new(error)),
+            _ => Unhandled(Box::new(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aws_sdk_verifiedpermissions::operation::get_identity_source::GetIdentitySourceError;
+    use aws_sdk_verifiedpermissions::operation::list_identity_sources::ListIdentitySourcesError;
+    use aws_sdk_verifiedpermissions::types::error::{
+        AccessDeniedException, ResourceNotFoundException, ThrottlingException,
+    };
+    use aws_sdk_verifiedpermissions::types::ResourceType;
+
+    use crate::private::sources::identity_source::error::IdentitySourceException;
+
+    const MESSAGE: &str = "dummy-message";
+
+    #[test]
+    fn from_list_identity_sources_error_access_denied_to_identity_source_error() {
+        assert_eq!(
+            IdentitySourceException::from(ListIdentitySourcesError::AccessDeniedException(
+                AccessDeniedException::builder()
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap(),
+            ))
+            .to_string(),
+            IdentitySourceException::AccessDenied(Box::new(
+                AccessDeniedException::builder()
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap()
+            ))
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn from_list_identity_sources_error_throttling_to_identity_source_error() {
+        assert_eq!(
+            IdentitySourceException::from(ListIdentitySourcesError::ThrottlingException(
+                ThrottlingException::builder()
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap(),
+            ))
+            .to_string(),
+            IdentitySourceException::Retryable(Box::new(
+                ThrottlingException::builder()
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap()
+            ))
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn from_get_identity_source_error_resource_not_found_to_identity_source_error() {
+        assert_eq!(
+            IdentitySourceException::from(GetIdentitySourceError::ResourceNotFoundException(
+                ResourceNotFoundException::builder()
+                    .resource_id("id")
+                    .resource_type(ResourceType::IdentitySource)
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap(),
+            ))
+            .to_string(),
+            IdentitySourceException::ResourceNotFound(Box::new(
+                ResourceNotFoundException::builder()
+                    .resource_id("id")
+                    .resource_type(ResourceType::IdentitySource)
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap(),
+            ))
+            .to_string()
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! This module implements the required functionality to list identity source ids from Amazon
+//! Verified Permissions.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::operation::list_identity_sources::ListIdentitySourcesOutput;
+use aws_sdk_verifiedpermissions::types::IdentitySourceItem;
+use aws_sdk_verifiedpermissions::Client;
+use aws_smithy_runtime_api::client::result::SdkError;
+use tracing::{debug, instrument};
+
+use crate::private::sources::identity_source::error::IdentitySourceException;
+use crate::private::sources::Load;
+use crate::private::types::identity_source_id::IdentitySourceId;
+use crate::private::types::policy_selector::PolicySelector;
+
+/// This structure implements the calls to Amazon Verified Permissions for retrieving a list of
+/// identity source ids. These identity source ids are required to query for the identity sources.
+#[derive(Debug, Clone)]
+pub struct ListIdentitySources {
+    avp_client: Client,
+}
+
+impl ListIdentitySources {
+    /// Create a new `ListIdentitySources` instance with the given client.
+    pub fn new(avp_client: Client) -> Self {
+        Self { avp_client }
+    }
+}
+
+#[async_trait]
+impl Load for ListIdentitySources {
+    type Input = PolicySelector;
+    type Output = HashMap<IdentitySourceId, IdentitySourceItem>;
+    type Exception = IdentitySourceException;
+
+    #[instrument(skip(self), err(Debug))]
+    async fn load(&self, policy_selector: Self::Input) -> Result<Self::Output, Self::Exception> {
+        let mut identity_source_ids_map = HashMap::new();
+
+        let mut client_results = self
+            .avp_client
+            .list_identity_sources()
+            .policy_store_id(policy_selector.id().to_string())
+            .into_paginator()
+            .send();
+
+        while let Some(page) = client_results.next().await {
+            let page: ListIdentitySourcesOutput = page.map_err(SdkError::into_service_error)?;
+
+            for identity_source_item in page.identity_sources {
+                identity_source_ids_map.insert(
+                    IdentitySourceId(identity_source_item.identity_source_id.clone()),
+                    identity_source_item,
+                );
+            }
+        }
+        debug!(
+            "Loaded all Identity Sources from Policy Store: identity_source_ids={:?}",
+            identity_source_ids_map.keys().collect::<Vec<_>>()
+        );
+        Ok(identity_source_ids_map)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::private::sources::identity_source::core::test::{
+        build_identity_source_item, ListIdentitySourcesRequest, ListIdentitySourcesResponse,
+    };
+    use crate::private::sources::identity_source::loader::{ListIdentitySources, Load};
+    use crate::private::sources::test::{build_client, build_empty_event, build_event, StatusCode};
+    use crate::private::types::identity_source_id::IdentitySourceId;
+    use crate::private::types::policy_selector::PolicySelector;
+
+    #[tokio::test]
+    async fn list_identity_sources_200() {
+        let policy_selector = PolicySelector::from("mockPolicyStore".to_string());
+        let identity_source_id = IdentitySourceId("mockIdentitySourceId".to_string());
+
+        let request = ListIdentitySourcesRequest {
+            policy_store_id: policy_selector.id().to_string(),
+            next_token: None,
+        };
+        let response = ListIdentitySourcesResponse {
+            next_token: None,
+            identity_sources: vec![build_identity_source_item(
+                &policy_selector,
+                &identity_source_id,
+            )],
+        };
+
+        let client = build_client(vec![build_event(&request, &response, StatusCode::OK)]);
+        let results = ListIdentitySources::new(client)
+            .load(policy_selector)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results.contains_key(&identity_source_id));
+    }
+
+    #[tokio::test]
+    async fn list_identity_sources_400() {
+        let policy_selector = PolicySelector::from("mockPolicyStore".to_string());
+        let request = ListIdentitySourcesRequest {
+            policy_store_id: policy_selector.id().to_string(),
+            next_token: None,
+        };
+
+        let client = build_client(vec![build_empty_event(&request, StatusCode::BAD_REQUEST)]);
+        let result = ListIdentitySources::new(client).load(policy_selector).await;
+        assert!(result.is_err());
+    }
+}
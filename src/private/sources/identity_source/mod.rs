@@ -0,0 +1,5 @@
+//! Implements an identity source source for Amazon Verified Permissions.
+pub mod core;
+pub mod error;
+pub mod loader;
+pub mod reader;
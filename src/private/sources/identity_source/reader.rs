@@ -0,0 +1,157 @@
+//! This module implements the required functionality to read an identity source from Amazon
+//! Verified Permissions.
+use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::operation::get_identity_source::{
+    GetIdentitySourceError, GetIdentitySourceOutput,
+};
+use aws_sdk_verifiedpermissions::Client;
+use aws_smithy_runtime_api::client::result::SdkError;
+use backon::Retryable;
+use tracing::instrument;
+
+use crate::private::sources::identity_source::error::IdentitySourceException;
+use crate::private::sources::retry::BackoffStrategy;
+use crate::private::sources::Read;
+use crate::private::types::identity_source_id::IdentitySourceId;
+use crate::private::types::policy_selector::PolicySelector;
+
+/// This structure implements the calls to Amazon Verified Permissions for retrieving an identity
+/// source.
+#[derive(Debug)]
+pub struct GetIdentitySource {
+    /// Provides a `Client` to fetch identity sources from AVP.
+    avp_client: Client,
+    /// `BackoffStrategy` defines how we will perform retries with exponential backoff
+    backoff_strategy: BackoffStrategy,
+}
+
+impl GetIdentitySource {
+    /// Create a new `GetIdentitySource` instance
+    pub fn new(avp_client: Client, backoff_strategy: BackoffStrategy) -> Self {
+        Self {
+            avp_client,
+            backoff_strategy,
+        }
+    }
+
+    async fn get_identity_source(
+        &self,
+        identity_source_id: &String,
+        policy_store_id: &String,
+    ) -> Result<GetIdentitySourceOutput, GetIdentitySourceError> {
+        let get_identity_source_operation = || async {
+            let get_identity_source_result = self
+                .avp_client
+                .get_identity_source()
+                .identity_source_id(identity_source_id)
+                .policy_store_id(policy_store_id)
+                .send()
+                .await
+                .map_err(SdkError::into_service_error)?;
+            Ok(get_identity_source_result)
+        };
+        get_identity_source_operation
+            .retry(self.backoff_strategy.get_backoff())
+            .await
+    }
+}
+
+/// Input required for the AVP `GetIdentitySource` operation.
+#[derive(Debug, Clone)]
+pub struct GetIdentitySourceInput {
+    /// The policy store id
+    pub policy_selector: PolicySelector,
+    /// The identity source id
+    pub identity_source_id: IdentitySourceId,
+}
+
+impl GetIdentitySourceInput {
+    /// Create a new `GetIdentitySourceInput` instance with the given policy store id and identity
+    /// source id.
+    pub fn new(policy_selector: PolicySelector, identity_source_id: IdentitySourceId) -> Self {
+        Self {
+            policy_selector,
+            identity_source_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Read for GetIdentitySource {
+    type Input = GetIdentitySourceInput;
+    type Output = GetIdentitySourceOutput;
+    type Exception = IdentitySourceException;
+
+    #[instrument(skip(self), err(Debug))]
+    async fn read(&self, input: Self::Input) -> Result<Self::Output, Self::Exception> {
+        Ok(self
+            .get_identity_source(
+                &input.identity_source_id.to_string(),
+                &input.policy_selector.id().to_string(),
+            )
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::private::sources::identity_source::core::test::{
+        build_get_identity_source_response, GetIdentitySourceRequest,
+    };
+    use crate::private::sources::identity_source::reader::{
+        GetIdentitySource, GetIdentitySourceInput,
+    };
+    use crate::private::sources::retry::BackoffStrategy;
+    use crate::private::sources::test::{build_client, build_empty_event, build_event, StatusCode};
+    use crate::private::sources::Read;
+    use crate::private::types::identity_source_id::IdentitySourceId;
+    use crate::private::types::policy_selector::PolicySelector;
+
+    #[tokio::test]
+    async fn get_identity_source_200() {
+        let policy_selector = PolicySelector::from("ps-1".to_string());
+        let identity_source_id = IdentitySourceId("is-1".to_string());
+
+        let request = GetIdentitySourceRequest {
+            policy_store_id: policy_selector.id().to_string(),
+            identity_source_id: identity_source_id.to_string(),
+        };
+        let response = build_get_identity_source_response(&policy_selector, &identity_source_id);
+
+        let client = build_client(vec![build_event(&request, &response, StatusCode::OK)]);
+        let reader = GetIdentitySource::new(client, BackoffStrategy::default());
+        let result = reader
+            .read(GetIdentitySourceInput::new(
+                policy_selector,
+                identity_source_id.clone(),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(result.identity_source_id, identity_source_id.to_string());
+        assert_eq!(
+            result.details().unwrap().user_pool_arn(),
+            response.details.user_pool_arn.as_deref()
+        );
+    }
+
+    #[tokio::test]
+    async fn get_identity_source_400() {
+        let policy_selector = PolicySelector::from("ps-1".to_string());
+        let identity_source_id = IdentitySourceId("is-1".to_string());
+        let request = GetIdentitySourceRequest {
+            policy_store_id: policy_selector.id().to_string(),
+            identity_source_id: identity_source_id.to_string(),
+        };
+
+        let client = build_client(vec![build_empty_event(&request, StatusCode::BAD_REQUEST)]);
+        let reader = GetIdentitySource::new(client, BackoffStrategy::default());
+        let result = reader
+            .read(GetIdentitySourceInput::new(
+                policy_selector,
+                identity_source_id,
+            ))
+            .await;
+        assert!(result.is_err());
+    }
+}
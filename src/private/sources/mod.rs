@@ -2,13 +2,16 @@
 use async_trait::async_trait;
 
 pub mod cache;
+#[cfg(feature = "token")]
+pub mod identity_source;
 pub mod policy;
+pub mod policy_store;
 mod retry;
 pub mod schema;
 pub mod template;
 
 /// Type values for cache changes
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum CacheChange {
     /// `Created` indicates a new cache item was created
     Created,
@@ -18,6 +21,17 @@ pub enum CacheChange {
     Deleted,
 }
 
+/// A cache change that has been computed against the remote policy store but not applied to the
+/// local cache.
+#[derive(Debug)]
+pub struct PendingChange<E> {
+    /// The change that would be applied to the cache item
+    pub change: CacheChange,
+    /// The error raised when translating the changed item to Cedar, only set when the item was
+    /// fetched for validation
+    pub error: Option<E>,
+}
+
 /// `Load` trait for AVP callers to retrieve lists of policy store data
 #[async_trait]
 pub trait Load {
@@ -36,7 +50,7 @@ pub trait Load {
 pub trait Read {
     /// `Input` id of policy store data
     type Input;
-    /// `Output` data value of "GetOutput" types retrieved with reader such as `GetPolicyOutput`
+    /// `Output` data value of "`GetOutput`" types retrieved with reader such as `GetPolicyOutput`
     type Output;
     /// `Exception` AVP error types mapped to a reader exception
     type Exception;
@@ -55,10 +69,10 @@ pub trait Cache {
     type Key;
     /// `Value` data of caches with types from response of AVP read calls such as from the policy reader
     type Value;
-    /// `LoadedItems` HashMap of id, value pairings of `Key` and cache item types from
+    /// `LoadedItems` `HashMap` of id, value pairings of `Key` and cache item types from
     /// AVP load calls such as `ListPolicies` returning `PolicyItem`
     type LoadedItems;
-    /// `PendingUpdates` HashMap of id, value pairings of `Key` and `CacheChange` as a reference for which
+    /// `PendingUpdates` `HashMap` of id, value pairings of `Key` and `CacheChange` as a reference for which
     /// cache values need updates
     type PendingUpdates;
 
@@ -76,12 +90,12 @@ pub trait Cache {
     fn remove(&mut self, key: &Self::Key) -> Option<Self::Value>;
 
     /// The function responsible for cross checking the values of current cache and returning
-    /// a HashMap of values that require an update
+    /// a `HashMap` of values that require an update
     fn get_pending_updates(&self, ids_map: &Self::LoadedItems) -> Self::PendingUpdates;
 }
 
 #[cfg(test)]
-mod test {
+pub mod test {
     use aws_credential_types::Credentials;
     use aws_sdk_verifiedpermissions::{Client, Config};
     use aws_smithy_runtime::client::http::test_util::{ReplayEvent, StaticReplayClient};
@@ -4,6 +4,7 @@
 use std::collections::HashMap;
 
 use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::operation::get_policy::GetPolicyOutput;
 use aws_sdk_verifiedpermissions::types::PolicyDefinitionDetail;
 use aws_sdk_verifiedpermissions::Client;
 use tracing::{debug, instrument};
@@ -15,7 +16,7 @@ use crate::private::sources::policy::{
     reader::{GetPolicy, GetPolicyInput},
 };
 use crate::private::sources::retry::BackoffStrategy;
-use crate::private::sources::{Cache, CacheChange, Load, Read};
+use crate::private::sources::{Cache, CacheChange, Load, PendingChange, Read};
 use crate::private::translator::avp_to_cedar::Policy;
 use crate::private::types::policy_id::PolicyId;
 use crate::private::types::policy_selector::PolicySelector;
@@ -57,6 +58,9 @@ pub struct VerifiedPermissionsPolicySource {
 
     /// A cache used to minimize API calls to `GetPolicies`.
     cache: GetPolicyOutputCache,
+
+    /// The cached values the last `fetch` replaced or removed, restored by `revert_fetch`.
+    replaced: HashMap<PolicyId, Option<GetPolicyOutput>>,
 }
 
 impl VerifiedPermissionsPolicySource {
@@ -66,10 +70,90 @@ impl VerifiedPermissionsPolicySource {
             loader: ListPolicies::new(client.clone()),
             reader: GetPolicy::new(client, BackoffStrategy::default()),
             cache: GetPolicyOutputCache::new(),
+            replaced: HashMap::new(),
+        }
+    }
+
+    /// Forgets the cache entries changed by the last `fetch`, whose policies are served.
+    pub fn accept_fetch(&mut self) {
+        self.replaced.clear();
+    }
+
+    /// Restores the cache entries changed by the last `fetch`, when the fetched policies are not
+    /// served.
+    pub fn revert_fetch(&mut self) {
+        for (policy_id, policy_output) in self.replaced.drain() {
+            match policy_output {
+                Some(policy_output) => self.cache.put(policy_id, policy_output),
+                None => self.cache.remove(&policy_id),
+            };
+        }
+    }
+
+    /// Returns the cached `GetPolicy` output of a policy, if it was fetched by a previous `fetch`.
+    pub fn cached_policy(&self, policy_id: &PolicyId) -> Option<&GetPolicyOutput> {
+        self.cache.get(policy_id)
+    }
+
+    /// Lists, reads and translates the policies selected by `policy_selector` without using or
+    /// updating the cache, so that concurrent loads can share the source.
+    #[instrument(skip(self), err(Debug))]
+    pub async fn load(
+        &self,
+        policy_selector: PolicySelector,
+    ) -> Result<HashMap<PolicyId, Policy>, PolicySourceException> {
+        let mut policies = HashMap::new();
+        for policy_id in self.loader.load(policy_selector.clone()).await?.into_keys() {
+            let read_input = GetPolicyInput::new(policy_selector.clone(), policy_id.clone());
+            let policy_output = self.reader.read(read_input).await?;
+            policies.insert(policy_id, translate(&policy_output)?);
+        }
+        Ok(policies)
+    }
+
+    /// Computes the changes the next `fetch` would apply to the cache without modifying it. When
+    /// `validate` is set, created and updated policies are read from AVP and translated to Cedar,
+    /// and any translation failure is recorded on the pending change.
+    #[instrument(skip(self), err(Debug))]
+    pub async fn preview(
+        &self,
+        policy_selector: PolicySelector,
+        validate: bool,
+    ) -> Result<HashMap<PolicyId, PendingChange<PolicySourceException>>, PolicySourceException>
+    {
+        let mut pending_changes = HashMap::new();
+
+        let policy_cache_diff_map = self
+            .cache
+            .get_pending_updates(&self.loader.load(policy_selector.clone()).await?);
+        for (policy_id, change) in policy_cache_diff_map {
+            let error = if validate && change != CacheChange::Deleted {
+                let read_input = GetPolicyInput::new(policy_selector.clone(), policy_id.clone());
+                let policy_output = self.reader.read(read_input).await?;
+                translate(&policy_output).err()
+            } else {
+                None
+            };
+            pending_changes.insert(policy_id, PendingChange { change, error });
         }
+        debug!("Previewed Policy Cache Changes: pending_changes={pending_changes:?}");
+        Ok(pending_changes)
     }
 }
 
+/// Translates a cached `GetPolicyOutput` to a Cedar `Policy`.
+fn translate(policy_output: &GetPolicyOutput) -> Result<Policy, PolicySourceException> {
+    let definition = policy_output
+        .definition
+        .as_ref()
+        .ok_or_else(PolicySourceException::PolicyDefinitionNotFound)?;
+
+    Ok(Policy::try_from(PolicyDefinition {
+        policy_id: policy_output.policy_id.clone(),
+        detail: definition.clone(),
+    })?)
+}
+
 /// Implements `PolicySource`.
 #[async_trait]
 impl PolicySource for VerifiedPermissionsPolicySource {
@@ -81,6 +165,7 @@ impl PolicySource for VerifiedPermissionsPolicySource {
         policy_selector: PolicySelector,
     ) -> Result<HashMap<PolicyId, Policy>, Self::Error> {
         let mut policy_definitions_map = HashMap::new();
+        self.replaced.clear();
 
         // Load policies and update policy cache
         let policy_cache_diff_map = self
@@ -88,27 +173,21 @@ impl PolicySource for VerifiedPermissionsPolicySource {
             .get_pending_updates(&self.loader.load(policy_selector.clone()).await?);
         for (policy_id, cache_change) in policy_cache_diff_map {
             if cache_change == CacheChange::Deleted {
-                self.cache.remove(&policy_id);
+                let previous = self.cache.remove(&policy_id);
+                self.replaced.entry(policy_id.clone()).or_insert(previous);
                 debug!("Removed Policy from Cache: policy_id={policy_id:?}");
             } else {
                 let read_input = GetPolicyInput::new(policy_selector.clone(), policy_id.clone());
                 let policy_output = self.reader.read(read_input).await?;
 
-                self.cache.put(policy_id.clone(), policy_output);
+                let previous = self.cache.put(policy_id.clone(), policy_output);
+                self.replaced.entry(policy_id.clone()).or_insert(previous);
                 debug!("Updated Policy in Cache: policy_id={policy_id:?}");
             }
         }
 
         for (policy_id, policy_output) in &mut self.cache {
-            let definition = policy_output
-                .definition
-                .as_ref()
-                .ok_or_else(PolicySourceException::PolicyDefinitionNotFound)?;
-
-            let cedar_policy = Policy::try_from(PolicyDefinition {
-                policy_id: policy_output.policy_id.clone(),
-                detail: definition.clone(),
-            })?;
+            let cedar_policy = translate(policy_output)?;
 
             policy_definitions_map.insert(policy_id.clone(), cedar_policy);
             debug!("Fetched Policy: policy_id={policy_id:?}");
@@ -132,8 +211,9 @@ pub mod test {
     use crate::private::sources::policy::core::{
         PolicyDefinition, PolicySource, VerifiedPermissionsPolicySource,
     };
+    use crate::private::sources::policy::error::PolicySourceException;
     use crate::private::sources::test::{build_client, build_event, StatusCode};
-    use crate::private::sources::Cache;
+    use crate::private::sources::{Cache, CacheChange};
     use crate::private::translator::avp_to_cedar::Policy;
     use crate::private::types::policy_id::PolicyId;
     use crate::private::types::policy_selector::PolicySelector;
@@ -529,4 +609,81 @@ pub mod test {
             Policy::try_from(template_linked_definition).unwrap()
         );
     }
+
+    #[tokio::test]
+    async fn test_policy_source_preview_reports_changes_without_updating_cache() {
+        let policy_selector: PolicySelector = PolicySelector::from("mockPolicyStoreId".to_string());
+        let policy_id_1 = PolicyId("mockPolicyId1".to_string());
+        let policy_id_2 = PolicyId("mockPolicyId2".to_string());
+        let policy_type = "STATIC";
+
+        let loader_request = ListPoliciesRequest {
+            policy_store_id: policy_selector.id().to_string(),
+            next_token: None,
+            max_results: 1,
+            filter: None,
+        };
+
+        let loader_response = ListPoliciesResponse {
+            policies: Some(vec![build_policy_item(
+                &policy_id_1,
+                &policy_selector,
+                Some(policy_type.to_string()),
+                None,
+                None,
+                None,
+            )]),
+            next_token: None,
+        };
+
+        let reader_request = GetPolicyRequest {
+            policy_id: policy_id_1.to_string(),
+            policy_store_id: policy_selector.id().to_string(),
+        };
+
+        let reader_response = build_get_policy_response(
+            &policy_id_1,
+            &policy_selector,
+            policy_type,
+            build_entity_identifier(PRINCIPAL_ENTITY_TYPE, PRINCIPAL_ENTITY_ID),
+            build_entity_identifier(RESOURCE_ENTITY_TYPE, RESOURCE_ENTITY_ID),
+            PolicyDefinitionDetailRaw::Static(StaticPolicyDefinitionDetailRaw {
+                description: None,
+                statement: Some("permit(principal, action,".to_string()),
+            }),
+        );
+
+        let client = build_client(vec![
+            build_event(&loader_request, &loader_response, StatusCode::OK),
+            build_event(&reader_request, &reader_response, StatusCode::OK),
+        ]);
+
+        let deleted_output = GetPolicyOutput::builder()
+            .policy_store_id(policy_selector.id().to_string())
+            .policy_id(policy_id_2.to_string())
+            .policy_type(PolicyType::Static)
+            .created_date(DateTime::from_secs(0))
+            .last_updated_date(DateTime::from_secs(0))
+            .build()
+            .unwrap();
+
+        let mut policy_source = VerifiedPermissionsPolicySource::from(client);
+        policy_source.cache.put(policy_id_2.clone(), deleted_output);
+
+        let result = policy_source.preview(policy_selector, true).await.unwrap();
+
+        assert_eq!(result.len(), 2);
+        let created = result.get(&policy_id_1).unwrap();
+        assert_eq!(created.change, CacheChange::Created);
+        assert!(matches!(
+            created.error,
+            Some(PolicySourceException::TranslatorException(..))
+        ));
+        let deleted = result.get(&policy_id_2).unwrap();
+        assert_eq!(deleted.change, CacheChange::Deleted);
+        assert!(deleted.error.is_none());
+
+        assert!(policy_source.cache.get(&policy_id_1).is_none());
+        assert!(policy_source.cache.get(&policy_id_2).is_some());
+    }
 }
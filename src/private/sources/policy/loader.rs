@@ -28,7 +28,7 @@ impl ListPolicies {
 
 #[async_trait]
 impl Load for ListPolicies {
-    /// Returns a HashMap of `PolicyId`s and `PolicyItem`s that represent all policies stored in
+    /// Returns a `HashMap` of `PolicyId`s and `PolicyItem`s that represent all policies stored in
     /// the specified policy store with the given `PolicySelector`.
     type Input = PolicySelector;
     type Output = HashMap<PolicyId, PolicyItem>;
@@ -0,0 +1,97 @@
+//! Defines the enum for errors returned by the AWS Verified Permissions policy store reader
+use crate::private::sources::policy_store::error::PolicyStoreException::{
+    AccessDenied, ResourceNotFound, Retryable, Unhandled, Validation,
+};
+use aws_sdk_verifiedpermissions::operation::get_policy_store::GetPolicyStoreError;
+use thiserror::Error;
+
+/// The enum for errors returned by the AWS Verified Permissions policy store reader.
+#[derive(Error, Debug)]
+pub enum PolicyStoreException {
+    /// The request failed because the user did not have the required permissions to perform
+    /// the action.
+    #[error("Amazon Verified Permissions Access Denied exception: {0}")]
+    AccessDenied(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// The request failed because one or more input parameters don't satisfy their constraint
+    /// requirements.
+    #[error("Invalid Input Exception: {0}")]
+    Validation(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// The request failed because the policy store does not exist in AVP.
+    #[error("Policy store not found exception: {0}")]
+    ResourceNotFound(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// The request failed because an internal error occurred, or it exceeded a throttling quota.
+    /// Try again.
+    #[error("Retryable Exception: {0}")]
+    Retryable(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// An unexpected error occurred.
+    #[error("Internal Exception, something uncaught occurred: {0}")]
+    Unhandled(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+impl From<GetPolicyStoreError> for PolicyStoreException {
+    fn from(error: GetPolicyStoreError) -> Self {
+        match error {
+            GetPolicyStoreError::ResourceNotFoundException(error) => {
+                ResourceNotFound(Box::new(error))
+            }
+            GetPolicyStoreError::AccessDeniedException(error) => AccessDenied(Box::new(error)),
+            GetPolicyStoreError::InternalServerException(error) => Retryable(Box::new(error)),
+            GetPolicyStoreError::ThrottlingException(error) => Retryable(Box::new(error)),
+            GetPolicyStoreError::ValidationException(error) => Validation(Box::new(error)),
+            _ => Unhandled(Box::new(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::private::sources::policy_store::error::PolicyStoreException;
+    use aws_sdk_verifiedpermissions::operation::get_policy_store::GetPolicyStoreError;
+    use aws_sdk_verifiedpermissions::types::error::{
+        AccessDeniedException, ResourceNotFoundException, ThrottlingException,
+    };
+    use aws_sdk_verifiedpermissions::types::ResourceType;
+
+    const MESSAGE: &str = "dummy-message";
+
+    #[test]
+    fn from_get_policy_store_error_resource_not_found_to_policy_store_exception() {
+        assert!(matches!(
+            PolicyStoreException::from(GetPolicyStoreError::ResourceNotFoundException(
+                ResourceNotFoundException::builder()
+                    .resource_id("id")
+                    .resource_type(ResourceType::PolicyStore)
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap(),
+            )),
+            PolicyStoreException::ResourceNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn from_get_policy_store_error_access_denied_to_policy_store_exception() {
+        assert!(matches!(
+            PolicyStoreException::from(GetPolicyStoreError::AccessDeniedException(
+                AccessDeniedException::builder()
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap()
+            )),
+            PolicyStoreException::AccessDenied(_)
+        ));
+    }
+
+    #[test]
+    fn from_get_policy_store_error_throttling_to_policy_store_exception() {
+        assert!(matches!(
+            PolicyStoreException::from(GetPolicyStoreError::ThrottlingException(
+                ThrottlingException::builder()
+                    .message(MESSAGE)
+                    .build()
+                    .unwrap()
+            )),
+            PolicyStoreException::Retryable(_)
+        ));
+    }
+}
@@ -0,0 +1,3 @@
+//! Reads the settings of an Amazon Verified Permissions policy store.
+pub mod error;
+pub mod reader;
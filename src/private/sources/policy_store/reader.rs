@@ -0,0 +1,158 @@
+//! This module implements the required functionality to read the settings of a specific
+//! Amazon Verified Permissions Policy Store.
+use crate::private::sources::policy_store::error::PolicyStoreException;
+use crate::private::sources::retry::BackoffStrategy;
+use crate::private::sources::Read;
+use crate::private::types::policy_selector::PolicySelector;
+use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::operation::get_policy_store::{
+    GetPolicyStoreError, GetPolicyStoreOutput,
+};
+use aws_sdk_verifiedpermissions::Client;
+use aws_smithy_runtime_api::client::result::SdkError;
+use backon::Retryable;
+use tracing::instrument;
+
+/// This structure implements the calls to Amazon Verified Permissions for retrieving the policy
+/// store.
+#[derive(Debug)]
+pub struct GetPolicyStore {
+    /// Provides a `Client` to fetch the policy store from AVP.
+    avp_client: Client,
+    /// `BackoffStrategy` defines how we will perform retries with exponential backoff
+    backoff_strategy: BackoffStrategy,
+}
+
+impl GetPolicyStore {
+    /// Create a new `GetPolicyStore` instance
+    pub fn new(avp_client: Client, backoff_strategy: BackoffStrategy) -> Self {
+        Self {
+            avp_client,
+            backoff_strategy,
+        }
+    }
+
+    /// Create a new `GetPolicyStore` instance with the default `BackoffStrategy`
+    pub fn from(avp_client: Client) -> Self {
+        Self::new(avp_client, BackoffStrategy::default())
+    }
+
+    async fn get_policy_store(
+        &self,
+        policy_store_id: &String,
+    ) -> Result<GetPolicyStoreOutput, GetPolicyStoreError> {
+        let get_policy_store_operation = || async {
+            let get_policy_store_result = self
+                .avp_client
+                .get_policy_store()
+                .policy_store_id(policy_store_id)
+                .send()
+                .await
+                .map_err(SdkError::into_service_error)?;
+            Ok(get_policy_store_result)
+        };
+
+        get_policy_store_operation
+            .retry(self.backoff_strategy.get_backoff())
+            .await
+    }
+}
+
+#[async_trait]
+impl Read for GetPolicyStore {
+    type Input = PolicySelector;
+    type Output = GetPolicyStoreOutput;
+    type Exception = PolicyStoreException;
+
+    #[instrument(skip(self), err(Debug))]
+    async fn read(&self, policy_selector: Self::Input) -> Result<Self::Output, Self::Exception> {
+        Ok(self
+            .get_policy_store(&policy_selector.id().to_string())
+            .await?)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use crate::private::sources::policy_store::reader::GetPolicyStore;
+    use crate::private::sources::retry::BackoffStrategy;
+    use crate::private::sources::test::{build_client, build_empty_event, build_event, StatusCode};
+    use crate::private::sources::Read;
+    use crate::private::types::policy_selector::PolicySelector;
+    use aws_sdk_verifiedpermissions::types::ValidationMode;
+    use aws_smithy_runtime::client::http::test_util::ReplayEvent;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct GetPolicyStoreRequest {
+        #[serde(rename = "policyStoreId")]
+        pub policy_store_id: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ValidationSettingsRaw {
+        pub mode: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct GetPolicyStoreResponse {
+        #[serde(rename = "policyStoreId")]
+        pub policy_store_id: String,
+        pub arn: String,
+        #[serde(rename = "validationSettings")]
+        pub validation_settings: ValidationSettingsRaw,
+        #[serde(rename = "createdDate")]
+        pub created_date: String,
+        #[serde(rename = "lastUpdatedDate")]
+        pub last_updated_date: String,
+    }
+
+    /// Builds a `GetPolicyStore` event returning the policy store with the given validation mode.
+    pub fn build_policy_store_event(policy_store_id: &str, mode: &str) -> ReplayEvent {
+        build_event(
+            &GetPolicyStoreRequest {
+                policy_store_id: policy_store_id.to_string(),
+            },
+            &GetPolicyStoreResponse {
+                policy_store_id: policy_store_id.to_string(),
+                arn: format!(
+                    "arn:aws:verifiedpermissions::123456789012:policy-store/{policy_store_id}"
+                ),
+                validation_settings: ValidationSettingsRaw {
+                    mode: mode.to_string(),
+                },
+                created_date: "2024-01-01T00:00:00Z".to_string(),
+                last_updated_date: "2024-01-01T00:00:00Z".to_string(),
+            },
+            StatusCode::OK,
+        )
+    }
+
+    #[tokio::test]
+    async fn get_policy_store_200() {
+        let policy_selector = PolicySelector::from("ps-1".to_string());
+        let client = build_client(vec![build_policy_store_event("ps-1", "STRICT")]);
+
+        let policy_store_reader = GetPolicyStore::new(client, BackoffStrategy::default());
+        let result = policy_store_reader.read(policy_selector).await.unwrap();
+
+        assert_eq!(result.policy_store_id, "ps-1");
+        assert_eq!(
+            result.validation_settings.unwrap().mode,
+            ValidationMode::Strict
+        );
+    }
+
+    #[tokio::test]
+    async fn get_policy_store_400() {
+        let policy_selector = PolicySelector::from("ps-1".to_string());
+        let request = GetPolicyStoreRequest {
+            policy_store_id: policy_selector.id().to_string(),
+        };
+        let client = build_client(vec![build_empty_event(&request, StatusCode::BAD_REQUEST)]);
+
+        let policy_store_reader = GetPolicyStore::new(client, BackoffStrategy::default());
+        let result = policy_store_reader.read(policy_selector).await;
+        assert!(result.is_err());
+    }
+}
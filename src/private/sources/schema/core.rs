@@ -1,6 +1,9 @@
 //! Exposes a `SchemaSource` trait and up the disjoint policy cases. This also exposes an
 //! implementation using Verified Permissions API calls.
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::primitives::DateTime;
 use aws_sdk_verifiedpermissions::Client;
 use tracing::{debug, instrument};
 
@@ -27,12 +30,23 @@ pub trait SchemaSource {
     ) -> Result<cedar_policy::Schema, Self::Error>;
 }
 
+/// The last parsed schema of a policy store with the `last_updated_date` it was fetched with.
+#[derive(Debug, Clone)]
+pub struct CachedSchema {
+    /// The `last_updated_date` of the schema in Amazon Verified Permissions.
+    pub last_updated_date: DateTime,
+    /// The parsed schema.
+    pub schema: Arc<cedar_policy::Schema>,
+}
+
 /// The `VerifiedPermissionsSchemaSource` is responsible for fetching remote verified
 /// permissions Schema scoped to a Policy Store and providing a `cedar_policy::Schema`.
 #[derive(Debug)]
 pub struct VerifiedPermissionsSchemaSource {
     /// A reader to fetch a Policy Schema from a remote Policy Store.
     pub reader: GetSchema,
+    /// The last parsed schema, reused while its `last_updated_date` is unchanged.
+    pub cache: Option<CachedSchema>,
 }
 
 impl VerifiedPermissionsSchemaSource {
@@ -40,6 +54,7 @@ impl VerifiedPermissionsSchemaSource {
     pub fn from(client: Client) -> Self {
         Self {
             reader: GetSchema::new(client, BackoffStrategy::default()),
+            cache: None,
         }
     }
 }
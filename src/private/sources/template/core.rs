@@ -6,13 +6,14 @@ use crate::private::sources::template::{
     loader::ListPolicyTemplates,
     reader::{GetPolicyTemplate, GetPolicyTemplateInput},
 };
-use crate::private::sources::{Cache, CacheChange, Load, Read};
+use crate::private::sources::{Cache, CacheChange, Load, PendingChange, Read};
 use crate::private::translator::avp_to_cedar::Template;
 use crate::private::types::policy_selector::PolicySelector;
 use crate::private::types::template_id::TemplateId;
 
 use crate::private::sources::retry::BackoffStrategy;
 use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::operation::get_policy_template::GetPolicyTemplateOutput;
 use aws_sdk_verifiedpermissions::Client;
 use std::collections::HashMap;
 use tracing::{debug, instrument};
@@ -45,6 +46,9 @@ pub struct VerifiedPermissionsTemplateSource {
 
     /// A cache used to minimize API calls through `GetPolicyTemplate`.
     cache: GetPolicyTemplateOutputCache,
+
+    /// The cached values the last `fetch` replaced or removed, restored by `revert_fetch`.
+    replaced: HashMap<TemplateId, Option<GetPolicyTemplateOutput>>,
 }
 
 impl VerifiedPermissionsTemplateSource {
@@ -54,7 +58,62 @@ impl VerifiedPermissionsTemplateSource {
             loader: ListPolicyTemplates::new(client.clone()),
             reader: GetPolicyTemplate::new(client, BackoffStrategy::default()),
             cache: GetPolicyTemplateOutputCache::new(),
+            replaced: HashMap::new(),
+        }
+    }
+
+    /// Forgets the cache entries changed by the last `fetch`, whose templates are served.
+    pub fn accept_fetch(&mut self) {
+        self.replaced.clear();
+    }
+
+    /// Restores the cache entries changed by the last `fetch`, when the fetched templates are not
+    /// served.
+    pub fn revert_fetch(&mut self) {
+        for (template_id, template_output) in self.replaced.drain() {
+            match template_output {
+                Some(template_output) => self.cache.put(template_id, template_output),
+                None => self.cache.remove(&template_id),
+            };
+        }
+    }
+
+    /// Returns the cached `GetPolicyTemplate` output of a template, if it was fetched by a previous
+    /// `fetch`.
+    pub fn cached_template(&self, template_id: &TemplateId) -> Option<&GetPolicyTemplateOutput> {
+        self.cache.get(template_id)
+    }
+
+    /// Computes the changes the next `fetch` would apply to the cache without modifying it. When
+    /// `validate` is set, created and updated templates are read from AVP and translated to Cedar,
+    /// and any translation failure is recorded on the pending change.
+    #[instrument(skip(self), err(Debug))]
+    pub async fn preview(
+        &self,
+        policy_selector: PolicySelector,
+        validate: bool,
+    ) -> Result<HashMap<TemplateId, PendingChange<TemplateSourceException>>, TemplateSourceException>
+    {
+        let mut pending_changes = HashMap::new();
+
+        let template_cache_diff_map = self
+            .cache
+            .get_pending_updates(&self.loader.load(policy_selector.clone()).await?);
+        for (template_id, change) in template_cache_diff_map {
+            let error = if validate && change != CacheChange::Deleted {
+                let read_input =
+                    GetPolicyTemplateInput::new(policy_selector.clone(), template_id.clone());
+                let template_output = self.reader.read(read_input).await?;
+                Template::try_from(template_output)
+                    .err()
+                    .map(TemplateSourceException::from)
+            } else {
+                None
+            };
+            pending_changes.insert(template_id, PendingChange { change, error });
         }
+        debug!("Previewed Template Cache Changes: pending_changes={pending_changes:?}");
+        Ok(pending_changes)
     }
 }
 
@@ -69,6 +128,7 @@ impl TemplateSource for VerifiedPermissionsTemplateSource {
         policy_selector: PolicySelector,
     ) -> Result<HashMap<TemplateId, Template>, Self::Error> {
         let mut cedar_template_map: HashMap<TemplateId, Template> = HashMap::new();
+        self.replaced.clear();
 
         // Load templates and update template cache
         let template_cache_diff_map = self
@@ -76,14 +136,16 @@ impl TemplateSource for VerifiedPermissionsTemplateSource {
             .get_pending_updates(&self.loader.load(policy_selector.clone()).await?);
         for (template_id, cache_change) in template_cache_diff_map {
             if cache_change == CacheChange::Deleted {
-                self.cache.remove(&template_id);
+                let previous = self.cache.remove(&template_id);
+                self.replaced.entry(template_id.clone()).or_insert(previous);
                 debug!("Removed Template from Cache: template_id={template_id:?}");
             } else {
                 let read_input =
                     GetPolicyTemplateInput::new(policy_selector.clone(), template_id.clone());
                 let template_output = self.reader.read(read_input).await?;
 
-                self.cache.put(template_id.clone(), template_output);
+                let previous = self.cache.put(template_id.clone(), template_output);
+                self.replaced.entry(template_id.clone()).or_insert(previous);
                 debug!("Updated Template in Cache: template_id={template_id:?}");
             }
         }
@@ -103,7 +165,7 @@ pub mod test {
         TemplateSource, VerifiedPermissionsTemplateSource,
     };
     use crate::private::sources::test::{build_client, build_event, StatusCode};
-    use crate::private::sources::Cache;
+    use crate::private::sources::{Cache, CacheChange};
     use crate::private::translator::avp_to_cedar::Template;
     use crate::private::types::policy_selector::PolicySelector;
     use crate::private::types::template_id::TemplateId;
@@ -288,4 +350,44 @@ pub mod test {
 
         assert_eq!(template_result.clone(), template_copy);
     }
+
+    #[tokio::test]
+    async fn test_template_source_preview_reports_changes_without_updating_cache() {
+        let policy_selector = PolicySelector::from("mockPolicyStoreId".to_string());
+        let policy_template_id = TemplateId("mockTemplateId".to_string());
+        let template_description = "mockDescription";
+
+        let template_loader_request = ListPolicyTemplatesRequest {
+            policy_store_id: policy_selector.id().to_string(),
+            next_token: None,
+            max_results: 1,
+        };
+
+        let template_loader_response = ListPolicyTemplatesResponse {
+            next_token: None,
+            policy_templates: Some(vec![build_policy_template(
+                &policy_selector,
+                &policy_template_id,
+                template_description,
+            )]),
+        };
+
+        let client = build_client(vec![build_event(
+            &template_loader_request,
+            &template_loader_response,
+            StatusCode::OK,
+        )]);
+
+        let template_source = VerifiedPermissionsTemplateSource::from(client);
+        let result = template_source
+            .preview(policy_selector, false)
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        let created = result.get(&policy_template_id).unwrap();
+        assert_eq!(created.change, CacheChange::Created);
+        assert!(created.error.is_none());
+        assert!(template_source.cache.get(&policy_template_id).is_none());
+    }
 }
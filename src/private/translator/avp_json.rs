@@ -0,0 +1,500 @@
+//! The JSON wire format of the Amazon Verified Permissions `IsAuthorized` and `BatchIsAuthorized`
+//! operations, and its conversion to and from the AWS SDK shapes.
+use std::collections::HashMap;
+
+use aws_sdk_verifiedpermissions::error::BuildError;
+use aws_sdk_verifiedpermissions::operation::batch_is_authorized::{
+    BatchIsAuthorizedInput, BatchIsAuthorizedOutput,
+};
+use aws_sdk_verifiedpermissions::operation::is_authorized::{
+    IsAuthorizedInput, IsAuthorizedOutput,
+};
+use aws_sdk_verifiedpermissions::types::{
+    ActionIdentifier, AttributeValue, BatchIsAuthorizedInputItem, ContextDefinition,
+    DeterminingPolicyItem, EntitiesDefinition, EntityIdentifier, EntityItem, EvaluationErrorItem,
+};
+use serde::{Deserialize, Serialize};
+
+/// An `EntityIdentifier`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityIdentifierJson {
+    /// The entity type
+    pub entity_type: String,
+    /// The entity id
+    pub entity_id: String,
+}
+
+/// An `ActionIdentifier`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionIdentifierJson {
+    /// The action type
+    pub action_type: String,
+    /// The action id
+    pub action_id: String,
+}
+
+/// An `AttributeValue`, tagged with its type like `{"long": 3}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttributeValueJson {
+    /// A boolean
+    Boolean(bool),
+    /// A reference to an entity
+    EntityIdentifier(EntityIdentifierJson),
+    /// A 64 bit integer
+    Long(i64),
+    /// A string
+    String(String),
+    /// A set of values
+    Set(Vec<Self>),
+    /// A record of named values
+    Record(HashMap<String, Self>),
+}
+
+/// A `ContextDefinition`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContextJson {
+    /// The context attributes
+    ContextMap(HashMap<String, AttributeValueJson>),
+}
+
+/// An `EntityItem`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityItemJson {
+    /// The identifier of the entity
+    pub identifier: EntityIdentifierJson,
+    /// The attributes of the entity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, AttributeValueJson>>,
+    /// The parents of the entity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parents: Option<Vec<EntityIdentifierJson>>,
+}
+
+/// An `EntitiesDefinition`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntitiesJson {
+    /// The entities of the request
+    EntityList(Vec<EntityItemJson>),
+}
+
+/// The body of an `IsAuthorized` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IsAuthorizedRequestJson {
+    /// The policy store the request is evaluated against
+    pub policy_store_id: String,
+    /// The principal of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub principal: Option<EntityIdentifierJson>,
+    /// The action of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<ActionIdentifierJson>,
+    /// The resource of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<EntityIdentifierJson>,
+    /// The context of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextJson>,
+    /// The entities of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entities: Option<EntitiesJson>,
+}
+
+/// A request of a `BatchIsAuthorized` request, echoed in its result.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchIsAuthorizedItemJson {
+    /// The principal of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub principal: Option<EntityIdentifierJson>,
+    /// The action of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<ActionIdentifierJson>,
+    /// The resource of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<EntityIdentifierJson>,
+    /// The context of the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextJson>,
+}
+
+/// The body of a `BatchIsAuthorized` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchIsAuthorizedRequestJson {
+    /// The policy store the requests are evaluated against
+    pub policy_store_id: String,
+    /// The entities shared by the requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entities: Option<EntitiesJson>,
+    /// The requests to evaluate
+    pub requests: Vec<BatchIsAuthorizedItemJson>,
+}
+
+/// A `DeterminingPolicyItem`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeterminingPolicyJson {
+    /// The id of the policy
+    pub policy_id: String,
+}
+
+/// An `EvaluationErrorItem`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationErrorJson {
+    /// The description of the error
+    pub error_description: String,
+}
+
+/// The body of an `IsAuthorized` response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IsAuthorizedResponseJson {
+    /// `ALLOW` or `DENY`
+    pub decision: String,
+    /// The policies that determined the decision
+    pub determining_policies: Vec<DeterminingPolicyJson>,
+    /// The errors that occurred during the evaluation
+    pub errors: Vec<EvaluationErrorJson>,
+}
+
+/// A result of a `BatchIsAuthorized` response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchIsAuthorizedResultJson {
+    /// The evaluated request
+    pub request: BatchIsAuthorizedItemJson,
+    /// `ALLOW` or `DENY`
+    pub decision: String,
+    /// The policies that determined the decision
+    pub determining_policies: Vec<DeterminingPolicyJson>,
+    /// The errors that occurred during the evaluation
+    pub errors: Vec<EvaluationErrorJson>,
+}
+
+/// The body of a `BatchIsAuthorized` response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchIsAuthorizedResponseJson {
+    /// The results in the order of the requests
+    pub results: Vec<BatchIsAuthorizedResultJson>,
+}
+
+impl TryFrom<&EntityIdentifierJson> for EntityIdentifier {
+    type Error = BuildError;
+
+    fn try_from(value: &EntityIdentifierJson) -> Result<Self, Self::Error> {
+        Self::builder()
+            .entity_type(&value.entity_type)
+            .entity_id(&value.entity_id)
+            .build()
+    }
+}
+
+impl From<&EntityIdentifier> for EntityIdentifierJson {
+    fn from(value: &EntityIdentifier) -> Self {
+        Self {
+            entity_type: value.entity_type().to_string(),
+            entity_id: value.entity_id().to_string(),
+        }
+    }
+}
+
+impl TryFrom<&ActionIdentifierJson> for ActionIdentifier {
+    type Error = BuildError;
+
+    fn try_from(value: &ActionIdentifierJson) -> Result<Self, Self::Error> {
+        Self::builder()
+            .action_type(&value.action_type)
+            .action_id(&value.action_id)
+            .build()
+    }
+}
+
+impl From<&ActionIdentifier> for ActionIdentifierJson {
+    fn from(value: &ActionIdentifier) -> Self {
+        Self {
+            action_type: value.action_type().to_string(),
+            action_id: value.action_id().to_string(),
+        }
+    }
+}
+
+impl TryFrom<&AttributeValueJson> for AttributeValue {
+    type Error = BuildError;
+
+    fn try_from(value: &AttributeValueJson) -> Result<Self, Self::Error> {
+        Ok(match value {
+            AttributeValueJson::Boolean(value) => Self::Boolean(*value),
+            AttributeValueJson::EntityIdentifier(value) => {
+                Self::EntityIdentifier(EntityIdentifier::try_from(value)?)
+            }
+            AttributeValueJson::Long(value) => Self::Long(*value),
+            AttributeValueJson::String(value) => Self::String(value.clone()),
+            AttributeValueJson::Set(values) => Self::Set(
+                values
+                    .iter()
+                    .map(Self::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            AttributeValueJson::Record(values) => Self::Record(attributes(values)?),
+        })
+    }
+}
+
+impl From<&AttributeValue> for AttributeValueJson {
+    fn from(value: &AttributeValue) -> Self {
+        match value {
+            AttributeValue::Boolean(value) => Self::Boolean(*value),
+            AttributeValue::EntityIdentifier(value) => Self::EntityIdentifier(value.into()),
+            AttributeValue::Long(value) => Self::Long(*value),
+            AttributeValue::Set(values) => Self::Set(values.iter().map(Self::from).collect()),
+            AttributeValue::Record(values) => Self::Record(
+                values
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.into()))
+                    .collect(),
+            ),
+            AttributeValue::String(value) => Self::String(value.clone()),
+            // The SDK keeps no value for variants it does not know, they become empty strings
+            _ => Self::String(String::new()),
+        }
+    }
+}
+
+/// Converts named attribute values to the SDK shape.
+fn attributes(
+    values: &HashMap<String, AttributeValueJson>,
+) -> Result<HashMap<String, AttributeValue>, BuildError> {
+    values
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), AttributeValue::try_from(value)?)))
+        .collect()
+}
+
+impl TryFrom<&ContextJson> for ContextDefinition {
+    type Error = BuildError;
+
+    fn try_from(value: &ContextJson) -> Result<Self, Self::Error> {
+        let ContextJson::ContextMap(values) = value;
+        Ok(Self::ContextMap(attributes(values)?))
+    }
+}
+
+impl From<&ContextDefinition> for ContextJson {
+    fn from(value: &ContextDefinition) -> Self {
+        match value {
+            ContextDefinition::ContextMap(values) => Self::ContextMap(
+                values
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.into()))
+                    .collect(),
+            ),
+            _ => Self::ContextMap(HashMap::new()),
+        }
+    }
+}
+
+impl TryFrom<&EntitiesJson> for EntitiesDefinition {
+    type Error = BuildError;
+
+    fn try_from(value: &EntitiesJson) -> Result<Self, Self::Error> {
+        let EntitiesJson::EntityList(items) = value;
+        let items = items
+            .iter()
+            .map(|item| {
+                Ok(EntityItem::builder()
+                    .identifier(EntityIdentifier::try_from(&item.identifier)?)
+                    .set_attributes(item.attributes.as_ref().map(attributes).transpose()?)
+                    .set_parents(
+                        item.parents
+                            .as_ref()
+                            .map(|parents| {
+                                parents
+                                    .iter()
+                                    .map(EntityIdentifier::try_from)
+                                    .collect::<Result<Vec<_>, _>>()
+                            })
+                            .transpose()?,
+                    )
+                    .build())
+            })
+            .collect::<Result<_, BuildError>>()?;
+        Ok(Self::EntityList(items))
+    }
+}
+
+impl TryFrom<&IsAuthorizedRequestJson> for IsAuthorizedInput {
+    type Error = BuildError;
+
+    fn try_from(value: &IsAuthorizedRequestJson) -> Result<Self, Self::Error> {
+        Self::builder()
+            .policy_store_id(&value.policy_store_id)
+            .set_principal(
+                value
+                    .principal
+                    .as_ref()
+                    .map(TryFrom::try_from)
+                    .transpose()?,
+            )
+            .set_action(value.action.as_ref().map(TryFrom::try_from).transpose()?)
+            .set_resource(value.resource.as_ref().map(TryFrom::try_from).transpose()?)
+            .set_context(value.context.as_ref().map(TryFrom::try_from).transpose()?)
+            .set_entities(value.entities.as_ref().map(TryFrom::try_from).transpose()?)
+            .build()
+    }
+}
+
+impl TryFrom<&BatchIsAuthorizedItemJson> for BatchIsAuthorizedInputItem {
+    type Error = BuildError;
+
+    fn try_from(value: &BatchIsAuthorizedItemJson) -> Result<Self, Self::Error> {
+        Ok(Self::builder()
+            .set_principal(
+                value
+                    .principal
+                    .as_ref()
+                    .map(TryFrom::try_from)
+                    .transpose()?,
+            )
+            .set_action(value.action.as_ref().map(TryFrom::try_from).transpose()?)
+            .set_resource(value.resource.as_ref().map(TryFrom::try_from).transpose()?)
+            .set_context(value.context.as_ref().map(TryFrom::try_from).transpose()?)
+            .build())
+    }
+}
+
+impl From<&BatchIsAuthorizedInputItem> for BatchIsAuthorizedItemJson {
+    fn from(value: &BatchIsAuthorizedInputItem) -> Self {
+        Self {
+            principal: value.principal().map(Into::into),
+            action: value.action().map(Into::into),
+            resource: value.resource().map(Into::into),
+            context: value.context().map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<&BatchIsAuthorizedRequestJson> for BatchIsAuthorizedInput {
+    type Error = BuildError;
+
+    fn try_from(value: &BatchIsAuthorizedRequestJson) -> Result<Self, Self::Error> {
+        Self::builder()
+            .policy_store_id(&value.policy_store_id)
+            .set_entities(value.entities.as_ref().map(TryFrom::try_from).transpose()?)
+            .set_requests(Some(
+                value
+                    .requests
+                    .iter()
+                    .map(TryFrom::try_from)
+                    .collect::<Result<_, _>>()?,
+            ))
+            .build()
+    }
+}
+
+/// Converts determining policies to the JSON shape.
+fn determining_policies(items: &[DeterminingPolicyItem]) -> Vec<DeterminingPolicyJson> {
+    items
+        .iter()
+        .map(|item| DeterminingPolicyJson {
+            policy_id: item.policy_id().to_string(),
+        })
+        .collect()
+}
+
+/// Converts evaluation errors to the JSON shape.
+fn errors(items: &[EvaluationErrorItem]) -> Vec<EvaluationErrorJson> {
+    items
+        .iter()
+        .map(|item| EvaluationErrorJson {
+            error_description: item.error_description().to_string(),
+        })
+        .collect()
+}
+
+impl From<&IsAuthorizedOutput> for IsAuthorizedResponseJson {
+    fn from(value: &IsAuthorizedOutput) -> Self {
+        Self {
+            decision: value.decision().as_str().to_string(),
+            determining_policies: determining_policies(value.determining_policies()),
+            errors: errors(value.errors()),
+        }
+    }
+}
+
+impl From<&BatchIsAuthorizedOutput> for BatchIsAuthorizedResponseJson {
+    fn from(value: &BatchIsAuthorizedOutput) -> Self {
+        Self {
+            results: value
+                .results()
+                .iter()
+                .map(|result| BatchIsAuthorizedResultJson {
+                    request: result.request().map(Into::into).unwrap_or_default(),
+                    decision: result.decision().as_str().to_string(),
+                    determining_policies: determining_policies(result.determining_policies()),
+                    errors: errors(result.errors()),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aws_sdk_verifiedpermissions::operation::is_authorized::IsAuthorizedInput;
+    use aws_sdk_verifiedpermissions::types::{
+        AttributeValue, ContextDefinition, EntitiesDefinition,
+    };
+    use serde_json::json;
+
+    use super::{BatchIsAuthorizedItemJson, IsAuthorizedRequestJson};
+
+    #[test]
+    fn is_authorized_request_converts_to_the_sdk_input() {
+        let request: IsAuthorizedRequestJson = serde_json::from_value(json!({
+            "policyStoreId": "ps-1",
+            "principal": {"entityType": "User", "entityId": "alice"},
+            "action": {"actionType": "Action", "actionId": "view"},
+            "resource": {"entityType": "Photo", "entityId": "beach"},
+            "context": {"contextMap": {
+                "mfa": {"boolean": true},
+                "tags": {"set": [{"string": "a"}, {"long": 1}]}
+            }},
+            "entities": {"entityList": [{
+                "identifier": {"entityType": "User", "entityId": "alice"},
+                "attributes": {"level": {"long": 5}},
+                "parents": [{"entityType": "Group", "entityId": "admins"}]
+            }]}
+        }))
+        .unwrap();
+
+        let input = IsAuthorizedInput::try_from(&request).unwrap();
+
+        assert_eq!(input.policy_store_id(), Some("ps-1"));
+        assert_eq!(input.principal().unwrap().entity_id(), "alice");
+        assert_eq!(input.action().unwrap().action_id(), "view");
+        let Some(ContextDefinition::ContextMap(context)) = input.context() else {
+            panic!("expected a context map");
+        };
+        assert_eq!(context.get("mfa"), Some(&AttributeValue::Boolean(true)));
+        let Some(EntitiesDefinition::EntityList(entities)) = input.entities() else {
+            panic!("expected an entity list");
+        };
+        assert_eq!(entities[0].parents()[0].entity_id(), "admins");
+    }
+
+    #[test]
+    fn batch_item_is_echoed_without_missing_fields() {
+        let item = BatchIsAuthorizedItemJson::default();
+
+        assert_eq!(serde_json::to_value(&item).unwrap(), json!({}));
+    }
+}
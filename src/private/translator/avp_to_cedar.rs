@@ -1,18 +1,27 @@
 use crate::private::sources::policy::core::PolicyDefinition;
 use crate::private::translator::avp_to_cedar::Policy::{Static, TemplateLinked};
-use crate::private::translator::error::TranslatorException;
+use crate::private::translator::error::{StatementParseError, TranslatorException};
 use crate::private::types::policy_id::PolicyId;
 use crate::private::types::template_id::TemplateId;
 use aws_sdk_verifiedpermissions::operation::get_policy_template::GetPolicyTemplateOutput;
-use aws_sdk_verifiedpermissions::types::{EntityIdentifier, PolicyDefinitionDetail};
-use cedar_policy::{EntityId, EntityTypeName, EntityUid, SlotId};
-use std::collections::HashMap;
+use aws_sdk_verifiedpermissions::operation::is_authorized::IsAuthorizedInput;
+use aws_sdk_verifiedpermissions::types::{
+    ActionIdentifier, AttributeValue, ContextDefinition, EntitiesDefinition, EntityIdentifier,
+    PolicyDefinitionDetail,
+};
+use cedar_policy::{
+    Context, Entities, Entity, EntityId, EntityTypeName, EntityUid, Request, RestrictedExpression,
+    SlotId,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 
 /// This wraps the cases for `Static` and `TemplateLinked` policies from the `PolicyDefinitionDetail`
 /// in order to facilitate cedar translation to Policy Sets.
 #[derive(Eq, PartialEq, Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum Policy {
     Static(cedar_policy::Policy),
     TemplateLinked(PolicyId, TemplateId, HashMap<SlotId, EntityUid>),
@@ -26,6 +35,36 @@ pub struct Template(pub(crate) cedar_policy::Template);
 #[derive(Debug)]
 pub struct Schema(pub(crate) cedar_policy::Schema);
 
+/// The syntax an Amazon Verified Permissions schema is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    /// The Cedar JSON schema format
+    Json,
+    /// The Cedar human-readable schema format
+    Cedar,
+}
+
+impl SchemaFormat {
+    /// Detects the format of a schema: a JSON schema is an object, while a Cedar schema starts with
+    /// a declaration, an annotation or a comment.
+    pub fn detect(schema_str: &str) -> Self {
+        if schema_str.trim_start().starts_with('{') {
+            Self::Json
+        } else {
+            Self::Cedar
+        }
+    }
+}
+
+impl Display for SchemaFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "JSON"),
+            Self::Cedar => write!(f, "Cedar"),
+        }
+    }
+}
+
 /// Translates an Amazon Verified Permissions `PolicyDefinition` to a wrapped Cedar static policy or a
 /// template linked policy, or returns a `TranslatorException`. The translated policy can help build
 /// a policy set
@@ -39,9 +78,16 @@ impl TryFrom<PolicyDefinition> for Policy {
         match detail {
             PolicyDefinitionDetail::Static(definition_detail) => {
                 let Ok(cedar_policy_id) = cedar_policy::PolicyId::from_str(policy_id.as_str());
-                let cedar_policy =
-                    cedar_policy::Policy::parse(Some(cedar_policy_id), definition_detail.statement)
-                        .map_err(|_| TranslatorException::ParsePolicy(policy_id.to_string()))?;
+                let cedar_policy = cedar_policy::Policy::parse(
+                    Some(cedar_policy_id),
+                    &definition_detail.statement,
+                )
+                .map_err(|e| {
+                    TranslatorException::ParsePolicy(
+                        policy_id.clone(),
+                        StatementParseError::new(&definition_detail.statement, e),
+                    )
+                })?;
                 debug!("Translated AVP Policy Definition to a Cedar Static Policy: policy_id={policy_id:?}");
                 Ok(Static(cedar_policy))
             }
@@ -85,8 +131,13 @@ impl TryFrom<GetPolicyTemplateOutput> for Template {
         let Ok(cedar_policy_id) = cedar_policy::PolicyId::from_str(policy_template_id.as_str());
 
         let cedar_template =
-            cedar_policy::Template::parse(Some(cedar_policy_id), template_output.statement)
-                .map_err(|_| TranslatorException::ParseTemplate(policy_template_id.clone()))?;
+            cedar_policy::Template::parse(Some(cedar_policy_id), &template_output.statement)
+                .map_err(|e| {
+                    TranslatorException::ParseTemplate(
+                        policy_template_id.clone(),
+                        StatementParseError::new(&template_output.statement, e),
+                    )
+                })?;
 
         debug!(
             "Translated AVP Policy Template to a Cedar Template: template_id={policy_template_id}"
@@ -95,15 +146,27 @@ impl TryFrom<GetPolicyTemplateOutput> for Template {
     }
 }
 
-/// Translates an Amazon Verified Permissions Schema to a wrapped Cedar schema, or returns a
-/// `TranslatorException`
+/// Translates an Amazon Verified Permissions Schema, in either `SchemaFormat`, to a wrapped Cedar
+/// schema, or returns a `TranslatorException`
 impl TryFrom<&str> for Schema {
     type Error = TranslatorException;
 
     #[instrument(skip(schema_str), err(Debug))]
     fn try_from(schema_str: &str) -> Result<Self, Self::Error> {
-        let cedar_schema = cedar_policy::Schema::from_json_str(schema_str)
-            .map_err(|_e| TranslatorException::ParseSchema())?;
+        let format = SchemaFormat::detect(schema_str);
+        let cedar_schema = match format {
+            SchemaFormat::Json => cedar_policy::Schema::from_json_str(schema_str)
+                .map_err(|e| TranslatorException::ParseSchema(format, Box::new(e)))?,
+            SchemaFormat::Cedar => {
+                let (cedar_schema, warnings) =
+                    cedar_policy::Schema::from_cedarschema_str(schema_str)
+                        .map_err(|e| TranslatorException::ParseSchema(format, Box::new(e)))?;
+                for warning in warnings {
+                    warn!("Cedar schema warning: {warning}");
+                }
+                cedar_schema
+            }
+        };
         if let Ok(action_entities) = cedar_schema.action_entities() {
             let schema_entities_ids = action_entities
                 .iter()
@@ -124,28 +187,260 @@ fn update_entity_map(
     option_identifier: Option<EntityIdentifier>,
 ) -> Result<(), TranslatorException> {
     if let Some(identifier) = option_identifier {
-        let entity_name = EntityTypeName::from_str(&identifier.entity_type)
-            .map_err(|_e| TranslatorException::ParseEntity(policy_id.clone()))?;
-        let entity_id = EntityId::from_str(&identifier.entity_id)
-            .map_err(|_e| TranslatorException::ParseEntity(policy_id))?;
+        let entity_name = EntityTypeName::from_str(&identifier.entity_type).map_err(|e| {
+            TranslatorException::ParseEntity(
+                policy_id,
+                StatementParseError::new(&identifier.entity_type, e),
+            )
+        })?;
+        let Ok(entity_id) = EntityId::from_str(&identifier.entity_id);
         let entity = EntityUid::from_type_name_and_id(entity_name, entity_id);
         entity_map.insert(slot_id, entity);
     }
     Ok(())
 }
 
+/// The attribute name Cedar uses to escape extension values in JSON, such as
+/// `{"__extn": {"fn": "decimal", "arg": "1.23"}}`.
+const EXTENSION_ESCAPE: &str = "__extn";
+
+/// A Cedar authorization request and its entities, translated from an Amazon Verified Permissions
+/// `IsAuthorized` input.
+#[derive(Debug, Clone)]
+pub struct CedarRequest {
+    /// The principal, action, resource and context of the request
+    pub request: Request,
+    /// The entities provided with the request
+    pub entities: Entities,
+}
+
+/// Translates an Amazon Verified Permissions `IsAuthorizedInput` to a Cedar request and entities.
+impl TryFrom<&IsAuthorizedInput> for CedarRequest {
+    type Error = TranslatorException;
+
+    #[instrument(skip_all, err(Debug))]
+    fn try_from(input: &IsAuthorizedInput) -> Result<Self, Self::Error> {
+        Ok(Self {
+            request: request(
+                input.principal(),
+                input.action(),
+                input.resource(),
+                input.context(),
+            )?,
+            entities: entities(input.entities())?,
+        })
+    }
+}
+
+/// Translates an Amazon Verified Permissions `EntityIdentifier` to a Cedar `EntityUid`.
+///
+/// # Errors
+///
+/// Returns a `TranslatorException` if the entity type is not a valid Cedar type name.
+pub fn entity_uid(identifier: &EntityIdentifier) -> Result<EntityUid, TranslatorException> {
+    build_uid(&identifier.entity_type, &identifier.entity_id)
+}
+
+/// Translates an Amazon Verified Permissions `ActionIdentifier` to a Cedar `EntityUid`.
+///
+/// # Errors
+///
+/// Returns a `TranslatorException` if the action type is not a valid Cedar type name.
+pub fn action_uid(identifier: &ActionIdentifier) -> Result<EntityUid, TranslatorException> {
+    build_uid(&identifier.action_type, &identifier.action_id)
+}
+
+fn build_uid(entity_type: &str, entity_id: &str) -> Result<EntityUid, TranslatorException> {
+    let type_name = EntityTypeName::from_str(entity_type).map_err(|e| {
+        TranslatorException::InvalidEntityIdentifier(entity_type.to_string(), e.to_string())
+    })?;
+    let Ok(entity_id) = EntityId::from_str(entity_id);
+    Ok(EntityUid::from_type_name_and_id(type_name, entity_id))
+}
+
+/// Translates an Amazon Verified Permissions `AttributeValue` to a Cedar `RestrictedExpression`.
+///
+/// Decimal and IP address values are written as records using the Cedar extension escape, for
+/// example `{"__extn": {"fn": "ip", "arg": "10.0.0.1"}}`.
+///
+/// # Errors
+///
+/// Returns a `TranslatorException` if the value has an unknown type, an invalid entity
+/// identifier or an unsupported extension function.
+pub fn attribute_value(
+    value: &AttributeValue,
+) -> Result<RestrictedExpression, TranslatorException> {
+    match value {
+        AttributeValue::Boolean(value) => Ok(RestrictedExpression::new_bool(*value)),
+        AttributeValue::Long(value) => Ok(RestrictedExpression::new_long(*value)),
+        AttributeValue::String(value) => Ok(RestrictedExpression::new_string(value.clone())),
+        AttributeValue::EntityIdentifier(identifier) => Ok(RestrictedExpression::new_entity_uid(
+            entity_uid(identifier)?,
+        )),
+        AttributeValue::Set(values) => Ok(RestrictedExpression::new_set(
+            values
+                .iter()
+                .map(attribute_value)
+                .collect::<Result<Vec<_>, _>>()?,
+        )),
+        AttributeValue::Record(record) => match record.get(EXTENSION_ESCAPE) {
+            Some(extension) if record.len() == 1 => extension_value(extension),
+            _ => RestrictedExpression::new_record(attributes(record)?)
+                .map_err(|e| TranslatorException::InvalidAttributeValue(e.to_string())),
+        },
+        _ => Err(TranslatorException::InvalidAttributeValue(format!(
+            "unsupported attribute type: {value:?}"
+        ))),
+    }
+}
+
+/// Translates an escaped `{"fn": .., "arg": ..}` extension value.
+fn extension_value(
+    extension: &AttributeValue,
+) -> Result<RestrictedExpression, TranslatorException> {
+    let AttributeValue::Record(call) = extension else {
+        return Err(TranslatorException::InvalidAttributeValue(
+            "extension values must be records with fn and arg".to_string(),
+        ));
+    };
+    match (call.get("fn"), call.get("arg")) {
+        (Some(AttributeValue::String(function)), Some(AttributeValue::String(arg))) => {
+            match function.as_str() {
+                "decimal" => Ok(RestrictedExpression::new_decimal(arg)),
+                "ip" => Ok(RestrictedExpression::new_ip(arg)),
+                _ => Err(TranslatorException::InvalidAttributeValue(format!(
+                    "unsupported extension function: {function}"
+                ))),
+            }
+        }
+        _ => Err(TranslatorException::InvalidAttributeValue(
+            "extension values must be records with fn and arg".to_string(),
+        )),
+    }
+}
+
+fn attributes(
+    record: &HashMap<String, AttributeValue>,
+) -> Result<Vec<(String, RestrictedExpression)>, TranslatorException> {
+    record
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), attribute_value(value)?)))
+        .collect()
+}
+
+/// Translates an Amazon Verified Permissions `ContextDefinition` to a Cedar `Context`, an absent
+/// definition is an empty context.
+///
+/// # Errors
+///
+/// Returns a `TranslatorException` if an attribute cannot be translated.
+pub fn context(definition: Option<&ContextDefinition>) -> Result<Context, TranslatorException> {
+    match definition {
+        None => Ok(Context::empty()),
+        Some(ContextDefinition::ContextMap(map)) => Context::from_pairs(attributes(map)?)
+            .map_err(|e| TranslatorException::InvalidRequest(e.to_string())),
+        Some(definition) => Err(TranslatorException::InvalidAttributeValue(format!(
+            "unsupported context definition: {definition:?}"
+        ))),
+    }
+}
+
+/// Translates an Amazon Verified Permissions `EntitiesDefinition` to Cedar `Entities`, an absent
+/// definition has no entities.
+///
+/// # Errors
+///
+/// Returns a `TranslatorException` if an entity has no identifier, an attribute cannot be
+/// translated or the entities are inconsistent.
+pub fn entities(definition: Option<&EntitiesDefinition>) -> Result<Entities, TranslatorException> {
+    let items = match definition {
+        None => return Ok(Entities::empty()),
+        Some(EntitiesDefinition::EntityList(items)) => items,
+        Some(definition) => {
+            return Err(TranslatorException::InvalidAttributeValue(format!(
+                "unsupported entities definition: {definition:?}"
+            )))
+        }
+    };
+
+    let entities = items
+        .iter()
+        .map(|item| {
+            let uid = entity_uid(item.identifier.as_ref().ok_or(
+                TranslatorException::MissingRequestField("entity identifier"),
+            )?)?;
+            let attrs = match &item.attributes {
+                Some(attributes) => attributes_map(attributes)?,
+                None => HashMap::new(),
+            };
+            let parents = item
+                .parents
+                .iter()
+                .flatten()
+                .map(entity_uid)
+                .collect::<Result<HashSet<_>, _>>()?;
+            Entity::new(uid, attrs, parents)
+                .map_err(|e| TranslatorException::InvalidRequest(e.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Entities::from_entities(entities, None)
+        .map_err(|e| TranslatorException::InvalidRequest(e.to_string()))
+}
+
+fn attributes_map(
+    record: &HashMap<String, AttributeValue>,
+) -> Result<HashMap<String, RestrictedExpression>, TranslatorException> {
+    Ok(attributes(record)?.into_iter().collect())
+}
+
+/// Translates the principal, action, resource and context of an Amazon Verified Permissions
+/// authorization request to a Cedar `Request`.
+///
+/// # Errors
+///
+/// Returns a `TranslatorException` if the principal, action or resource is missing, or any part
+/// of the request cannot be translated.
+pub fn request(
+    principal: Option<&EntityIdentifier>,
+    action: Option<&ActionIdentifier>,
+    resource: Option<&EntityIdentifier>,
+    context_definition: Option<&ContextDefinition>,
+) -> Result<Request, TranslatorException> {
+    let principal =
+        entity_uid(principal.ok_or(TranslatorException::MissingRequestField("principal"))?)?;
+    let action = action_uid(action.ok_or(TranslatorException::MissingRequestField("action"))?)?;
+    let resource =
+        entity_uid(resource.ok_or(TranslatorException::MissingRequestField("resource"))?)?;
+    Request::new(
+        principal,
+        action,
+        resource,
+        context(context_definition)?,
+        None,
+    )
+    .map_err(|e| TranslatorException::InvalidRequest(e.to_string()))
+}
+
 #[cfg(test)]
 mod test {
     use crate::private::sources::policy::core::PolicyDefinition;
-    use crate::private::translator::avp_to_cedar::{Policy, Schema, Template};
+    use crate::private::translator::avp_to_cedar::{
+        attribute_value, context, entities, request, CedarRequest, Policy, Schema, SchemaFormat,
+        Template,
+    };
     use crate::private::translator::error::TranslatorException;
     use aws_sdk_verifiedpermissions::operation::get_policy_template::GetPolicyTemplateOutput;
+    use aws_sdk_verifiedpermissions::operation::is_authorized::IsAuthorizedInput;
     use aws_sdk_verifiedpermissions::types::{
-        EntityIdentifier, PolicyDefinitionDetail, StaticPolicyDefinitionDetail,
+        ActionIdentifier, AttributeValue, ContextDefinition, EntitiesDefinition, EntityIdentifier,
+        EntityItem, PolicyDefinitionDetail, StaticPolicyDefinitionDetail,
         TemplateLinkedPolicyDefinitionDetail,
     };
     use aws_smithy_types::DateTime;
     use cedar_policy::{entities_errors::EntitiesError, Entities};
+    use cedar_policy::{Authorizer, Decision, EntityUid, PolicySet};
+    use std::collections::HashMap;
+    use std::str::FromStr;
 
     const POLICY_ID: &str = "dummy-policy-id";
     const POLICY_STORE_ID: &str = "dummy-policy-store-id";
@@ -253,6 +548,7 @@ mod test {
             .unwrap()
     }
 
+    #[allow(clippy::result_large_err)]
     fn generate_action_entity() -> Result<Entities, EntitiesError> {
         let action_json =
             r#"[{"uid":{"type":"AvpLocalAgent::Action","id":"viewPhoto"},"attrs":{},"parents":[]}]"#
@@ -292,10 +588,10 @@ mod test {
 
         let error = Policy::try_from(definition);
         assert!(matches!(error, Err(TranslatorException::ParsePolicy(..)),));
-        assert_eq!(
-            error.err().unwrap().to_string(),
-            "Error occurred when parsing the policy, policy id: dummy-policy-id.",
-        );
+        let message = error.err().unwrap().to_string();
+        assert!(message
+            .starts_with("Error occurred when parsing the policy, policy id: dummy-policy-id: "));
+        assert!(message.contains(" at line "), "{message}");
     }
 
     #[test]
@@ -345,10 +641,11 @@ mod test {
             .unwrap();
         let error = Template::try_from(output);
         assert!(matches!(error, Err(TranslatorException::ParseTemplate(..))));
-        assert_eq!(
-            error.err().unwrap().to_string(),
-            "Error occurred when parsing the template, template id: dummy-template-id.",
-        );
+        let message = error.err().unwrap().to_string();
+        assert!(message.starts_with(
+            "Error occurred when parsing the template, template id: dummy-template-id: "
+        ));
+        assert!(message.contains(" at line "), "{message}");
     }
 
     // Schema Translator Tests
@@ -367,10 +664,152 @@ mod test {
     #[test]
     fn schema_translator_parsing_error() {
         let error = Schema::try_from(INVALID_SCHEMA);
-        assert!(matches!(error, Err(TranslatorException::ParseSchema(..))));
+        assert!(matches!(
+            error,
+            Err(TranslatorException::ParseSchema(SchemaFormat::Json, _))
+        ));
+        assert!(error
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("Error occurred when parsing the JSON schema: "));
+    }
+
+    #[test]
+    fn schema_translator_parses_the_cedar_format() {
+        let Schema(schema) = Schema::try_from(
+            "// The photo sharing schema
+            entity User;
+            entity Photo;
+            action view appliesTo { principal: [User], resource: [Photo] };",
+        )
+        .unwrap();
+        assert_eq!(schema.entity_types().count(), 2);
+        assert_eq!(schema.actions().count(), 1);
+    }
+
+    #[test]
+    fn schema_translator_reports_cedar_format_errors() {
+        let error = Schema::try_from("entity User;\nactoin view;").unwrap_err();
+        assert!(matches!(
+            error,
+            TranslatorException::ParseSchema(SchemaFormat::Cedar, _)
+        ));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    // Authorization Request Translator Tests
+    fn identifier(entity_type: &str, entity_id: &str) -> EntityIdentifier {
+        EntityIdentifier::builder()
+            .entity_type(entity_type)
+            .entity_id(entity_id)
+            .build()
+            .unwrap()
+    }
+
+    fn extension(function: &str, arg: &str) -> AttributeValue {
+        AttributeValue::Record(HashMap::from([(
+            "__extn".to_string(),
+            AttributeValue::Record(HashMap::from([
+                (
+                    "fn".to_string(),
+                    AttributeValue::String(function.to_string()),
+                ),
+                ("arg".to_string(), AttributeValue::String(arg.to_string())),
+            ])),
+        )]))
+    }
+
+    #[test]
+    fn is_authorized_input_translates_to_an_evaluable_request() {
+        let context = ContextDefinition::ContextMap(HashMap::from([
+            ("mfa".to_string(), AttributeValue::Boolean(true)),
+            ("sourceIp".to_string(), extension("ip", "10.0.0.1")),
+            (
+                "risk".to_string(),
+                AttributeValue::Record(HashMap::from([
+                    ("score".to_string(), extension("decimal", "0.25")),
+                    (
+                        "tags".to_string(),
+                        AttributeValue::Set(vec![AttributeValue::String("vpn".to_string())]),
+                    ),
+                ])),
+            ),
+        ]));
+        let entities = EntitiesDefinition::EntityList(vec![
+            EntityItem::builder()
+                .identifier(identifier("User", "alice"))
+                .attributes("level", AttributeValue::Long(5))
+                .attributes(
+                    "manager",
+                    AttributeValue::EntityIdentifier(identifier("User", "bob")),
+                )
+                .parents(identifier("Group", "admins"))
+                .build(),
+            EntityItem::builder()
+                .identifier(identifier("Group", "admins"))
+                .build(),
+        ]);
+        let input = IsAuthorizedInput::builder()
+            .principal(identifier("User", "alice"))
+            .action(
+                ActionIdentifier::builder()
+                    .action_type("Action")
+                    .action_id("view")
+                    .build()
+                    .unwrap(),
+            )
+            .resource(identifier("Photo", "beach"))
+            .context(context)
+            .entities(entities)
+            .build()
+            .unwrap();
+
+        let CedarRequest { request, entities } = CedarRequest::try_from(&input).unwrap();
+        let policies = PolicySet::from_str(
+            r#"permit(principal in Group::"admins", action == Action::"view", resource)
+            when {
+                context.mfa && context.sourceIp.isInRange(ip("10.0.0.0/8")) &&
+                context.risk.score.lessThan(decimal("0.5")) && context.risk.tags.contains("vpn") &&
+                principal.level > 3 && principal.manager == User::"bob"
+            };"#,
+        )
+        .unwrap();
+        let response = Authorizer::new().is_authorized(&request, &policies, &entities);
+        assert_eq!(response.decision(), Decision::Allow);
+    }
+
+    #[test]
+    fn request_without_a_principal_errors() {
+        let error = request(None, None, Some(&identifier("Photo", "beach")), None);
+        assert!(matches!(
+            error,
+            Err(TranslatorException::MissingRequestField("principal"))
+        ));
+    }
+
+    #[test]
+    fn invalid_identifiers_and_extensions_error() {
+        assert!(matches!(
+            attribute_value(&AttributeValue::EntityIdentifier(identifier(
+                "not a type",
+                "x"
+            ))),
+            Err(TranslatorException::InvalidEntityIdentifier(..))
+        ));
+        assert!(matches!(
+            attribute_value(&extension("datetime", "2024-01-01")),
+            Err(TranslatorException::InvalidAttributeValue(..))
+        ));
+    }
+
+    #[test]
+    fn absent_context_and_entities_are_empty() {
+        assert!(context(None).unwrap().into_iter().next().is_none());
+        assert!(entities(None).unwrap().iter().next().is_none());
         assert_eq!(
-            error.err().unwrap().to_string(),
-            "Error occurred when parsing the schema",
+            EntityUid::from_str(r#"User::"alice""#).unwrap(),
+            super::entity_uid(&identifier("User", "alice")).unwrap()
         );
     }
 }
@@ -1,15 +1,137 @@
+//! Errors that occur when translating Amazon Verified Permissions models to Cedar.
+use std::fmt::Write;
+
+use cedar_policy::{ParseError, ParseErrors};
+use miette::Diagnostic;
 use thiserror::Error;
 
+use crate::private::translator::avp_to_cedar::SchemaFormat;
+
+/// `TranslatorException` occurs when an Amazon Verified Permissions model cannot be translated to
+/// Cedar.
 #[derive(Error, Debug)]
 pub enum TranslatorException {
+    /// The input is not supported
     #[error("Input is invalid.")]
     InvalidInput(),
-    #[error("Error occurred when parsing the policy, policy id: {0}.")]
-    ParsePolicy(String),
-    #[error("Error occurred when parsing the entity in the policy, policy id: {0}.")]
-    ParseEntity(String),
-    #[error("Error occurred when parsing the template, template id: {0}.")]
-    ParseTemplate(String),
-    #[error("Error occurred when parsing the schema")]
-    ParseSchema(),
+    /// A policy failed to parse
+    #[error("Error occurred when parsing the policy, policy id: {0}: {1}")]
+    ParsePolicy(String, #[source] StatementParseError),
+    /// An entity of a template-linked policy failed to parse
+    #[error("Error occurred when parsing the entity in the policy, policy id: {0}: {1}")]
+    ParseEntity(String, #[source] StatementParseError),
+    /// A template failed to parse
+    #[error("Error occurred when parsing the template, template id: {0}: {1}")]
+    ParseTemplate(String, #[source] StatementParseError),
+    /// The schema failed to parse, with the format it was parsed as and the Cedar error
+    #[error("Error occurred when parsing the {0} schema: {1}")]
+    ParseSchema(
+        SchemaFormat,
+        #[source] Box<dyn std::error::Error + Send + Sync + 'static>,
+    ),
+    /// An entity or action identifier has an invalid type
+    #[error("Invalid entity identifier {0}: {1}")]
+    InvalidEntityIdentifier(String, String),
+    /// An attribute value cannot be represented in Cedar
+    #[error("Invalid attribute value: {0}")]
+    InvalidAttributeValue(String),
+    /// A required field of the authorization request is missing
+    #[error("The authorization request is missing the {0}")]
+    MissingRequestField(&'static str),
+    /// The Cedar request, context or entities cannot be built
+    #[error("Invalid authorization request: {0}")]
+    InvalidRequest(String),
+}
+
+/// The Cedar `ParseErrors` of a statement, displayed with the line, column and text of the statement
+/// at each error location.
+#[derive(Error, Debug)]
+#[error("{rendered}")]
+pub struct StatementParseError {
+    /// Every error with its locations in the statement
+    rendered: String,
+    /// The errors reported by Cedar, boxed to keep `TranslatorException` small
+    #[source]
+    errors: Box<ParseErrors>,
+}
+
+impl StatementParseError {
+    /// Locates the `errors` in the `statement` that failed to parse.
+    pub fn new(statement: &str, errors: ParseErrors) -> Self {
+        let rendered = errors
+            .iter()
+            .map(|error| render(statement, error))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Self {
+            rendered,
+            errors: Box::new(errors),
+        }
+    }
+
+    /// The errors reported by Cedar.
+    pub const fn errors(&self) -> &ParseErrors {
+        &self.errors
+    }
+}
+
+/// Renders a parse error with each of its labeled locations and its help, if any.
+fn render(statement: &str, error: &ParseError) -> String {
+    let mut rendered = error.to_string();
+    for label in error.labels().into_iter().flatten() {
+        let (line, column, text) = locate(statement, label.offset());
+        let _ = write!(rendered, " at line {line}, column {column}: `{text}`");
+    }
+    if let Some(help) = error.help() {
+        let _ = write!(rendered, " (help: {help})");
+    }
+    rendered
+}
+
+/// Returns the 1-based line and column of a byte offset and the trimmed text of its line.
+fn locate(statement: &str, offset: usize) -> (usize, usize, &str) {
+    let mut offset = offset.min(statement.len());
+    while !statement.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let line_start = statement[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = statement[offset..]
+        .find('\n')
+        .map_or(statement.len(), |index| offset + index);
+    let line = statement[..line_start].matches('\n').count() + 1;
+    let column = statement[line_start..offset].chars().count() + 1;
+    (line, column, statement[line_start..line_end].trim())
+}
+
+#[cfg(test)]
+mod test {
+    use cedar_policy::Policy;
+
+    use super::{locate, StatementParseError};
+
+    #[test]
+    fn locate_returns_the_line_column_and_text() {
+        let statement = "permit(\n  principal,\n  actoin,\n  resource\n);";
+        let offset = statement.find("actoin").unwrap();
+
+        assert_eq!(locate(statement, offset), (3, 3, "actoin,"));
+        assert_eq!(locate(statement, 0), (1, 1, "permit("));
+        assert_eq!(locate(statement, statement.len()), (5, 3, ");"));
+    }
+
+    #[test]
+    fn statement_parse_error_displays_the_error_locations() {
+        let statement = "permit(\n  principal,\n  action,\n  resource\n) when { principal.age > };";
+        let errors = Policy::parse(None, statement).unwrap_err();
+
+        let error = StatementParseError::new(statement, errors);
+
+        let rendered = error.to_string();
+        assert!(rendered.contains("at line 5, column"), "{rendered}");
+        assert!(
+            rendered.contains("`) when { principal.age > };`"),
+            "{rendered}"
+        );
+        assert!(std::error::Error::source(&error).is_some());
+    }
 }
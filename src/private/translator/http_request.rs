@@ -0,0 +1,147 @@
+//! Maps the attributes of an HTTP request and the principal it authenticated as to a Cedar
+//! request, for the adapters authorizing proxied or API Gateway requests.
+//!
+//! The action and resource ids are rendered from templates whose `{placeholder}`s are resolved by
+//! the adapter. The context holds the access token claims of the principal and the request
+//! attributes of the adapter, which replace claims of the same name so that a token cannot forge
+//! the method, path or headers of the request.
+use std::collections::HashMap;
+
+use aws_sdk_verifiedpermissions::types::{
+    ActionIdentifier, AttributeValue, ContextDefinition, EntitiesDefinition, EntityIdentifier,
+    EntityItem,
+};
+use cedar_policy::{Entities, Request};
+
+#[cfg(feature = "token")]
+use crate::public::token_verifier::VerifiedToken;
+
+use super::avp_to_cedar::{entities, request};
+
+/// The principal of a request and what its token added.
+#[derive(Debug)]
+pub struct Principal {
+    /// The principal entity
+    pub identifier: EntityIdentifier,
+    /// The principal entity and its groups, when read from a token
+    pub entities: Vec<EntityItem>,
+    /// The access token claims
+    pub claims: HashMap<String, AttributeValue>,
+}
+
+impl Principal {
+    /// Builds a principal without token entities or claims.
+    pub fn new(entity_type: &str, entity_id: &str) -> Result<Self, String> {
+        Ok(Self {
+            identifier: EntityIdentifier::builder()
+                .entity_type(entity_type)
+                .entity_id(entity_id)
+                .build()
+                .map_err(|e| e.to_string())?,
+            entities: Vec::new(),
+            claims: HashMap::new(),
+        })
+    }
+}
+
+#[cfg(feature = "token")]
+impl From<VerifiedToken> for Principal {
+    fn from(token: VerifiedToken) -> Self {
+        Self {
+            identifier: token.principal,
+            entities: token.entities,
+            claims: token.context,
+        }
+    }
+}
+
+/// The attributes of an HTTP request, as seen by an adapter.
+pub trait HttpAttributes {
+    /// Returns the value of a template placeholder, `Ok(None)` if the request has no value for
+    /// it, or an error if the placeholder is unknown.
+    fn placeholder(&self, placeholder: &str) -> Result<Option<String>, String>;
+
+    /// Returns the request attributes added to the context.
+    fn context(&self) -> HashMap<String, AttributeValue>;
+}
+
+/// How an adapter maps a request to the action and resource of a Cedar request.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTemplates<'a> {
+    /// The entity type of the action
+    pub action_type: &'a str,
+    /// The template of the action id
+    pub action_id: &'a str,
+    /// The entity type of the resource
+    pub resource_type: &'a str,
+    /// The template of the resource id
+    pub resource_id: &'a str,
+}
+
+/// Renders an action or resource id template, failing on an unknown or missing placeholder.
+pub fn render(template: &str, attributes: &impl HttpAttributes) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unterminated placeholder in {template}"))?
+            + start;
+        let placeholder = &rest[start + 1..end];
+        let value = attributes
+            .placeholder(placeholder)?
+            .ok_or_else(|| format!("the request has no {placeholder}"))?;
+        rendered.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Returns the record of the configured headers present in the request, looked up by their
+/// lowercase name.
+pub fn header_record(names: &[String], headers: &HashMap<String, String>) -> AttributeValue {
+    AttributeValue::Record(
+        names
+            .iter()
+            .filter_map(|name| {
+                headers
+                    .get(&name.to_lowercase())
+                    .map(|value| (name.clone(), AttributeValue::String(value.clone())))
+            })
+            .collect(),
+    )
+}
+
+/// Maps the request to a Cedar request and the entities of its principal.
+pub fn cedar_request(
+    templates: RequestTemplates<'_>,
+    attributes: &impl HttpAttributes,
+    principal: Principal,
+) -> Result<(Request, Entities), String> {
+    let action = ActionIdentifier::builder()
+        .action_type(templates.action_type)
+        .action_id(render(templates.action_id, attributes)?)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resource = EntityIdentifier::builder()
+        .entity_type(templates.resource_type)
+        .entity_id(render(templates.resource_id, attributes)?)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut context = principal.claims;
+    context.extend(attributes.context());
+
+    let request = request(
+        Some(&principal.identifier),
+        Some(&action),
+        Some(&resource),
+        Some(&ContextDefinition::ContextMap(context)),
+    )
+    .map_err(|e| e.to_string())?;
+    let entities = entities(Some(&EntitiesDefinition::EntityList(principal.entities)))
+        .map_err(|e| e.to_string())?;
+    Ok((request, entities))
+}
@@ -1,2 +1,5 @@
+#[cfg(feature = "sidecar")]
+pub mod avp_json;
 pub mod avp_to_cedar;
 pub mod error;
+pub mod http_request;
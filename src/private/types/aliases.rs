@@ -1,4 +1,6 @@
 //! Contains type aliases used in the crate.
+#[cfg(feature = "token")]
+use crate::private::types::identity_source_id::IdentitySourceId;
 use crate::private::types::policy_id::PolicyId;
 use crate::private::types::template_id::TemplateId;
 use std::collections::HashMap;
@@ -9,3 +11,7 @@ pub type PolicyCache<T> = HashMap<PolicyId, T>;
 
 /// A type alias to store `Templates` from an AVP Policy Store.
 pub type TemplateCache<T> = HashMap<TemplateId, T>;
+
+/// A type alias to store `IdentitySources` from an AVP Policy Store.
+#[cfg(feature = "token")]
+pub type IdentitySourceCache<T> = HashMap<IdentitySourceId, T>;
@@ -24,7 +24,7 @@ use nom::{
 pub enum Value<'src> {
     Simple(&'src str),
     MaybeEscaped(&'src str),
-    Struct(Vec<(&'src str, Value<'src>)>),
+    Struct(Vec<(&'src str, Self)>),
 }
 impl Value<'_> {
     pub fn is_string(&self) -> bool {
@@ -0,0 +1,39 @@
+//! Represents a wrapper for typing an `IdentitySourceId` String
+use std::fmt;
+use std::fmt::Formatter;
+
+/// A wrapper for typing an `IdentitySourceId` string
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct IdentitySourceId(pub String);
+
+// Enables an easy way to call `to_string` on `IdentitySourceId`.
+impl fmt::Display for IdentitySourceId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::private::types::identity_source_id::IdentitySourceId;
+    use std::collections::HashMap;
+
+    #[test]
+    fn identity_source_id_formats_as_expected() {
+        let id = IdentitySourceId("identity-source-id".to_string());
+        assert_eq!(id.to_string(), "identity-source-id");
+    }
+
+    #[test]
+    fn identity_source_id_can_be_inserted_into_map() {
+        let mut map: HashMap<IdentitySourceId, i32> = HashMap::new();
+        assert_eq!(
+            map.insert(IdentitySourceId("identitySourceId".to_string()), 10),
+            None
+        );
+        assert_eq!(
+            map.get(&IdentitySourceId("identitySourceId".to_string())),
+            Some(&10)
+        );
+    }
+}
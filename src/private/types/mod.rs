@@ -1,6 +1,8 @@
 //! A module containing various structs and types used throughout the crate.
 pub mod aliases;
 pub mod cli_shorthand;
+#[cfg(feature = "token")]
+pub mod identity_source_id;
 pub mod policy_id;
 pub mod policy_selector;
 pub mod policy_store_filter;
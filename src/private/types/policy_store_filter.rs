@@ -125,12 +125,13 @@ impl PolicyStoreFilter {
 /// Formats the `PolicyStoreFilter` as CLI shorthand using the given formatter.
 impl fmt::Display for PolicyStoreFilter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut comma = "";
-        if let Some(e_ref) = &self.principal {
+        let mut comma = if let Some(e_ref) = &self.principal {
             f.write_str("principal=")?;
             e_ref.fmt(f)?;
-            comma = ",";
-        }
+            ","
+        } else {
+            ""
+        };
         if let Some(e_ref) = &self.resource {
             f.write_str(comma)?;
             f.write_str("resource=")?;
@@ -191,6 +192,84 @@ impl PolicyStoreFilter {
     }
 }
 
+/// Narrow an optional user-provided filter for internal `ListPolicies` calls
+impl PolicyStoreFilter {
+    /// Restricts `filter` to policies of `policy_type`
+    ///
+    /// # Errors
+    /// If the `filter` already selects a different policy type
+    pub fn narrow_policy_type(
+        filter: Option<Self>,
+        policy_type: PolicyType,
+    ) -> Result<Self, PolicyFilterInputError> {
+        let mut filter = filter.unwrap_or_else(Self::unrestricted);
+        match &filter.policy_type {
+            Some(existing) if existing != &policy_type => {
+                return Err(PolicyFilterInputError::ConflictingFilter(format!(
+                    "policyType is already set to {}",
+                    existing.as_str()
+                )));
+            }
+            _ => filter.policy_type = Some(policy_type),
+        }
+        Ok(filter)
+    }
+
+    /// Restricts `filter` to policies whose principal is the given entity
+    ///
+    /// # Errors
+    /// If the `filter` already selects a different principal, or the entity is invalid
+    pub fn narrow_principal(
+        filter: Option<Self>,
+        entity_type: &str,
+        entity_id: &str,
+    ) -> Result<Self, PolicyFilterInputError> {
+        let principal = EntityReference::try_from(Entity::Identifier {
+            entity_type: entity_type.to_string(),
+            entity_id: entity_id.to_string(),
+        })?;
+        let mut filter = filter.unwrap_or_else(Self::unrestricted);
+        match &filter.principal {
+            Some(existing) if existing != &principal => {
+                return Err(PolicyFilterInputError::ConflictingFilter(format!(
+                    "principal is already set to {existing}"
+                )));
+            }
+            _ => filter.principal = Some(principal),
+        }
+        Ok(filter)
+    }
+
+    /// Restricts `filter` to the policies linked to the template `policy_template_id`
+    ///
+    /// # Errors
+    /// If the `filter` already selects a different template or static policies
+    pub fn narrow_policy_template(
+        filter: Option<Self>,
+        policy_template_id: &str,
+    ) -> Result<Self, PolicyFilterInputError> {
+        let mut filter = Self::narrow_policy_type(filter, PolicyType::TemplateLinked)?;
+        match &filter.policy_template_id {
+            Some(existing) if existing != policy_template_id => {
+                return Err(PolicyFilterInputError::ConflictingFilter(format!(
+                    "policyTemplateId is already set to {existing}"
+                )));
+            }
+            _ => filter.policy_template_id = Some(policy_template_id.to_string()),
+        }
+        Ok(filter)
+    }
+
+    const fn unrestricted() -> Self {
+        Self {
+            principal: None,
+            resource: None,
+            policy_type: None,
+            policy_template_id: None,
+        }
+    }
+}
+
 ///
 /// Get an SDK `PolicyFilter` from our representation
 ///
@@ -223,6 +302,9 @@ pub enum PolicyFilterInputError {
     /// A CLI shorthand expression contains unsupported structures
     #[error("shorthand content error: {0}")]
     ShorthandContentError(String),
+    /// A filter cannot be narrowed because it already selects other policies
+    #[error("conflicting filter: {0}")]
+    ConflictingFilter(String),
 }
 
 ///
@@ -678,4 +760,35 @@ mod tests {
         let filter_ref = hashmap.get(&json_filter);
         assert_eq!(Some(&true), filter_ref);
     }
+
+    #[test]
+    fn test_narrow_without_filter() {
+        let filter = PolicyStoreFilter::narrow_policy_type(None, PolicyType::Static)
+            .expect("an absent filter should be narrowed");
+        let filter = PolicyStoreFilter::narrow_principal(Some(filter), "User", "alice")
+            .expect("an unrestricted principal should be narrowed");
+        assert_eq!(
+            filter.to_string(),
+            "principal={identifier={entityType=User,entityId=alice}},policyType=STATIC"
+        );
+    }
+
+    #[test]
+    fn test_narrow_conflicting_filter() {
+        let filter = PolicyStoreFilter::from_cli_str(FULL_FILTER_CLI)
+            .expect("shorthand should be correctly parsed");
+        assert!(matches!(
+            PolicyStoreFilter::narrow_policy_type(Some(filter.clone()), PolicyType::TemplateLinked),
+            Err(PolicyFilterInputError::ConflictingFilter(_))
+        ));
+        assert!(matches!(
+            PolicyStoreFilter::narrow_principal(Some(filter.clone()), "User", "alice"),
+            Err(PolicyFilterInputError::ConflictingFilter(_))
+        ));
+        assert_eq!(
+            PolicyStoreFilter::narrow_principal(Some(filter.clone()), "User", "nobody")
+                .expect("the same principal should be accepted"),
+            filter
+        );
+    }
 }
@@ -0,0 +1,932 @@
+//! Declarative configuration of the Amazon Verified Permissions providers.
+//!
+//! An `AgentConfig` is deserialized from a JSON file, or a TOML or YAML file with the `config`
+//! feature, and describes the client, its credentials and the policy stores to load. `AgentProviders::from_config` builds the client and
+//! a `PolicySetProvider`, and optionally an `EntityProvider`, for every configured policy store.
+//!
+//! ```toml
+//! region = "us-east-1"
+//!
+//! [credentials]
+//! mode = "assume_role"
+//! role_arn = "arn:aws:iam::123456789012:role/avp-reader"
+//!
+//! [client]
+//! max_attempts = 3
+//!
+//! [[policy_stores]]
+//! policy_store = "arn:aws:verifiedpermissions::123456789012:policy-store/ps-1"
+//! filter = "policyTemplateId=template-1"
+//! refresh_interval_secs = 30
+//! snapshot_path = "/var/lib/avp/ps-1.json"
+//! ```
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use aws_config::default_provider::credentials::DefaultCredentialsChain;
+use aws_config::environment::EnvironmentVariableCredentialsProvider;
+use aws_config::profile::ProfileFileCredentialsProvider;
+use aws_credential_types::provider::SharedCredentialsProvider;
+use aws_sdk_verifiedpermissions::config::AppName;
+use aws_sdk_verifiedpermissions::Client;
+use aws_types::region::Region;
+use cedar_local_agent::public::{
+    EntityProviderError as SimpleEntityProviderError, SimpleEntityProvider, UpdateProviderData,
+    UpdateProviderDataError,
+};
+use cedar_policy::{Entities, Request};
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument};
+
+use super::client::{
+    verified_permissions_assume_role, verified_permissions_with_options, AssumeRoleOptions,
+    ClientError, ClientOptions, PolicyStoreArn, AVP_CLIENT_DEFAULT_SESSION_NAME,
+    AVP_CLIENT_DEFAULT_TIMEOUT_MS, AVP_CLIENT_MAX_ATTEMPTS,
+};
+use super::deletion_guard::DeletionGuard;
+use super::entity_provider::{EntityProvider, ProviderError as EntityProviderError};
+use super::policy_overlay::{OverlayError, PolicyOverlay};
+use super::policy_set_filter::{OwnedPolicySetFilter, PolicySetFilter};
+use super::policy_set_provider::{
+    PolicySetProvider, ProviderError as PolicySetProviderError, ProviderOptions,
+    DEFAULT_HISTORY_DEPTH,
+};
+
+/// The default refresh interval in seconds of a configured policy store.
+pub const DEFAULT_REFRESH_INTERVAL_SECS: u64 = 15;
+
+/// `AgentConfigError` occurs when the configuration cannot be read, or the configured providers
+/// cannot be built.
+#[derive(Error, Debug)]
+pub enum AgentConfigError {
+    /// The configuration file cannot be read
+    #[error("Failed to read the configuration file {path}: {1}", path = .0.display())]
+    Io(PathBuf, #[source] std::io::Error),
+    /// The configuration file extension is not `json`, or `toml`, `yaml` or `yml` with the
+    /// `config` feature
+    #[error("Unknown configuration format of {}, expected a json file, or a toml, yaml or yml file with the config feature", .0.display())]
+    UnknownFormat(PathBuf),
+    /// The TOML configuration is invalid
+    #[cfg(feature = "config")]
+    #[error("Invalid TOML configuration: {0}")]
+    Toml(#[from] toml::de::Error),
+    /// The JSON configuration is invalid
+    #[error("Invalid JSON configuration: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The YAML configuration is invalid
+    #[cfg(feature = "config")]
+    #[error("Invalid YAML configuration: {0}")]
+    Yaml(#[from] serde_norway::Error),
+    /// The app name is not a valid user agent suffix
+    #[error("Invalid app name: {0}")]
+    InvalidAppName(String),
+    /// A policy store ARN is malformed
+    #[error("Invalid policy store: {0}")]
+    InvalidPolicyStore(#[from] ClientError),
+    /// A policy store ARN is in another region than the client
+    #[error("The policy store {0} is not in the configured region {1}")]
+    RegionMismatch(String, String),
+    /// The refresh interval of a policy store is zero
+    #[error("The refresh interval of the policy store {0} must be at least one second")]
+    InvalidRefreshInterval(String),
+    /// A policy overlay file cannot be loaded
+    #[error("Failed to load the policy overlay: {0}")]
+    Overlay(#[from] OverlayError),
+    /// A `PolicySetProvider` cannot be built
+    #[error("Failed to build the policy set provider of {0}: {1}")]
+    PolicySetProvider(String, #[source] PolicySetProviderError),
+    /// An `EntityProvider` cannot be built
+    #[error("Failed to build the entity provider of {0}: {1}")]
+    EntityProvider(String, #[source] EntityProviderError),
+    /// One or more providers of a policy store cannot be refreshed
+    #[error("Failed to refresh the providers of {0}: {errors}", errors = join_errors(.1))]
+    Refresh(String, Vec<UpdateProviderDataError>),
+    /// The served `PolicySet` cannot be written to its snapshot path
+    #[error("Failed to write the policy set snapshot {path}: {1}", path = .0.display())]
+    Snapshot(PathBuf, String),
+}
+
+/// Joins the errors of the providers of a policy store for display.
+fn join_errors(errors: &[UpdateProviderDataError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// The configuration of the Amazon Verified Permissions providers of an agent.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentConfig {
+    /// The region of the client
+    pub region: String,
+    /// How the client gets its credentials, the default credentials chain if not set
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
+    /// The endpoint, timeouts and retries of the client
+    #[serde(default)]
+    pub client: ClientConfig,
+    /// The policy stores to load
+    pub policy_stores: Vec<PolicyStoreConfig>,
+}
+
+/// How the client gets its credentials.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum CredentialsConfig {
+    /// The default credentials chain
+    #[default]
+    Default,
+    /// The `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN` variables
+    Environment,
+    /// A profile of the shared config and credentials files
+    Profile {
+        /// The name of the profile
+        profile_name: String,
+    },
+    /// A role assumed with the default credentials chain
+    AssumeRole {
+        /// The ARN of the role to assume
+        role_arn: String,
+        /// The name of the role session
+        session_name: Option<String>,
+        /// The external id required by the trust policy of the role
+        external_id: Option<String>,
+        /// The duration of the role session in seconds
+        session_duration_secs: Option<u64>,
+    },
+}
+
+/// The endpoint, timeouts and retries of the client, see `ClientOptions`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    /// Overrides the resolved endpoint
+    pub endpoint_url: Option<String>,
+    /// Uses the FIPS endpoint of the region
+    pub use_fips: bool,
+    /// Uses the dual-stack endpoint of the region
+    pub use_dual_stack: bool,
+    /// The timeout of each attempt in milliseconds
+    pub attempt_timeout_ms: u64,
+    /// The timeout of the whole operation in milliseconds
+    pub operation_timeout_ms: Option<u64>,
+    /// The max attempts of each operation, including the first one
+    pub max_attempts: u32,
+    /// Appended to the user agent of the requests
+    pub app_name: Option<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            endpoint_url: None,
+            use_fips: false,
+            use_dual_stack: false,
+            attempt_timeout_ms: AVP_CLIENT_DEFAULT_TIMEOUT_MS,
+            operation_timeout_ms: None,
+            max_attempts: AVP_CLIENT_MAX_ATTEMPTS,
+            app_name: None,
+        }
+    }
+}
+
+impl TryFrom<&ClientConfig> for ClientOptions {
+    type Error = AgentConfigError;
+
+    fn try_from(config: &ClientConfig) -> Result<Self, Self::Error> {
+        let app_name = config
+            .app_name
+            .as_ref()
+            .map(|app_name| {
+                AppName::new(app_name.clone())
+                    .map_err(|e| AgentConfigError::InvalidAppName(e.to_string()))
+            })
+            .transpose()?;
+        Ok(Self {
+            endpoint_url: config.endpoint_url.clone(),
+            use_fips: config.use_fips,
+            use_dual_stack: config.use_dual_stack,
+            http_client: None,
+            attempt_timeout: Duration::from_millis(config.attempt_timeout_ms),
+            operation_timeout: config.operation_timeout_ms.map(Duration::from_millis),
+            max_attempts: config.max_attempts,
+            app_name,
+        })
+    }
+}
+
+/// A policy set filter in the CLI shorthand or the JSON syntax, see `PolicySetFilter`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum FilterConfig {
+    /// CLI shorthand representation, such as `policyTemplateId=template-1`
+    Cli(String),
+    /// JSON representation
+    Json(Value),
+}
+
+impl FilterConfig {
+    /// Returns the `PolicySetFilter` of this filter.
+    pub fn to_policy_set_filter(&self) -> PolicySetFilter<'_> {
+        match self {
+            Self::Cli(cli) => PolicySetFilter::Cli(cli),
+            Self::Json(value) => PolicySetFilter::Value(value.clone()),
+        }
+    }
+}
+
+impl From<FilterConfig> for OwnedPolicySetFilter {
+    fn from(filter: FilterConfig) -> Self {
+        match filter {
+            FilterConfig::Cli(cli) => Self::Cli(cli),
+            FilterConfig::Json(value) => Self::Value(value),
+        }
+    }
+}
+
+/// A policy store to load and the settings of its providers.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyStoreConfig {
+    /// The id or the ARN of the policy store
+    pub policy_store: String,
+    /// Loads only the policies matching the filter
+    pub filter: Option<FilterConfig>,
+    /// How often the providers are refreshed in seconds, at least one
+    #[serde(default = "default_refresh_interval_secs")]
+    pub refresh_interval_secs: u64,
+    /// Builds an `EntityProvider` serving the action entities of the schema
+    #[serde(default = "default_true")]
+    pub entities: bool,
+    /// The number of published `PolicySet` versions retained for `rollback_to`
+    #[serde(default = "default_history_depth")]
+    pub history_depth: usize,
+    /// Returns only the policies that could apply to each request
+    #[serde(default)]
+    pub slice_by_request: bool,
+    /// Applies the validation settings of the policy store
+    #[serde(default)]
+    pub honor_validation_settings: bool,
+    /// The maximum number of policies a refresh may remove
+    pub max_removed_policies: Option<usize>,
+    /// The maximum percentage of the served policies a refresh may remove
+    pub max_removed_percent: Option<u8>,
+    /// A file of local Cedar policies merged on top of the policy store policies
+    pub overlay_path: Option<PathBuf>,
+    /// Writes the served `PolicySet` in the Cedar JSON format after every refresh
+    pub snapshot_path: Option<PathBuf>,
+}
+
+const fn default_refresh_interval_secs() -> u64 {
+    DEFAULT_REFRESH_INTERVAL_SECS
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+const fn default_history_depth() -> usize {
+    DEFAULT_HISTORY_DEPTH
+}
+
+impl PolicyStoreConfig {
+    /// Returns the id of the policy store, checking that a policy store ARN is in `region`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AgentConfigError` if the ARN is malformed or in another region.
+    pub fn policy_store_id(&self, region: &str) -> Result<String, AgentConfigError> {
+        if !self.policy_store.starts_with("arn:") {
+            return Ok(self.policy_store.clone());
+        }
+        let arn = PolicyStoreArn::from_str(&self.policy_store)?;
+        match &arn.region {
+            Some(arn_region) if arn_region.as_ref() != region => Err(
+                AgentConfigError::RegionMismatch(self.policy_store.clone(), region.to_string()),
+            ),
+            _ => Ok(arn.policy_store_id),
+        }
+    }
+
+    /// Returns the `ProviderOptions` of the `PolicySetProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AgentConfigError` if the overlay file cannot be loaded.
+    pub fn provider_options(&self) -> Result<ProviderOptions, AgentConfigError> {
+        let deletion_guard = (self.max_removed_policies.is_some()
+            || self.max_removed_percent.is_some())
+        .then_some(DeletionGuard {
+            max_removed_policies: self.max_removed_policies,
+            max_removed_percent: self.max_removed_percent,
+        });
+        Ok(ProviderOptions {
+            history_depth: self.history_depth,
+            deletion_guard,
+            overlay: self
+                .overlay_path
+                .as_ref()
+                .map(PolicyOverlay::from_file)
+                .transpose()?,
+            slice_by_request: self.slice_by_request,
+            principal_loading: None,
+            honor_validation_settings: self.honor_validation_settings,
+        })
+    }
+}
+
+impl AgentConfig {
+    /// Reads the configuration from a file, in the format given by its `json` extension, or its
+    /// `toml`, `yaml` or `yml` extension with the `config` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AgentConfigError` if the file cannot be read or parsed.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, AgentConfigError> {
+        let path = path.as_ref();
+        let parse = match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "config")]
+            Some("toml") => Self::from_toml_str,
+            Some("json") => Self::from_json_str,
+            #[cfg(feature = "config")]
+            Some("yaml" | "yml") => Self::from_yaml_str,
+            _ => return Err(AgentConfigError::UnknownFormat(path.to_path_buf())),
+        };
+        let contents =
+            fs::read_to_string(path).map_err(|e| AgentConfigError::Io(path.to_path_buf(), e))?;
+        parse(&contents)
+    }
+
+    /// Parses a TOML configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AgentConfigError::Toml` if the configuration is invalid.
+    #[cfg(feature = "config")]
+    pub fn from_toml_str(config: &str) -> Result<Self, AgentConfigError> {
+        Ok(toml::from_str(config)?)
+    }
+
+    /// Parses a JSON configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AgentConfigError::Json` if the configuration is invalid.
+    pub fn from_json_str(config: &str) -> Result<Self, AgentConfigError> {
+        Ok(serde_json::from_str(config)?)
+    }
+
+    /// Parses a YAML configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AgentConfigError::Yaml` if the configuration is invalid.
+    #[cfg(feature = "config")]
+    pub fn from_yaml_str(config: &str) -> Result<Self, AgentConfigError> {
+        Ok(serde_norway::from_str(config)?)
+    }
+
+    /// Builds the configured Verified Permissions `Client`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AgentConfigError` if the client options are invalid.
+    pub async fn client(&self) -> Result<Client, AgentConfigError> {
+        let region = Region::new(self.region.clone());
+        let options = ClientOptions::try_from(&self.client)?;
+        let default_credentials = || async {
+            SharedCredentialsProvider::new(
+                DefaultCredentialsChain::builder()
+                    .region(region.clone())
+                    .build()
+                    .await,
+            )
+        };
+
+        let client = match &self.credentials {
+            CredentialsConfig::Default => verified_permissions_with_options(
+                region.clone(),
+                default_credentials().await,
+                &options,
+            ),
+            CredentialsConfig::Environment => verified_permissions_with_options(
+                region.clone(),
+                SharedCredentialsProvider::new(EnvironmentVariableCredentialsProvider::new()),
+                &options,
+            ),
+            CredentialsConfig::Profile { profile_name } => verified_permissions_with_options(
+                region.clone(),
+                SharedCredentialsProvider::new(
+                    ProfileFileCredentialsProvider::builder()
+                        .profile_name(profile_name)
+                        .build(),
+                ),
+                &options,
+            ),
+            CredentialsConfig::AssumeRole {
+                role_arn,
+                session_name,
+                external_id,
+                session_duration_secs,
+            } => {
+                let assume_role = AssumeRoleOptions {
+                    role_arn: role_arn.clone(),
+                    session_name: session_name
+                        .clone()
+                        .unwrap_or_else(|| AVP_CLIENT_DEFAULT_SESSION_NAME.to_string()),
+                    external_id: external_id.clone(),
+                    session_duration: session_duration_secs.map(Duration::from_secs),
+                    sts_options: ClientOptions::default(),
+                };
+                verified_permissions_assume_role(
+                    region.clone(),
+                    default_credentials().await,
+                    &assume_role,
+                    &options,
+                )
+                .await
+            }
+        };
+        Ok(client)
+    }
+}
+
+/// The providers built for a configured policy store.
+#[derive(Debug, Clone)]
+pub struct PolicyStoreProviders {
+    /// The id of the policy store
+    pub policy_store_id: String,
+    /// Serves the policies of the policy store
+    pub policy_set_provider: Arc<PolicySetProvider>,
+    /// Serves the action entities of the policy store schema, if configured
+    pub entity_provider: Option<Arc<EntityProvider>>,
+    /// How often the providers are refreshed
+    pub refresh_interval: Duration,
+    /// Where the served `PolicySet` is written after every refresh
+    pub snapshot_path: Option<PathBuf>,
+}
+
+impl PolicyStoreProviders {
+    /// Refreshes the providers and writes the snapshot of the served `PolicySet`. Every provider
+    /// is refreshed even if another one fails, and the snapshot is written once the
+    /// `PolicySetProvider` refreshed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AgentConfigError::Refresh` with the errors of every provider that failed to
+    /// refresh, or an `AgentConfigError::Snapshot` if the snapshot cannot be written.
+    #[instrument(skip(self), fields(policy_store_id = %self.policy_store_id), err(Debug))]
+    pub async fn refresh(&self) -> Result<(), AgentConfigError> {
+        let policy_set_result = self.policy_set_provider.update_provider_data().await;
+        let entity_result = match &self.entity_provider {
+            Some(entity_provider) => entity_provider.update_provider_data().await,
+            None => Ok(()),
+        };
+
+        let policy_set_refreshed = policy_set_result.is_ok();
+        let errors = [policy_set_result.err(), entity_result.err()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        if policy_set_refreshed {
+            self.write_snapshot().await?;
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AgentConfigError::Refresh(
+                self.policy_store_id.clone(),
+                errors,
+            ))
+        }
+    }
+
+    /// Writes the served `PolicySet` in the Cedar JSON format to the snapshot path, if one is
+    /// configured. The snapshot is written to a temporary file in the same directory and renamed
+    /// over the snapshot path, so that readers never see a partially written snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AgentConfigError::Snapshot` if the snapshot cannot be written.
+    pub async fn write_snapshot(&self) -> Result<(), AgentConfigError> {
+        let Some(path) = &self.snapshot_path else {
+            return Ok(());
+        };
+        let snapshot_error = |e: String| AgentConfigError::Snapshot(path.clone(), e);
+
+        let policy_set = self.policy_set_provider.policy_set().await;
+        let json = policy_set
+            .as_ref()
+            .clone()
+            .to_json()
+            .map_err(|e| snapshot_error(e.to_string()))?;
+        let contents =
+            serde_json::to_string_pretty(&json).map_err(|e| snapshot_error(e.to_string()))?;
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| snapshot_error("the snapshot path has no file name".to_string()))?;
+        let mut temp_name = OsString::from(".");
+        temp_name.push(file_name);
+        temp_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            SNAPSHOT_WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp_path = path.with_file_name(temp_name);
+        if let Err(e) = write_and_rename(&temp_path, path, contents).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(snapshot_error(e.to_string()));
+        }
+        debug!("Wrote policy set snapshot: path={}", path.display());
+        Ok(())
+    }
+}
+
+/// Counts snapshot writes, so that concurrent writes use distinct temporary files.
+static SNAPSHOT_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes `contents` to `temp_path` and renames it to `path`.
+async fn write_and_rename(temp_path: &Path, path: &Path, contents: String) -> std::io::Result<()> {
+    tokio::fs::write(temp_path, contents).await?;
+    tokio::fs::rename(temp_path, path).await
+}
+
+/// Serves the action entities of the `EntityProvider`, or no entities when the policy store is
+/// configured without one, so that the providers can back a `simple::Authorizer` directly.
+#[async_trait]
+impl SimpleEntityProvider for PolicyStoreProviders {
+    async fn get_entities(
+        &self,
+        request: &Request,
+    ) -> Result<Arc<Entities>, SimpleEntityProviderError> {
+        match &self.entity_provider {
+            Some(entity_provider) => entity_provider.get_entities(request).await,
+            None => Ok(Arc::new(Entities::empty())),
+        }
+    }
+}
+
+/// The client and the providers built from an `AgentConfig`.
+#[derive(Debug)]
+pub struct AgentProviders {
+    /// The client shared by the providers
+    client: Client,
+    /// The providers of each configured policy store, in configuration order
+    policy_stores: Vec<PolicyStoreProviders>,
+}
+
+impl AgentProviders {
+    /// Builds the client and the providers of every configured policy store, and writes their
+    /// first snapshots. Must be called from a multi-threaded Tokio runtime, since the providers
+    /// load their data on construction.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AgentConfigError` if the client or any provider cannot be built.
+    #[instrument(skip(config), err(Debug))]
+    pub async fn from_config(config: &AgentConfig) -> Result<Self, AgentConfigError> {
+        let client = config.client().await?;
+        Self::from_config_with_client(config, client).await
+    }
+
+    /// Builds the providers of every configured policy store with the given client, ignoring the
+    /// configured client and credentials.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AgentConfigError` if any provider cannot be built.
+    #[instrument(skip(config, client), err(Debug))]
+    pub async fn from_config_with_client(
+        config: &AgentConfig,
+        client: Client,
+    ) -> Result<Self, AgentConfigError> {
+        let mut policy_stores = Vec::with_capacity(config.policy_stores.len());
+        for policy_store in &config.policy_stores {
+            let policy_store_id = policy_store.policy_store_id(&config.region)?;
+            if policy_store.refresh_interval_secs == 0 {
+                return Err(AgentConfigError::InvalidRefreshInterval(policy_store_id));
+            }
+            let policy_set_provider = PolicySetProvider::from_client_with_options(
+                policy_store_id.clone(),
+                policy_store
+                    .filter
+                    .as_ref()
+                    .map(FilterConfig::to_policy_set_filter),
+                client.clone(),
+                policy_store.provider_options()?,
+            )
+            .map_err(|e| AgentConfigError::PolicySetProvider(policy_store_id.clone(), e))?;
+            let entity_provider = policy_store
+                .entities
+                .then(|| EntityProvider::from_client(policy_store_id.clone(), client.clone()))
+                .transpose()
+                .map_err(|e| AgentConfigError::EntityProvider(policy_store_id.clone(), e))?;
+
+            let providers = PolicyStoreProviders {
+                policy_store_id,
+                policy_set_provider: Arc::new(policy_set_provider),
+                entity_provider: entity_provider.map(Arc::new),
+                refresh_interval: Duration::from_secs(policy_store.refresh_interval_secs),
+                snapshot_path: policy_store.snapshot_path.clone(),
+            };
+            providers.write_snapshot().await?;
+            info!(
+                "Built providers of policy store: policy_store_id={}",
+                providers.policy_store_id
+            );
+            policy_stores.push(providers);
+        }
+        Ok(Self {
+            client,
+            policy_stores,
+        })
+    }
+
+    /// Returns the client shared by the providers.
+    pub const fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Returns the providers of every configured policy store, in configuration order.
+    pub fn policy_stores(&self) -> &[PolicyStoreProviders] {
+        &self.policy_stores
+    }
+
+    /// Returns the providers of a policy store.
+    pub fn policy_store(&self, policy_store_id: &str) -> Option<&PolicyStoreProviders> {
+        self.policy_stores
+            .iter()
+            .find(|providers| providers.policy_store_id == policy_store_id)
+    }
+
+    /// Spawns a task per policy store refreshing its providers at its refresh interval.
+    pub fn spawn_refresh_tasks(&self) -> Vec<JoinHandle<()>> {
+        self.policy_stores
+            .iter()
+            .cloned()
+            .map(|providers| {
+                tokio::spawn(async move {
+                    let mut interval = tokio::time::interval(providers.refresh_interval);
+                    interval.tick().await;
+                    loop {
+                        interval.tick().await;
+                        if let Err(error) = providers.refresh().await {
+                            error!("Failed to refresh the policy store providers: {error:?}");
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cedar_policy::PolicySet;
+    use serde_json::json;
+
+    use crate::private::sources::test::build_client;
+    use crate::public::policy_set_provider::test::{
+        get_policy_event, list_policies_event, list_templates_event, POLICY_ID, POLICY_STORE_ID,
+        STATIC_POLICY,
+    };
+    use crate::public::schema_provider::test::{build_schema_event, SCHEMA};
+
+    use super::{AgentConfig, AgentConfigError, AgentProviders};
+    #[cfg(feature = "config")]
+    use super::{CredentialsConfig, FilterConfig, DEFAULT_REFRESH_INTERVAL_SECS};
+
+    #[cfg(feature = "config")]
+    const TOML: &str = r#"
+        region = "us-east-1"
+
+        [credentials]
+        mode = "profile"
+        profile_name = "security"
+
+        [client]
+        endpoint_url = "http://localhost:8080"
+        max_attempts = 3
+
+        [[policy_stores]]
+        policy_store = "arn:aws:verifiedpermissions::123456789012:policy-store/ps-1"
+        filter = "policyTemplateId=template-1"
+        refresh_interval_secs = 30
+
+        [[policy_stores]]
+        policy_store = "ps-2"
+        filter = { principal = { identifier = { entityType = "User", entityId = "alice" } } }
+        entities = false
+    "#;
+
+    #[cfg(feature = "config")]
+    const YAML: &str = r"
+        region: us-east-1
+        credentials:
+          mode: profile
+          profile_name: security
+        client:
+          endpoint_url: http://localhost:8080
+          max_attempts: 3
+        policy_stores:
+          - policy_store: arn:aws:verifiedpermissions::123456789012:policy-store/ps-1
+            filter: policyTemplateId=template-1
+            refresh_interval_secs: 30
+          - policy_store: ps-2
+            filter:
+              principal:
+                identifier:
+                  entityType: User
+                  entityId: alice
+            entities: false
+    ";
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn toml_json_and_yaml_configurations_are_equivalent() {
+        let toml = AgentConfig::from_toml_str(TOML).unwrap();
+        let json = AgentConfig::from_json_str(
+            &json!({
+                "region": "us-east-1",
+                "credentials": { "mode": "profile", "profile_name": "security" },
+                "client": { "endpoint_url": "http://localhost:8080", "max_attempts": 3 },
+                "policy_stores": [
+                    {
+                        "policy_store": "arn:aws:verifiedpermissions::123456789012:policy-store/ps-1",
+                        "filter": "policyTemplateId=template-1",
+                        "refresh_interval_secs": 30
+                    },
+                    {
+                        "policy_store": "ps-2",
+                        "filter": { "principal": { "identifier": { "entityType": "User", "entityId": "alice" } } },
+                        "entities": false
+                    }
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let yaml = AgentConfig::from_yaml_str(YAML).unwrap();
+
+        assert_eq!(toml, json);
+        assert_eq!(toml, yaml);
+        assert_eq!(
+            toml.credentials,
+            CredentialsConfig::Profile {
+                profile_name: "security".to_string()
+            }
+        );
+        assert_eq!(toml.client.max_attempts, 3);
+        assert_eq!(
+            toml.policy_stores[0].filter,
+            Some(FilterConfig::Cli("policyTemplateId=template-1".to_string()))
+        );
+        assert!(matches!(
+            toml.policy_stores[1].filter,
+            Some(FilterConfig::Json(_))
+        ));
+        assert_eq!(
+            toml.policy_stores[1].refresh_interval_secs,
+            DEFAULT_REFRESH_INTERVAL_SECS
+        );
+        assert_eq!(
+            toml.policy_stores[0].policy_store_id("us-east-1").unwrap(),
+            "ps-1"
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn invalid_configurations_are_rejected() {
+        assert!(matches!(
+            AgentConfig::from_toml_str("region = \"us-east-1\"\npolicy_stores = []\nunknown = 1"),
+            Err(AgentConfigError::Toml(_))
+        ));
+        assert!(matches!(
+            AgentConfig::from_file("agent.ini"),
+            Err(AgentConfigError::UnknownFormat(_))
+        ));
+
+        let config = AgentConfig::from_yaml_str(
+            "region: us-east-1\npolicy_stores:\n  - policy_store: arn:aws:verifiedpermissions:eu-west-1:123456789012:policy-store/ps-1",
+        )
+        .unwrap();
+        assert!(matches!(
+            config.policy_stores[0].policy_store_id(&config.region),
+            Err(AgentConfigError::RegionMismatch(..))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn from_config_builds_the_providers_and_writes_the_snapshot() {
+        let snapshot_path = std::env::temp_dir().join(format!(
+            "avp-local-agent-agent-config-snapshot-{}.json",
+            std::process::id()
+        ));
+        let config = AgentConfig::from_json_str(
+            &json!({
+                "region": "us-east-1",
+                "policy_stores": [{
+                    "policy_store": POLICY_STORE_ID,
+                    "entities": false,
+                    "snapshot_path": snapshot_path
+                }]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+        ]);
+
+        let providers = AgentProviders::from_config_with_client(&config, client)
+            .await
+            .unwrap();
+
+        let policy_store = providers.policy_store(POLICY_STORE_ID).unwrap();
+        assert!(policy_store.entity_provider.is_none());
+        assert_eq!(
+            policy_store
+                .policy_set_provider
+                .policy_set()
+                .await
+                .policies()
+                .count(),
+            1
+        );
+        let snapshot =
+            PolicySet::from_json_str(std::fs::read_to_string(&snapshot_path).unwrap()).unwrap();
+        assert!(snapshot.policy(&POLICY_ID.parse().unwrap()).is_some());
+        let snapshot_name = snapshot_path.file_name().unwrap().to_str().unwrap();
+        assert!(!std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(Result::ok)
+            .any(|entry| entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(&format!(".{snapshot_name}")))));
+        std::fs::remove_file(snapshot_path).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn from_config_rejects_a_zero_refresh_interval() {
+        let config = AgentConfig::from_json_str(
+            &json!({
+                "region": "us-east-1",
+                "policy_stores": [{ "policy_store": POLICY_STORE_ID, "refresh_interval_secs": 0 }]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let error = AgentProviders::from_config_with_client(&config, build_client(vec![]))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            AgentConfigError::InvalidRefreshInterval(policy_store_id) if policy_store_id == POLICY_STORE_ID
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn refresh_refreshes_every_provider_and_reports_all_failures() {
+        let config = AgentConfig::from_json_str(
+            &json!({
+                "region": "us-east-1",
+                "policy_stores": [{ "policy_store": POLICY_STORE_ID }]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            build_schema_event(SCHEMA, "2024-01-01T00:00:00Z"),
+        ]);
+        let providers = AgentProviders::from_config_with_client(&config, client)
+            .await
+            .unwrap();
+
+        let error = providers.policy_stores()[0].refresh().await.unwrap_err();
+        assert!(matches!(
+            error,
+            AgentConfigError::Refresh(policy_store_id, errors)
+                if policy_store_id == POLICY_STORE_ID && errors.len() == 2
+        ));
+    }
+}
@@ -0,0 +1,477 @@
+//! A `tower::Layer` authorizing HTTP requests against the providers of a policy store, for axum,
+//! hyper and tonic services.
+//!
+//! The layer builds a Cedar request from the principal, action, resource and context returned by
+//! extractor closures over the request `Parts`, and evaluates it locally:
+//!
+//! * allowed requests reach the inner service with an `AuthorizationDecision` extension holding
+//!   the decision and the determining policy ids,
+//! * denied requests, and requests whose action, resource or context cannot be extracted, are
+//!   answered with `403 Forbidden`,
+//! * requests without a principal are answered with `401 Unauthorized`,
+//! * provider failures are answered with `500 Internal Server Error`.
+//!
+//! With `with_enforcement(false)` denied requests also reach the inner service, which decides
+//! from the `AuthorizationDecision` extension.
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+
+use cedar_local_agent::public::simple::{Authorizer, AuthorizerConfigBuilder};
+use cedar_policy::{Context, Decision, Entities, EntityUid, PolicyId, Request};
+use http::request::Parts;
+use http::{Response, StatusCode};
+use thiserror::Error;
+use tower::{Layer, Service};
+use tracing::{debug, error};
+
+use super::agent_config::PolicyStoreProviders;
+use super::policy_set_provider::PolicySetProvider;
+
+/// `AuthorizationLayerError` can occur when building an `AuthorizationLayer`.
+#[derive(Error, Debug)]
+pub enum AuthorizationLayerError {
+    /// The authorizer of the layer cannot be built
+    #[error("Invalid authorization layer configuration: {0}")]
+    Configuration(String),
+}
+
+/// Extracts an entity of the Cedar request from the request `Parts`.
+pub type EntityExtractor = Arc<dyn Fn(&Parts) -> Option<EntityUid> + Send + Sync>;
+
+/// Extracts the context of the Cedar request from the request `Parts`.
+pub type ContextExtractor = Arc<dyn Fn(&Parts) -> Option<Context> + Send + Sync>;
+
+/// The decision of a request, attached to its extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationDecision {
+    /// Whether the request is allowed
+    pub decision: Decision,
+    /// The ids of the policies that determined the decision, sorted
+    pub determining_policies: Vec<PolicyId>,
+}
+
+/// Why a request was not passed to the inner service.
+enum Rejection {
+    /// The request has no principal
+    Unauthenticated,
+    /// The request is denied
+    Forbidden,
+    /// A provider failed
+    Internal,
+}
+
+impl Rejection {
+    /// The HTTP status of the rejection.
+    const fn status(&self) -> StatusCode {
+        match self {
+            Self::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// The state shared by the services of a layer.
+#[derive(Clone)]
+struct Shared {
+    /// Evaluates the requests with the providers
+    authorizer: Arc<Authorizer<PolicySetProvider, PolicyStoreProviders>>,
+    /// Extracts the principal
+    principal: EntityExtractor,
+    /// Extracts the action
+    action: EntityExtractor,
+    /// Extracts the resource
+    resource: EntityExtractor,
+    /// Extracts the context, empty when not set
+    context: Option<ContextExtractor>,
+    /// Whether denied requests are answered with `403 Forbidden`
+    enforce: bool,
+}
+
+impl Shared {
+    /// Evaluates the request, returning the decision to attach or the rejection to answer.
+    async fn authorize(&self, parts: &Parts) -> Result<AuthorizationDecision, Rejection> {
+        let Some(principal) = (self.principal)(parts) else {
+            debug!("Unauthenticated request to {}", parts.uri.path());
+            return Err(Rejection::Unauthenticated);
+        };
+        let Some(request) = self.request(principal, parts) else {
+            debug!("Unmapped request to {}", parts.uri.path());
+            return self.deny(AuthorizationDecision {
+                decision: Decision::Deny,
+                determining_policies: Vec::new(),
+            });
+        };
+
+        let response = self
+            .authorizer
+            .is_authorized(&request, &Entities::empty())
+            .await
+            .map_err(|e| {
+                error!("Failed to authorize the request: {e:?}");
+                Rejection::Internal
+            })?;
+        let mut determining_policies = response.diagnostics().reason().cloned().collect::<Vec<_>>();
+        determining_policies.sort_by_key(ToString::to_string);
+        let decision = AuthorizationDecision {
+            decision: response.decision(),
+            determining_policies,
+        };
+
+        if decision.decision == Decision::Allow {
+            Ok(decision)
+        } else {
+            self.deny(decision)
+        }
+    }
+
+    /// Builds the Cedar request, or `None` if the action, resource or context is missing.
+    fn request(&self, principal: EntityUid, parts: &Parts) -> Option<Request> {
+        let context = match &self.context {
+            Some(context) => context(parts)?,
+            None => Context::empty(),
+        };
+        Request::new(
+            principal,
+            (self.action)(parts)?,
+            (self.resource)(parts)?,
+            context,
+            None,
+        )
+        .map_err(|e| debug!("Invalid request: {e}"))
+        .ok()
+    }
+
+    /// Rejects a denied request when enforcing, and passes it on otherwise.
+    fn deny(&self, decision: AuthorizationDecision) -> Result<AuthorizationDecision, Rejection> {
+        if self.enforce {
+            Err(Rejection::Forbidden)
+        } else {
+            Ok(decision)
+        }
+    }
+}
+
+/// A `tower::Layer` authorizing requests against the providers of one policy store.
+///
+/// # Examples
+///
+/// ```no_run
+/// use avp_local_agent::public::agent_config::{AgentConfig, AgentProviders};
+/// use avp_local_agent::public::authorization_layer::AuthorizationLayer;
+/// use cedar_policy::EntityUid;
+///
+/// # async fn example() {
+/// let config = AgentConfig::from_file("agent.toml").unwrap();
+/// let providers = AgentProviders::from_config(&config).await.unwrap();
+/// let layer = AuthorizationLayer::new(
+///     providers.policy_stores()[0].clone(),
+///     |parts| {
+///         let id = parts.headers.get("x-user-id")?.to_str().ok()?;
+///         EntityUid::from_type_name_and_id("User".parse().ok()?, id.parse().ok()?).into()
+///     },
+///     |parts| format!(r#"Action::"{}""#, parts.method).parse().ok(),
+///     |parts| format!(r#"Path::"{}""#, parts.uri.path()).parse().ok(),
+/// )
+/// .unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AuthorizationLayer {
+    /// The state shared by the services of the layer
+    shared: Arc<Shared>,
+}
+
+impl Debug for AuthorizationLayer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthorizationLayer")
+            .field("enforce", &self.shared.enforce)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuthorizationLayer {
+    /// Creates an `AuthorizationLayer` evaluating requests against `providers`, with the
+    /// principal, action and resource returned by the extractors. Requests without a principal
+    /// are answered with `401 Unauthorized`, and requests without an action or resource are
+    /// denied.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuthorizationLayerError::Configuration` if the authorizer cannot be built.
+    pub fn new<P, A, R>(
+        providers: PolicyStoreProviders,
+        principal: P,
+        action: A,
+        resource: R,
+    ) -> Result<Self, AuthorizationLayerError>
+    where
+        P: Fn(&Parts) -> Option<EntityUid> + Send + Sync + 'static,
+        A: Fn(&Parts) -> Option<EntityUid> + Send + Sync + 'static,
+        R: Fn(&Parts) -> Option<EntityUid> + Send + Sync + 'static,
+    {
+        let authorizer = Arc::new(Authorizer::new(
+            AuthorizerConfigBuilder::default()
+                .policy_set_provider(providers.policy_set_provider.clone())
+                .entity_provider(Arc::new(providers))
+                .build()
+                .map_err(|e| AuthorizationLayerError::Configuration(e.to_string()))?,
+        ));
+
+        Ok(Self {
+            shared: Arc::new(Shared {
+                authorizer,
+                principal: Arc::new(principal),
+                action: Arc::new(action),
+                resource: Arc::new(resource),
+                context: None,
+                enforce: true,
+            }),
+        })
+    }
+
+    /// Builds the context of the Cedar requests with `context`. Requests for which it returns
+    /// `None` are denied.
+    #[must_use]
+    pub fn with_context<C>(self, context: C) -> Self
+    where
+        C: Fn(&Parts) -> Option<Context> + Send + Sync + 'static,
+    {
+        self.map_shared(|shared| Shared {
+            context: Some(Arc::new(context)),
+            ..shared
+        })
+    }
+
+    /// Whether denied requests are answered with `403 Forbidden`, the default, or passed to the
+    /// inner service with a `Deny` `AuthorizationDecision`.
+    #[must_use]
+    pub fn with_enforcement(self, enforce: bool) -> Self {
+        self.map_shared(|shared| Shared { enforce, ..shared })
+    }
+
+    /// Rebuilds the shared state, copying it when it is shared with clones of the layer or its
+    /// services, which keep their configuration.
+    fn map_shared(self, f: impl FnOnce(Shared) -> Shared) -> Self {
+        let shared = Arc::unwrap_or_clone(self.shared);
+        Self {
+            shared: Arc::new(f(shared)),
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthorizationLayer {
+    type Service = AuthorizationService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthorizationService {
+            inner,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// The service created by an `AuthorizationLayer`.
+#[derive(Clone)]
+pub struct AuthorizationService<S> {
+    /// The authorized service
+    inner: S,
+    /// The state shared by the services of the layer
+    shared: Arc<Shared>,
+}
+
+impl<S: Debug> Debug for AuthorizationService<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthorizationService")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, B, ResBody> Service<http::Request<B>> for AuthorizationService<S>
+where
+    S: Service<http::Request<B>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    B: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        // The ready service is moved into the future and replaced by a clone.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let shared = self.shared.clone();
+
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            match shared.authorize(&parts).await {
+                Ok(decision) => {
+                    parts.extensions.insert(decision);
+                    inner.call(http::Request::from_parts(parts, body)).await
+                }
+                Err(rejection) => {
+                    let mut response = Response::new(ResBody::default());
+                    *response.status_mut() = rejection.status();
+                    Ok(response)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use cedar_policy::{Context, Decision, EntityUid, PolicyId};
+    use http::request::Parts;
+    use http::{Request, Response, StatusCode};
+    use tower::{service_fn, Layer, ServiceExt};
+
+    use crate::public::policy_set_provider::test::{build_policy_store_providers, POLICY_ID};
+
+    use super::{AuthorizationDecision, AuthorizationLayer};
+
+    fn entity(entity_type: &str, id: &str) -> Option<EntityUid> {
+        format!(r#"{entity_type}::"{id}""#).parse().ok()
+    }
+
+    fn layer(statement: &str) -> AuthorizationLayer {
+        AuthorizationLayer::new(
+            build_policy_store_providers(statement),
+            |parts: &Parts| entity("User", parts.headers.get("x-user-id")?.to_str().ok()?),
+            |parts: &Parts| entity("Action", parts.method.as_str()),
+            |parts: &Parts| entity("Path", parts.uri.path()),
+        )
+        .unwrap()
+    }
+
+    /// Sends a request through the layer, returning the status and the decision seen by the
+    /// inner service.
+    async fn send(
+        layer: &AuthorizationLayer,
+        method: &str,
+        user: Option<&str>,
+    ) -> (StatusCode, Option<AuthorizationDecision>) {
+        let service = layer.layer(service_fn(|request: Request<String>| async move {
+            let mut response = Response::new(String::new());
+            if let Some(decision) = request.extensions().get::<AuthorizationDecision>() {
+                response.extensions_mut().insert(decision.clone());
+            }
+            Ok::<_, Infallible>(response)
+        }));
+        let mut request = Request::builder().method(method).uri("/photos/beach.jpg");
+        if let Some(user) = user {
+            request = request.header("x-user-id", user);
+        }
+        let response = service
+            .oneshot(request.body(String::new()).unwrap())
+            .await
+            .unwrap();
+        (
+            response.status(),
+            response
+                .extensions()
+                .get::<AuthorizationDecision>()
+                .cloned(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn requests_are_allowed_denied_or_unauthenticated() {
+        let layer = layer(
+            r#"permit(principal == User::"alice", action == Action::"GET", resource == Path::"/photos/beach.jpg");"#,
+        );
+
+        assert_eq!(
+            send(&layer, "GET", Some("alice")).await,
+            (
+                StatusCode::OK,
+                Some(AuthorizationDecision {
+                    decision: Decision::Allow,
+                    determining_policies: vec![PolicyId::new(POLICY_ID)],
+                })
+            )
+        );
+        assert_eq!(
+            send(&layer, "DELETE", Some("alice")).await,
+            (StatusCode::FORBIDDEN, None)
+        );
+        assert_eq!(
+            send(&layer, "GET", Some("bob")).await,
+            (StatusCode::FORBIDDEN, None)
+        );
+        assert_eq!(
+            send(&layer, "GET", None).await,
+            (StatusCode::UNAUTHORIZED, None)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn context_and_enforcement_are_configurable() {
+        let layer =
+            layer(r#"permit(principal, action, resource) when { context.tenant == "acme" };"#)
+                .with_context(|parts: &Parts| {
+                    let tenant = parts.headers.get("x-user-id")?.to_str().ok()?;
+                    Context::from_json_value(serde_json::json!({ "tenant": tenant }), None).ok()
+                })
+                .with_enforcement(false);
+
+        assert_eq!(
+            send(&layer, "GET", Some("acme")).await,
+            (
+                StatusCode::OK,
+                Some(AuthorizationDecision {
+                    decision: Decision::Allow,
+                    determining_policies: vec![PolicyId::new(POLICY_ID)],
+                })
+            )
+        );
+        assert_eq!(
+            send(&layer, "GET", Some("other")).await,
+            (
+                StatusCode::OK,
+                Some(AuthorizationDecision {
+                    decision: Decision::Deny,
+                    determining_policies: Vec::new(),
+                })
+            )
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn cloned_layers_are_configured_independently() {
+        let enforcing = layer(
+            r#"permit(principal == User::"alice", action == Action::"GET", resource == Path::"/photos/beach.jpg");"#,
+        );
+        let _service = enforcing.layer(service_fn(|_: Request<String>| async {
+            Ok::<_, Infallible>(Response::new(String::new()))
+        }));
+        let permissive = enforcing.clone().with_enforcement(false);
+
+        assert_eq!(
+            send(&enforcing, "DELETE", Some("alice")).await,
+            (StatusCode::FORBIDDEN, None)
+        );
+        assert_eq!(
+            send(&permissive, "DELETE", Some("alice")).await,
+            (
+                StatusCode::OK,
+                Some(AuthorizationDecision {
+                    decision: Decision::Deny,
+                    determining_policies: Vec::new(),
+                })
+            )
+        );
+    }
+}
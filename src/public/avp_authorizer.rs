@@ -0,0 +1,548 @@
+//! Evaluates Amazon Verified Permissions `IsAuthorized` and `BatchIsAuthorized` inputs locally and
+//! returns outputs in the shape of the Amazon Verified Permissions responses.
+//!
+//! Code written against the AWS SDK can swap the remote call for the `AvpLocalAuthorizer` without
+//! changing how inputs are built or outputs are read. The policy store id of the input is not used,
+//! the policies are the ones served by the `SimplePolicySetProvider` of the authorizer.
+#[cfg(feature = "token")]
+use std::collections::HashMap;
+#[cfg(feature = "token")]
+use std::sync::Arc;
+
+use aws_sdk_verifiedpermissions::error::BuildError;
+use aws_sdk_verifiedpermissions::operation::batch_is_authorized::{
+    BatchIsAuthorizedInput, BatchIsAuthorizedOutput,
+};
+use aws_sdk_verifiedpermissions::operation::is_authorized::{
+    IsAuthorizedInput, IsAuthorizedOutput,
+};
+#[cfg(feature = "token")]
+use aws_sdk_verifiedpermissions::operation::is_authorized_with_token::{
+    IsAuthorizedWithTokenInput, IsAuthorizedWithTokenOutput,
+};
+use aws_sdk_verifiedpermissions::types::{
+    BatchIsAuthorizedOutputItem, Decision, DeterminingPolicyItem, EvaluationErrorItem,
+};
+#[cfg(feature = "token")]
+use aws_sdk_verifiedpermissions::types::{ContextDefinition, EntitiesDefinition};
+use cedar_local_agent::public::simple::{Authorizer, AuthorizerError};
+use cedar_local_agent::public::{SimpleEntityProvider, SimplePolicySetProvider};
+use cedar_policy::Response;
+use thiserror::Error;
+use tracing::{debug, instrument};
+
+use crate::private::translator::avp_to_cedar::{entities, request, CedarRequest};
+use crate::private::translator::error::TranslatorException;
+#[cfg(feature = "token")]
+use crate::public::token_verifier::{TokenError, TokenVerifier, VerifiedToken};
+
+/// `AvpAuthorizerError` can occur when an input cannot be evaluated locally.
+#[derive(Error, Debug)]
+pub enum AvpAuthorizerError {
+    /// The input cannot be translated to a Cedar request, Amazon Verified Permissions would
+    /// reject it with a validation exception
+    #[error("The input failed to be translated to a Cedar request: {0}")]
+    Translate(#[from] TranslatorException),
+    /// The policy set or entity provider of the authorizer failed
+    #[error("The authorizer failed to evaluate the request: {0}")]
+    Authorizer(#[from] AuthorizerError),
+    /// The Amazon Verified Permissions output failed to be built
+    #[error("The output failed to be built: {0}")]
+    Output(#[from] BuildError),
+    /// The tokens of an `IsAuthorizedWithToken` input failed to be verified
+    #[cfg(feature = "token")]
+    #[error("The tokens failed to be verified: {0}")]
+    Token(#[from] TokenError),
+    /// An `IsAuthorizedWithToken` input was evaluated without a `TokenVerifier`
+    #[cfg(feature = "token")]
+    #[error("No token verifier is configured")]
+    NoTokenVerifier,
+}
+
+/// The Amazon Verified Permissions shaped response of a Cedar evaluation.
+struct Evaluation {
+    decision: Decision,
+    determining_policies: Vec<DeterminingPolicyItem>,
+    errors: Vec<EvaluationErrorItem>,
+}
+
+impl TryFrom<&Response> for Evaluation {
+    type Error = BuildError;
+
+    fn try_from(response: &Response) -> Result<Self, Self::Error> {
+        let decision = match response.decision() {
+            cedar_policy::Decision::Allow => Decision::Allow,
+            cedar_policy::Decision::Deny => Decision::Deny,
+        };
+
+        let mut policy_ids = response
+            .diagnostics()
+            .reason()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        policy_ids.sort();
+        let determining_policies = policy_ids
+            .into_iter()
+            .map(|policy_id| {
+                DeterminingPolicyItem::builder()
+                    .policy_id(policy_id)
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let errors = response
+            .diagnostics()
+            .errors()
+            .map(|error| {
+                EvaluationErrorItem::builder()
+                    .error_description(error.to_string())
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            decision,
+            determining_policies,
+            errors,
+        })
+    }
+}
+
+/// Evaluates Amazon Verified Permissions authorization inputs with a `simple::Authorizer`.
+///
+/// Determining policies are reported with the Amazon Verified Permissions policy ids of the
+/// served `PolicySet`, and evaluation errors are reported in the `errors` of the output.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use aws_sdk_verifiedpermissions::operation::is_authorized::IsAuthorizedInput;
+/// use avp_local_agent::public::avp_authorizer::AvpLocalAuthorizer;
+/// use avp_local_agent::public::entity_provider::EntityProvider;
+/// use avp_local_agent::public::policy_set_provider::PolicySetProvider;
+/// use cedar_local_agent::public::simple::{Authorizer, AuthorizerConfigBuilder};
+///
+/// # async fn example(
+/// #     policy_set_provider: Arc<PolicySetProvider>,
+/// #     entity_provider: Arc<EntityProvider>,
+/// #     input: IsAuthorizedInput,
+/// # ) {
+/// let authorizer = AvpLocalAuthorizer::new(Authorizer::new(
+///     AuthorizerConfigBuilder::default()
+///         .policy_set_provider(policy_set_provider)
+///         .entity_provider(entity_provider)
+///         .build()
+///         .unwrap(),
+/// ));
+///
+/// let output = authorizer.is_authorized(&input).await.unwrap();
+/// println!("{:?}", output.decision());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AvpLocalAuthorizer<P, E>
+where
+    P: SimplePolicySetProvider + 'static,
+    E: SimpleEntityProvider + 'static,
+{
+    /// Evaluates the translated Cedar requests
+    authorizer: Authorizer<P, E>,
+    /// Verifies the tokens of `IsAuthorizedWithToken` inputs
+    #[cfg(feature = "token")]
+    token_verifier: Option<Arc<TokenVerifier>>,
+}
+
+impl<P, E> AvpLocalAuthorizer<P, E>
+where
+    P: SimplePolicySetProvider,
+    E: SimpleEntityProvider,
+{
+    /// Creates an `AvpLocalAuthorizer` evaluating inputs with `authorizer`.
+    pub const fn new(authorizer: Authorizer<P, E>) -> Self {
+        Self {
+            authorizer,
+            #[cfg(feature = "token")]
+            token_verifier: None,
+        }
+    }
+
+    /// Verifies the tokens of `IsAuthorizedWithToken` inputs with `token_verifier`.
+    #[cfg(feature = "token")]
+    #[must_use]
+    pub fn with_token_verifier(mut self, token_verifier: Arc<TokenVerifier>) -> Self {
+        self.token_verifier = Some(token_verifier);
+        self
+    }
+
+    /// Evaluates an `IsAuthorized` input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AvpAuthorizerError` if the input cannot be translated or a provider of the
+    /// authorizer fails.
+    #[instrument(skip_all, err(Debug))]
+    pub async fn is_authorized(
+        &self,
+        input: &IsAuthorizedInput,
+    ) -> Result<IsAuthorizedOutput, AvpAuthorizerError> {
+        let CedarRequest { request, entities } = CedarRequest::try_from(input)?;
+        let response = self.authorizer.is_authorized(&request, &entities).await?;
+        let evaluation = Evaluation::try_from(&response)?;
+
+        Ok(IsAuthorizedOutput::builder()
+            .decision(evaluation.decision)
+            .set_determining_policies(Some(evaluation.determining_policies))
+            .set_errors(Some(evaluation.errors))
+            .build()?)
+    }
+
+    /// Evaluates an `IsAuthorizedWithToken` input. The principal, its groups and the context are
+    /// derived from the tokens by the `TokenVerifier`, and claims of the access token replace
+    /// context attributes of the input with the same name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AvpAuthorizerError` if no `TokenVerifier` is configured, the tokens are
+    /// invalid, the input cannot be translated or a provider of the authorizer fails.
+    #[cfg(feature = "token")]
+    #[instrument(skip_all, err(Debug))]
+    pub async fn is_authorized_with_token(
+        &self,
+        input: &IsAuthorizedWithTokenInput,
+    ) -> Result<IsAuthorizedWithTokenOutput, AvpAuthorizerError> {
+        let token_verifier = self
+            .token_verifier
+            .as_ref()
+            .ok_or(AvpAuthorizerError::NoTokenVerifier)?;
+        let VerifiedToken {
+            principal,
+            entities: principal_entities,
+            context: claims,
+        } = token_verifier
+            .verify(input.identity_token(), input.access_token())
+            .await?;
+
+        let mut context = match input.context() {
+            Some(ContextDefinition::ContextMap(context)) => context.clone(),
+            _ => HashMap::new(),
+        };
+        context.extend(claims);
+        let mut entity_list = match input.entities() {
+            Some(EntitiesDefinition::EntityList(entity_list)) => entity_list.clone(),
+            _ => Vec::new(),
+        };
+        entity_list.extend(principal_entities);
+
+        let request = request(
+            Some(&principal),
+            input.action(),
+            input.resource(),
+            Some(&ContextDefinition::ContextMap(context)),
+        )?;
+        let entities = entities(Some(&EntitiesDefinition::EntityList(entity_list)))?;
+        let response = self.authorizer.is_authorized(&request, &entities).await?;
+        let evaluation = Evaluation::try_from(&response)?;
+
+        Ok(IsAuthorizedWithTokenOutput::builder()
+            .decision(evaluation.decision)
+            .set_determining_policies(Some(evaluation.determining_policies))
+            .set_errors(Some(evaluation.errors))
+            .build()?)
+    }
+
+    /// Evaluates every request of a `BatchIsAuthorized` input. The entities of the input are
+    /// translated once and shared by all requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AvpAuthorizerError` if any request or the entities cannot be translated, or a
+    /// provider of the authorizer fails.
+    #[instrument(skip_all, err(Debug))]
+    pub async fn batch_is_authorized(
+        &self,
+        input: &BatchIsAuthorizedInput,
+    ) -> Result<BatchIsAuthorizedOutput, AvpAuthorizerError> {
+        let entities = entities(input.entities())?;
+        let requests = input
+            .requests()
+            .iter()
+            .map(|item| {
+                request(
+                    item.principal(),
+                    item.action(),
+                    item.resource(),
+                    item.context(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        debug!("Evaluating Batch: requests={}", requests.len());
+
+        let mut results = Vec::with_capacity(requests.len());
+        for (item, request) in input.requests().iter().zip(requests) {
+            let response = self.authorizer.is_authorized(&request, &entities).await?;
+            let evaluation = Evaluation::try_from(&response)?;
+            results.push(
+                BatchIsAuthorizedOutputItem::builder()
+                    .request(item.clone())
+                    .decision(evaluation.decision)
+                    .set_determining_policies(Some(evaluation.determining_policies))
+                    .set_errors(Some(evaluation.errors))
+                    .build()?,
+            );
+        }
+
+        Ok(BatchIsAuthorizedOutput::builder()
+            .set_results(Some(results))
+            .build()?)
+    }
+}
+
+impl<P, E> From<Authorizer<P, E>> for AvpLocalAuthorizer<P, E>
+where
+    P: SimplePolicySetProvider,
+    E: SimpleEntityProvider,
+{
+    fn from(authorizer: Authorizer<P, E>) -> Self {
+        Self::new(authorizer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use aws_sdk_verifiedpermissions::operation::batch_is_authorized::BatchIsAuthorizedInput;
+    use aws_sdk_verifiedpermissions::operation::is_authorized::IsAuthorizedInput;
+    #[cfg(feature = "token")]
+    use aws_sdk_verifiedpermissions::operation::is_authorized_with_token::IsAuthorizedWithTokenInput;
+    use aws_sdk_verifiedpermissions::types::{
+        ActionIdentifier, AttributeValue, BatchIsAuthorizedInputItem, Decision, EntitiesDefinition,
+        EntityIdentifier, EntityItem,
+    };
+    use cedar_local_agent::public::file::entity_provider::EntityProvider;
+    use cedar_local_agent::public::simple::{Authorizer, AuthorizerConfigBuilder};
+    use cedar_local_agent::public::{PolicySetProviderError, SimplePolicySetProvider};
+    use cedar_policy::{PolicyId, PolicySet, Request};
+    #[cfg(feature = "token")]
+    use serde_json::json;
+
+    use crate::public::avp_authorizer::{AvpAuthorizerError, AvpLocalAuthorizer};
+    #[cfg(feature = "token")]
+    use crate::public::token_verifier::test::{build_verifier, sign};
+
+    const POLICY_STORE_ID: &str = "dummy-policy-store-id";
+
+    #[derive(Debug)]
+    struct StaticPolicySetProvider(Arc<PolicySet>);
+
+    #[async_trait]
+    impl SimplePolicySetProvider for StaticPolicySetProvider {
+        async fn get_policy_set(
+            &self,
+            _: &Request,
+        ) -> Result<Arc<PolicySet>, PolicySetProviderError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn authorizer() -> AvpLocalAuthorizer<StaticPolicySetProvider, EntityProvider> {
+        let policies = PolicySet::from_str(
+            r#"
+            @id("admins-view")
+            permit(principal in Group::"admins", action == Action::"view", resource);
+            @id("bob-view")
+            permit(principal == User::"bob", action == Action::"view", resource)
+            when { principal.level > 3 };
+            @id("no-delete")
+            forbid(principal, action == Action::"delete", resource);
+            @id("admins-share")
+            permit(
+                principal in MyApp::UserGroup::"us-east-1_example|admins",
+                action == Action::"share",
+                resource
+            ) when { context.scope == "photos/share" };
+            "#,
+        )
+        .unwrap();
+        let mut policy_set = PolicySet::new();
+        for policy in policies.policies() {
+            let id = policy.annotation("id").unwrap().to_string();
+            policy_set.add(policy.new_id(PolicyId::new(id))).unwrap();
+        }
+
+        AvpLocalAuthorizer::new(Authorizer::new(
+            AuthorizerConfigBuilder::default()
+                .policy_set_provider(Arc::new(StaticPolicySetProvider(Arc::new(policy_set))))
+                .entity_provider(Arc::new(EntityProvider::default()))
+                .build()
+                .unwrap(),
+        ))
+    }
+
+    fn identifier(entity_type: &str, entity_id: &str) -> EntityIdentifier {
+        EntityIdentifier::builder()
+            .entity_type(entity_type)
+            .entity_id(entity_id)
+            .build()
+            .unwrap()
+    }
+
+    fn action(action_id: &str) -> ActionIdentifier {
+        ActionIdentifier::builder()
+            .action_type("Action")
+            .action_id(action_id)
+            .build()
+            .unwrap()
+    }
+
+    fn entities() -> EntitiesDefinition {
+        EntitiesDefinition::EntityList(vec![
+            EntityItem::builder()
+                .identifier(identifier("User", "alice"))
+                .parents(identifier("Group", "admins"))
+                .build(),
+            EntityItem::builder()
+                .identifier(identifier("Group", "admins"))
+                .build(),
+        ])
+    }
+
+    fn item(principal: &str, action_id: &str) -> BatchIsAuthorizedInputItem {
+        BatchIsAuthorizedInputItem::builder()
+            .principal(identifier("User", principal))
+            .action(action(action_id))
+            .resource(identifier("Photo", "beach"))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn is_authorized_returns_the_decision_and_determining_policies() {
+        let input = IsAuthorizedInput::builder()
+            .policy_store_id(POLICY_STORE_ID)
+            .principal(identifier("User", "alice"))
+            .action(action("view"))
+            .resource(identifier("Photo", "beach"))
+            .entities(entities())
+            .build()
+            .unwrap();
+
+        let output = authorizer().is_authorized(&input).await.unwrap();
+        assert_eq!(output.decision(), &Decision::Allow);
+        assert_eq!(output.determining_policies().len(), 1);
+        assert_eq!(output.determining_policies()[0].policy_id(), "admins-view");
+        assert!(output.errors().is_empty());
+    }
+
+    #[tokio::test]
+    async fn is_authorized_reports_evaluation_errors() {
+        let input = IsAuthorizedInput::builder()
+            .policy_store_id(POLICY_STORE_ID)
+            .principal(identifier("User", "bob"))
+            .action(action("view"))
+            .resource(identifier("Photo", "beach"))
+            .entities(EntitiesDefinition::EntityList(vec![EntityItem::builder()
+                .identifier(identifier("User", "bob"))
+                .attributes("level", AttributeValue::String("high".to_string()))
+                .build()]))
+            .build()
+            .unwrap();
+
+        let output = authorizer().is_authorized(&input).await.unwrap();
+        assert_eq!(output.decision(), &Decision::Deny);
+        assert!(output.determining_policies().is_empty());
+        assert_eq!(output.errors().len(), 1);
+        assert!(output.errors()[0].error_description().contains("bob-view"));
+    }
+
+    #[tokio::test]
+    async fn batch_is_authorized_evaluates_every_request_with_shared_entities() {
+        let requests = vec![
+            item("alice", "view"),
+            item("carol", "view"),
+            item("alice", "delete"),
+        ];
+        let input = BatchIsAuthorizedInput::builder()
+            .policy_store_id(POLICY_STORE_ID)
+            .set_requests(Some(requests.clone()))
+            .entities(entities())
+            .build()
+            .unwrap();
+
+        let output = authorizer().batch_is_authorized(&input).await.unwrap();
+        let results = output.results();
+        assert_eq!(results.len(), 3);
+        for (result, request) in results.iter().zip(&requests) {
+            assert_eq!(result.request(), Some(request));
+        }
+        assert_eq!(results[0].decision(), &Decision::Allow);
+        assert_eq!(
+            results[0].determining_policies()[0].policy_id(),
+            "admins-view"
+        );
+        assert_eq!(results[1].decision(), &Decision::Deny);
+        assert!(results[1].determining_policies().is_empty());
+        assert_eq!(results[2].decision(), &Decision::Deny);
+        assert_eq!(
+            results[2].determining_policies()[0].policy_id(),
+            "no-delete"
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_is_authorized_rejects_an_untranslatable_request() {
+        let input = BatchIsAuthorizedInput::builder()
+            .policy_store_id(POLICY_STORE_ID)
+            .requests(item("alice", "view"))
+            .requests(
+                BatchIsAuthorizedInputItem::builder()
+                    .principal(identifier("User", "alice"))
+                    .action(action("view"))
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            authorizer().batch_is_authorized(&input).await,
+            Err(AvpAuthorizerError::Translate(_))
+        ));
+    }
+
+    #[cfg(feature = "token")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn is_authorized_with_token_authorizes_the_token_principal() {
+        let identity_token = sign(
+            "id",
+            json!({ "sub": "alice", "aud": "client-1", "cognito:groups": ["admins"] }),
+        );
+        let access_token = sign(
+            "access",
+            json!({ "sub": "alice", "client_id": "client-1", "scope": "photos/share" }),
+        );
+        let input = IsAuthorizedWithTokenInput::builder()
+            .policy_store_id(POLICY_STORE_ID)
+            .identity_token(identity_token)
+            .access_token(access_token)
+            .action(action("share"))
+            .resource(identifier("Photo", "beach"))
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            authorizer().is_authorized_with_token(&input).await,
+            Err(AvpAuthorizerError::NoTokenVerifier)
+        ));
+
+        let output = authorizer()
+            .with_token_verifier(Arc::new(build_verifier()))
+            .is_authorized_with_token(&input)
+            .await
+            .unwrap();
+        assert_eq!(output.decision(), &Decision::Allow);
+        assert_eq!(output.determining_policies()[0].policy_id(), "admins-share");
+    }
+}
@@ -0,0 +1,605 @@
+//! The `avp-local-agent` command-line tool, built with the `cli` feature.
+//!
+//! The tool loads an Amazon Verified Permissions policy store with the `PolicySetProvider` and
+//! `EntityProvider` of this crate to report its contents, export it as Cedar files, compare it
+//! to a local directory, and evaluate requests locally.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use aws_config::profile::ProfileFileCredentialsProvider;
+use aws_credential_types::provider::SharedCredentialsProvider;
+use aws_sdk_verifiedpermissions::Client;
+use aws_types::region::Region;
+use cedar_local_agent::public::simple::{Authorizer, AuthorizerConfigBuilder};
+use cedar_policy::{
+    Context, Decision, Entities, EntityUid, Policy, PolicyId, PolicySet, Request, Template,
+};
+use clap::{Args, Parser, Subcommand};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::client::{
+    verified_permissions_default_credentials_with_options, verified_permissions_with_options,
+    ClientOptions,
+};
+use super::entity_provider::{EntityProvider, ProviderError as EntityProviderError};
+use super::policy_set_filter::PolicySetFilter;
+use super::policy_set_provider::{PolicySetProvider, ProviderError as PolicySetProviderError};
+
+/// The extension of the Cedar files written by `export` and read by `diff`.
+const CEDAR_EXTENSION: &str = "cedar";
+/// The directory of the policies written by `export` and read by `diff`.
+const POLICIES_DIR: &str = "policies";
+/// The directory of the templates written by `export` and read by `diff`.
+const TEMPLATES_DIR: &str = "templates";
+
+/// `CliError` occurs when a command of the command-line tool fails.
+#[derive(Error, Debug)]
+pub enum CliError {
+    /// The policy store cannot be loaded
+    #[error("Failed to load the policy store: {0}")]
+    PolicySetProvider(#[from] PolicySetProviderError),
+    /// The schema of the policy store cannot be loaded
+    #[error("Failed to load the schema of the policy store: {0}")]
+    EntityProvider(#[from] EntityProviderError),
+    /// A file or directory cannot be read or written
+    #[error("Failed to access {path}: {1}", path = .0.display())]
+    Io(PathBuf, #[source] std::io::Error),
+    /// The output cannot be written
+    #[error("Failed to write the output: {0}")]
+    Output(#[from] std::io::Error),
+    /// A local Cedar file cannot be parsed
+    #[error("Failed to parse {path}: {1}", path = .0.display())]
+    Parse(PathBuf, String),
+    /// The request arguments are invalid
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    /// The request cannot be evaluated
+    #[error("Failed to evaluate the request: {0}")]
+    Authorization(String),
+}
+
+/// Syncs, inspects and tests Amazon Verified Permissions policy stores locally.
+#[derive(Parser, Debug)]
+#[command(name = "avp-local-agent", version)]
+pub struct Cli {
+    /// The region of the policy store
+    #[arg(long, env = "AWS_REGION")]
+    pub region: String,
+    /// The profile of the shared config and credentials files, the default credentials chain if
+    /// not set
+    #[arg(long, env = "AWS_PROFILE")]
+    pub profile: Option<String>,
+    /// Overrides the Amazon Verified Permissions endpoint
+    #[arg(long)]
+    pub endpoint_url: Option<String>,
+    /// The command to run
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// The commands of the command-line tool.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Loads the policy store and reports the number of policies and templates
+    Sync(PolicyStoreArgs),
+    /// Writes the policies and templates of the policy store as Cedar files
+    Export(ExportArgs),
+    /// Evaluates a request locally against the policy store
+    Authorize(RequestArgs),
+    /// Compares the policy store to a directory written by `export`
+    Diff(DiffArgs),
+    /// Evaluates a request and describes its determining policies
+    Explain(RequestArgs),
+}
+
+/// The policy store a command loads.
+#[derive(Args, Debug)]
+pub struct PolicyStoreArgs {
+    /// The id of the policy store
+    #[arg(long)]
+    pub policy_store_id: String,
+    /// Loads only the policies matching a filter in the CLI shorthand syntax, such as
+    /// `policyTemplateId=template-1`
+    #[arg(long)]
+    pub filter: Option<String>,
+}
+
+/// The arguments of `export`.
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// The policy store to export
+    #[command(flatten)]
+    pub policy_store: PolicyStoreArgs,
+    /// The directory the `policies` and `templates` directories are written to
+    #[arg(long)]
+    pub output_dir: PathBuf,
+}
+
+/// The arguments of `diff`.
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// The policy store to compare
+    #[command(flatten)]
+    pub policy_store: PolicyStoreArgs,
+    /// The directory written by `export`
+    #[arg(long)]
+    pub dir: PathBuf,
+}
+
+/// The arguments of `authorize` and `explain`.
+#[derive(Args, Debug)]
+pub struct RequestArgs {
+    /// The policy store to evaluate the request against
+    #[command(flatten)]
+    pub policy_store: PolicyStoreArgs,
+    /// The principal of the request, such as `User::"alice"`
+    #[arg(long)]
+    pub principal: String,
+    /// The action of the request, such as `Action::"view"`
+    #[arg(long)]
+    pub action: String,
+    /// The resource of the request, such as `Photo::"vacation.jpg"`
+    #[arg(long)]
+    pub resource: String,
+    /// The context of the request as a JSON object
+    #[arg(long)]
+    pub context: Option<String>,
+    /// A file of the entities of the request in the Cedar JSON format
+    #[arg(long)]
+    pub entities: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Builds the Verified Permissions `Client` of the command-line tool.
+    pub async fn client(&self) -> Client {
+        let region = Region::new(self.region.clone());
+        let options = ClientOptions {
+            endpoint_url: self.endpoint_url.clone(),
+            ..ClientOptions::default()
+        };
+        match &self.profile {
+            Some(profile) => verified_permissions_with_options(
+                region,
+                SharedCredentialsProvider::new(
+                    ProfileFileCredentialsProvider::builder()
+                        .profile_name(profile)
+                        .build(),
+                ),
+                &options,
+            ),
+            None => verified_permissions_default_credentials_with_options(region, &options).await,
+        }
+    }
+
+    /// Runs the command with the given client and writes its output. Must be called from a
+    /// multi-threaded Tokio runtime, since the providers load the policy store on construction.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `CliError` if the command fails.
+    pub async fn run(&self, client: Client, out: &mut impl Write) -> Result<(), CliError> {
+        match &self.command {
+            Command::Sync(args) => sync(args, client, out).await,
+            Command::Export(args) => export(args, client, out).await,
+            Command::Authorize(args) => authorize(args, client, out, false).await,
+            Command::Diff(args) => diff(args, client, out).await,
+            Command::Explain(args) => authorize(args, client, out, true).await,
+        }
+    }
+}
+
+impl PolicyStoreArgs {
+    /// Loads the policy store with the optional filter.
+    fn policy_set_provider(&self, client: Client) -> Result<PolicySetProvider, CliError> {
+        Ok(PolicySetProvider::from_client_with_filters(
+            self.policy_store_id.clone(),
+            self.filter.as_deref().map(PolicySetFilter::Cli),
+            client,
+        )?)
+    }
+}
+
+async fn sync(
+    args: &PolicyStoreArgs,
+    client: Client,
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let health = args.policy_set_provider(client)?.health().await;
+    writeln!(
+        out,
+        "Loaded {} policies and {} templates from policy store {}",
+        health.policies, health.templates, args.policy_store_id
+    )?;
+    Ok(())
+}
+
+async fn export(args: &ExportArgs, client: Client, out: &mut impl Write) -> Result<(), CliError> {
+    let policy_set = args
+        .policy_store
+        .policy_set_provider(client)?
+        .policy_set()
+        .await;
+
+    let policies = policy_texts(&policy_set);
+    let templates = template_texts(&policy_set);
+    write_cedar_files(&args.output_dir.join(POLICIES_DIR), &policies)?;
+    write_cedar_files(&args.output_dir.join(TEMPLATES_DIR), &templates)?;
+    writeln!(
+        out,
+        "Exported {} policies and {} templates to {}",
+        policies.len(),
+        templates.len(),
+        args.output_dir.display()
+    )?;
+    Ok(())
+}
+
+// The closures return the Cedar `ParseErrors` of the parsed file unchanged.
+#[allow(clippy::result_large_err)]
+async fn diff(args: &DiffArgs, client: Client, out: &mut impl Write) -> Result<(), CliError> {
+    let policy_set = args
+        .policy_store
+        .policy_set_provider(client)?
+        .policy_set()
+        .await;
+
+    let policies_dir = args.dir.join(POLICIES_DIR);
+    let templates_dir = args.dir.join(TEMPLATES_DIR);
+    let mut differences = diff_cedar_files(
+        &policy_texts(&policy_set),
+        &policies_dir,
+        |id, text| Policy::parse(Some(PolicyId::new(id)), text).map(|p| p.to_json().ok()),
+        "policy",
+        out,
+    )?;
+    differences += diff_cedar_files(
+        &template_texts(&policy_set),
+        &templates_dir,
+        |id, text| Template::parse(Some(PolicyId::new(id)), text).map(|t| t.to_json().ok()),
+        "template",
+        out,
+    )?;
+    if differences == 0 {
+        writeln!(out, "No differences with {}", args.dir.display())?;
+    } else {
+        writeln!(out, "{differences} differences with {}", args.dir.display())?;
+    }
+    Ok(())
+}
+
+async fn authorize(
+    args: &RequestArgs,
+    client: Client,
+    out: &mut impl Write,
+    explain: bool,
+) -> Result<(), CliError> {
+    let request = args.request()?;
+    let entities = args.entities()?;
+    let policy_set_provider = Arc::new(args.policy_store.policy_set_provider(client.clone())?);
+    let entity_provider = Arc::new(EntityProvider::from_client(
+        args.policy_store.policy_store_id.clone(),
+        client,
+    )?);
+    let authorizer = Authorizer::new(
+        AuthorizerConfigBuilder::default()
+            .policy_set_provider(policy_set_provider.clone())
+            .entity_provider(entity_provider)
+            .build()
+            .map_err(|e| CliError::Authorization(e.to_string()))?,
+    );
+
+    let response = authorizer
+        .is_authorized(&request, &entities)
+        .await
+        .map_err(|e| CliError::Authorization(e.to_string()))?;
+    let decision = match response.decision() {
+        Decision::Allow => "ALLOW",
+        Decision::Deny => "DENY",
+    };
+    writeln!(out, "Decision: {decision}")?;
+    for policy_id in response.diagnostics().reason() {
+        writeln!(out, "Determining policy: {policy_id}")?;
+        if !explain {
+            continue;
+        }
+        let Some(metadata) = policy_set_provider.policy_metadata(policy_id).await else {
+            writeln!(out, "  not an Amazon Verified Permissions policy")?;
+            continue;
+        };
+        writeln!(out, "  type: {}", metadata.policy_type.as_str())?;
+        if let Some(effect) = metadata.effect {
+            writeln!(out, "  effect: {effect}")?;
+        }
+        if let Some(description) = &metadata.description {
+            writeln!(out, "  description: {description}")?;
+        }
+        if let Some(template_id) = &metadata.policy_template_id {
+            writeln!(out, "  template: {template_id}")?;
+        }
+        writeln!(out, "  last updated: {}", metadata.last_updated_date)?;
+    }
+    for error in response.diagnostics().errors() {
+        writeln!(out, "Error: {error}")?;
+    }
+    Ok(())
+}
+
+impl RequestArgs {
+    /// Builds the Cedar request of the arguments.
+    fn request(&self) -> Result<Request, CliError> {
+        let entity_uid = |name: &str, uid: &str| {
+            EntityUid::from_str(uid)
+                .map_err(|e| CliError::InvalidRequest(format!("invalid {name} {uid}: {e}")))
+        };
+        let context = match &self.context {
+            Some(context) => Context::from_json_str(context, None)
+                .map_err(|e| CliError::InvalidRequest(format!("invalid context: {e}")))?,
+            None => Context::empty(),
+        };
+        Request::new(
+            entity_uid("principal", &self.principal)?,
+            entity_uid("action", &self.action)?,
+            entity_uid("resource", &self.resource)?,
+            context,
+            None,
+        )
+        .map_err(|e| CliError::InvalidRequest(e.to_string()))
+    }
+
+    /// Reads the entities of the request, if a file is given.
+    fn entities(&self) -> Result<Entities, CliError> {
+        let Some(path) = &self.entities else {
+            return Ok(Entities::empty());
+        };
+        let json = fs::read_to_string(path).map_err(|e| CliError::Io(path.clone(), e))?;
+        Entities::from_json_str(&json, None)
+            .map_err(|e| CliError::Parse(path.clone(), e.to_string()))
+    }
+}
+
+/// Returns the Cedar text of the policies keyed by id, with template-linked policies linked.
+fn policy_texts(policy_set: &PolicySet) -> BTreeMap<String, String> {
+    policy_set
+        .policies()
+        .map(|policy| (policy.id().to_string(), policy.to_string()))
+        .collect()
+}
+
+/// Returns the Cedar text of the templates keyed by id.
+fn template_texts(policy_set: &PolicySet) -> BTreeMap<String, String> {
+    policy_set
+        .templates()
+        .map(|template| (template.id().to_string(), template.to_string()))
+        .collect()
+}
+
+/// Writes each text to `<id>.cedar` in `dir`.
+fn write_cedar_files(dir: &Path, texts: &BTreeMap<String, String>) -> Result<(), CliError> {
+    fs::create_dir_all(dir).map_err(|e| CliError::Io(dir.to_path_buf(), e))?;
+    for (id, text) in texts {
+        let path = dir.join(format!("{id}.{CEDAR_EXTENSION}"));
+        fs::write(&path, text).map_err(|e| CliError::Io(path.clone(), e))?;
+    }
+    Ok(())
+}
+
+/// Reads the `<id>.cedar` files of `dir`, which may not exist.
+fn read_cedar_files(dir: &Path) -> Result<BTreeMap<String, String>, CliError> {
+    let mut texts = BTreeMap::new();
+    if !dir.exists() {
+        return Ok(texts);
+    }
+    for entry in fs::read_dir(dir).map_err(|e| CliError::Io(dir.to_path_buf(), e))? {
+        let path = entry
+            .map_err(|e| CliError::Io(dir.to_path_buf(), e))?
+            .path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some(CEDAR_EXTENSION) {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let text = fs::read_to_string(&path).map_err(|e| CliError::Io(path.clone(), e))?;
+        texts.insert(id.to_string(), text);
+    }
+    Ok(texts)
+}
+
+/// Compares the policy store texts to the files of `dir` by their parsed JSON representation, so
+/// that formatting differences are ignored, and returns the number of differences.
+fn diff_cedar_files<E: ToString>(
+    store: &BTreeMap<String, String>,
+    dir: &Path,
+    to_json: impl Fn(&str, &str) -> Result<Option<Value>, E>,
+    kind: &str,
+    out: &mut impl Write,
+) -> Result<usize, CliError> {
+    let local = read_cedar_files(dir)?;
+    let mut differences = 0;
+    for (id, store_text) in store {
+        let Some(local_text) = local.get(id) else {
+            writeln!(out, "Only in the policy store: {kind} {id}")?;
+            differences += 1;
+            continue;
+        };
+        let path = dir.join(format!("{id}.{CEDAR_EXTENSION}"));
+        let local_json =
+            to_json(id, local_text).map_err(|e| CliError::Parse(path.clone(), e.to_string()))?;
+        let store_json = to_json(id, store_text).ok().flatten();
+        if local_json.is_none() || local_json != store_json {
+            writeln!(out, "Differs: {kind} {id}")?;
+            differences += 1;
+        }
+    }
+    for id in local.keys().filter(|id| !store.contains_key(*id)) {
+        writeln!(out, "Only in {}: {kind} {id}", dir.display())?;
+        differences += 1;
+    }
+    Ok(differences)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use clap::Parser;
+
+    use crate::private::sources::test::build_client;
+    use crate::public::policy_set_provider::test::{
+        get_policy_event, list_policies_event, list_templates_event, POLICY_ID, POLICY_STORE_ID,
+        STATIC_POLICY,
+    };
+    use crate::public::schema_provider::test::{build_schema_event, SCHEMA};
+
+    use super::{Cli, Command};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("avp-local-agent-cli-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    async fn run(
+        args: &[&str],
+        events: Vec<aws_smithy_runtime::client::http::test_util::ReplayEvent>,
+    ) -> String {
+        let cli = Cli::try_parse_from(
+            ["avp-local-agent", "--region", "us-east-1"]
+                .iter()
+                .chain(args),
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        cli.run(build_client(events), &mut out).await.unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn policy_store_events() -> Vec<aws_smithy_runtime::client::http::test_util::ReplayEvent> {
+        vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+        ]
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        let cli = Cli::try_parse_from([
+            "avp-local-agent",
+            "--region",
+            "us-east-1",
+            "sync",
+            "--policy-store-id",
+            POLICY_STORE_ID,
+            "--filter",
+            "policyTemplateId=template-1",
+        ])
+        .unwrap();
+
+        let Command::Sync(args) = cli.command else {
+            panic!("expected the sync command");
+        };
+        assert_eq!(args.policy_store_id, POLICY_STORE_ID);
+        assert_eq!(args.filter.as_deref(), Some("policyTemplateId=template-1"));
+        assert!(Cli::try_parse_from(["avp-local-agent", "--region", "us-east-1", "sync"]).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn sync_reports_the_counts() {
+        let out = run(
+            &["sync", "--policy-store-id", POLICY_STORE_ID],
+            policy_store_events(),
+        )
+        .await;
+
+        assert_eq!(
+            out,
+            "Loaded 1 policies and 0 templates from policy store ps-1\n"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn diff_compares_the_exported_files() {
+        let dir = temp_dir("diff");
+        let dir_arg = dir.to_str().unwrap();
+        run(
+            &[
+                "export",
+                "--policy-store-id",
+                POLICY_STORE_ID,
+                "--output-dir",
+                dir_arg,
+            ],
+            policy_store_events(),
+        )
+        .await;
+        let policy_path = dir.join("policies").join(format!("{POLICY_ID}.cedar"));
+        assert_eq!(fs::read_to_string(&policy_path).unwrap(), STATIC_POLICY);
+
+        fs::write(&policy_path, format!("\n  {STATIC_POLICY}\n")).unwrap();
+        let out = run(
+            &[
+                "diff",
+                "--policy-store-id",
+                POLICY_STORE_ID,
+                "--dir",
+                dir_arg,
+            ],
+            policy_store_events(),
+        )
+        .await;
+        assert!(out.starts_with("No differences"));
+
+        fs::write(&policy_path, "forbid(principal, action, resource);").unwrap();
+        fs::write(dir.join("policies").join("local.cedar"), STATIC_POLICY).unwrap();
+        let out = run(
+            &[
+                "diff",
+                "--policy-store-id",
+                POLICY_STORE_ID,
+                "--dir",
+                dir_arg,
+            ],
+            policy_store_events(),
+        )
+        .await;
+        assert!(out.contains(&format!("Differs: policy {POLICY_ID}")));
+        assert!(out.contains("policy local"));
+        assert!(out.contains("2 differences"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn explain_describes_the_determining_policies() {
+        let mut events = policy_store_events();
+        events.push(build_schema_event(SCHEMA, "2024-01-01T00:00:00Z"));
+
+        let out = run(
+            &[
+                "explain",
+                "--policy-store-id",
+                POLICY_STORE_ID,
+                "--principal",
+                r#"User::"alice""#,
+                "--action",
+                r#"Action::"view""#,
+                "--resource",
+                r#"Photo::"vacation.jpg""#,
+            ],
+            events,
+        )
+        .await;
+
+        assert!(out.starts_with("Decision: ALLOW\n"));
+        assert!(out.contains(&format!("Determining policy: {POLICY_ID}\n")));
+        assert!(out.contains("  type: STATIC\n"));
+        assert!(out.contains("  effect: permit\n"));
+    }
+}
@@ -1,84 +1,383 @@
 //! A helper module for building a Verified Permissions `Client` from a `ClientConfig`.
+//!
+//! `ClientOptions` overrides the endpoint, the HTTP client, the timeouts, the retries and the
+//! user agent of the `Client`. Its defaults build the same `Client` as
+//! `verified_permissions_with_credentials`.
+//!
+//! `AssumeRoleOptions` reads policy stores of other accounts with the credentials of an assumed
+//! role, which the `Client` refreshes before they expire.
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::time::Duration;
 
 use aws_config::default_provider::credentials::DefaultCredentialsChain;
 use aws_config::retry::RetryConfig;
+use aws_config::sts::AssumeRoleProvider;
 use aws_config::timeout::TimeoutConfig;
 use aws_config::BehaviorVersion;
 use aws_credential_types::provider::SharedCredentialsProvider;
-use aws_sdk_verifiedpermissions::config::SharedAsyncSleep;
+use aws_sdk_verifiedpermissions::config::{AppName, SharedAsyncSleep, SharedHttpClient};
 use aws_sdk_verifiedpermissions::Client;
 use aws_smithy_async::rt::sleep::TokioSleep;
+use aws_smithy_async::time::SystemTimeSource;
 use aws_types::region::Region;
 use aws_types::sdk_config::SdkConfig;
+use derive_builder::Builder;
+use thiserror::Error;
+use tracing::{info, instrument};
 
 /// A const to control the max retry attempts in the `Client`.
 pub const AVP_CLIENT_MAX_ATTEMPTS: u32 = 2;
 /// A const to control the default timeout in milliseconds for the `Client`.
 pub const AVP_CLIENT_DEFAULT_TIMEOUT_MS: u64 = 5000;
+/// A const for the default session name of assumed roles.
+pub const AVP_CLIENT_DEFAULT_SESSION_NAME: &str = "avp-local-agent";
 
-/// Builds a new `Client`  from a region and a `SharedCredentialsProvider`.
-pub fn verified_permissions_with_credentials(
-    region: Region,
-    credentials: SharedCredentialsProvider,
-) -> Client {
-    let timeout_cfg = TimeoutConfig::builder()
-        .operation_timeout(Duration::from_millis(
-            AVP_CLIENT_DEFAULT_TIMEOUT_MS * u64::from(AVP_CLIENT_MAX_ATTEMPTS),
-        ))
-        .operation_attempt_timeout(Duration::from_millis(AVP_CLIENT_DEFAULT_TIMEOUT_MS))
-        .build();
-
-    Client::new(
-        &SdkConfig::builder()
+/// `ClientError` can occur when building a `Client` from a policy store ARN.
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The policy store ARN is malformed
+    #[error("Invalid policy store ARN {0}: {1}")]
+    InvalidPolicyStoreArn(String, String),
+}
+
+/// Options for building a Verified Permissions `Client`.
+///
+/// ```
+/// use std::time::Duration;
+/// use avp_local_agent::public::client::ClientOptionsBuilder;
+///
+/// let options = ClientOptionsBuilder::default()
+///     .endpoint_url("https://vpce-0123.verifiedpermissions.us-east-1.vpce.amazonaws.com")
+///     .attempt_timeout(Duration::from_secs(2))
+///     .max_attempts(3)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned")]
+pub struct ClientOptions {
+    /// Overrides the resolved endpoint, for VPC endpoints or local fakes
+    #[builder(default, setter(into, strip_option))]
+    pub endpoint_url: Option<String>,
+    /// Uses the FIPS endpoint of the region
+    #[builder(default)]
+    pub use_fips: bool,
+    /// Uses the dual-stack endpoint of the region
+    #[builder(default)]
+    pub use_dual_stack: bool,
+    /// Replaces the default HTTP client, for example with one that goes through a proxy
+    #[builder(default, setter(strip_option))]
+    pub http_client: Option<SharedHttpClient>,
+    /// The timeout of each attempt
+    #[builder(default = "Duration::from_millis(AVP_CLIENT_DEFAULT_TIMEOUT_MS)")]
+    pub attempt_timeout: Duration,
+    /// The timeout of the whole operation, `attempt_timeout` times `max_attempts` if not set
+    #[builder(default, setter(strip_option))]
+    pub operation_timeout: Option<Duration>,
+    /// The max attempts of each operation, including the first one
+    #[builder(default = "AVP_CLIENT_MAX_ATTEMPTS")]
+    pub max_attempts: u32,
+    /// Appended to the user agent of the requests
+    #[builder(default, setter(strip_option))]
+    pub app_name: Option<AppName>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            endpoint_url: None,
+            use_fips: false,
+            use_dual_stack: false,
+            http_client: None,
+            attempt_timeout: Duration::from_millis(AVP_CLIENT_DEFAULT_TIMEOUT_MS),
+            operation_timeout: None,
+            max_attempts: AVP_CLIENT_MAX_ATTEMPTS,
+            app_name: None,
+        }
+    }
+}
+
+impl ClientOptions {
+    /// Builds the `SdkConfig` of a client in the given region with the given credentials.
+    pub fn sdk_config(&self, region: Region, credentials: SharedCredentialsProvider) -> SdkConfig {
+        let operation_timeout = self
+            .operation_timeout
+            .unwrap_or_else(|| self.attempt_timeout.saturating_mul(self.max_attempts));
+        let timeout_cfg = TimeoutConfig::builder()
+            .operation_timeout(operation_timeout)
+            .operation_attempt_timeout(self.attempt_timeout)
+            .build();
+
+        let mut builder = SdkConfig::builder()
             .region(region)
             .timeout_config(timeout_cfg)
             .credentials_provider(credentials)
-            .retry_config(RetryConfig::standard().with_max_attempts(AVP_CLIENT_MAX_ATTEMPTS))
+            .retry_config(RetryConfig::standard().with_max_attempts(self.max_attempts))
             .sleep_impl(SharedAsyncSleep::new(TokioSleep::new()))
+            .time_source(SystemTimeSource::new())
             .behavior_version(BehaviorVersion::latest())
-            .build(),
-    )
+            .use_fips(self.use_fips)
+            .use_dual_stack(self.use_dual_stack);
+        builder.set_endpoint_url(self.endpoint_url.clone());
+        builder.set_http_client(self.http_client.clone());
+        builder.set_app_name(self.app_name.clone());
+        builder.build()
+    }
+}
+
+/// Builds a new `Client`  from a region and a `SharedCredentialsProvider`.
+pub fn verified_permissions_with_credentials(
+    region: Region,
+    credentials: SharedCredentialsProvider,
+) -> Client {
+    verified_permissions_with_options(region, credentials, &ClientOptions::default())
 }
 
 /// Amazon Verified Permissions Client from a region using `DefaultCredentialsProvider`
 pub async fn verified_permissions_default_credentials(region: Region) -> Client {
-    let timeout_cfg = TimeoutConfig::builder()
-        .operation_timeout(Duration::from_millis(
-            AVP_CLIENT_DEFAULT_TIMEOUT_MS * u64::from(AVP_CLIENT_MAX_ATTEMPTS),
-        ))
-        .operation_attempt_timeout(Duration::from_millis(AVP_CLIENT_DEFAULT_TIMEOUT_MS))
-        .build();
+    verified_permissions_default_credentials_with_options(region, &ClientOptions::default()).await
+}
+
+/// Builds a new `Client` from a region, a `SharedCredentialsProvider` and `ClientOptions`.
+pub fn verified_permissions_with_options(
+    region: Region,
+    credentials: SharedCredentialsProvider,
+    options: &ClientOptions,
+) -> Client {
+    Client::new(&options.sdk_config(region, credentials))
+}
+
+/// Amazon Verified Permissions Client from a region and `ClientOptions` using
+/// `DefaultCredentialsProvider`
+pub async fn verified_permissions_default_credentials_with_options(
+    region: Region,
+    options: &ClientOptions,
+) -> Client {
+    let creds = SharedCredentialsProvider::new(
+        DefaultCredentialsChain::builder()
+            .region(region.clone())
+            .build()
+            .await,
+    );
+    verified_permissions_with_options(region, creds, options)
+}
+/// Options for assuming a role, for example in the account that owns the policy store.
+///
+/// ```
+/// use avp_local_agent::public::client::AssumeRoleOptionsBuilder;
+///
+/// let assume_role = AssumeRoleOptionsBuilder::default()
+///     .role_arn("arn:aws:iam::123456789012:role/avp-reader")
+///     .external_id("my-external-id")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned")]
+pub struct AssumeRoleOptions {
+    /// The ARN of the role to assume
+    #[builder(setter(into))]
+    pub role_arn: String,
+    /// The name of the role session
+    #[builder(default = "AVP_CLIENT_DEFAULT_SESSION_NAME.to_string()", setter(into))]
+    pub session_name: String,
+    /// The external id required by the trust policy of the role
+    #[builder(default, setter(into, strip_option))]
+    pub external_id: Option<String>,
+    /// The duration of the role session, one hour if not set
+    #[builder(default, setter(strip_option))]
+    pub session_duration: Option<Duration>,
+    /// The options of the AWS STS client, such as an endpoint override
+    #[builder(default)]
+    pub sts_options: ClientOptions,
+}
+
+/// Builds a new `Client` that assumes a role with the given base credentials. The assumed role
+/// credentials are cached by the `Client` and refreshed before they expire.
+#[instrument(skip(credentials, options))]
+pub async fn verified_permissions_assume_role(
+    region: Region,
+    credentials: SharedCredentialsProvider,
+    assume_role: &AssumeRoleOptions,
+    options: &ClientOptions,
+) -> Client {
+    let mut builder = AssumeRoleProvider::builder(assume_role.role_arn.clone())
+        .session_name(assume_role.session_name.clone())
+        .configure(
+            &assume_role
+                .sts_options
+                .sdk_config(region.clone(), credentials),
+        );
+    if let Some(external_id) = &assume_role.external_id {
+        builder = builder.external_id(external_id.clone());
+    }
+    if let Some(session_duration) = assume_role.session_duration {
+        builder = builder.session_length(session_duration);
+    }
+    let assumed_credentials = SharedCredentialsProvider::new(builder.build().await);
 
+    info!(
+        "Built client assuming role: role_arn={}",
+        assume_role.role_arn
+    );
+    verified_permissions_with_options(region, assumed_credentials, options)
+}
+
+/// Amazon Verified Permissions Client that assumes a role using `DefaultCredentialsProvider` as
+/// base credentials
+pub async fn verified_permissions_assume_role_default_credentials(
+    region: Region,
+    assume_role: &AssumeRoleOptions,
+    options: &ClientOptions,
+) -> Client {
     let creds = SharedCredentialsProvider::new(
         DefaultCredentialsChain::builder()
             .region(region.clone())
             .build()
             .await,
     );
+    verified_permissions_assume_role(region, creds, assume_role, options).await
+}
 
-    Client::new(
-        &SdkConfig::builder()
-            .region(region)
-            .timeout_config(timeout_cfg)
-            .credentials_provider(creds)
-            .retry_config(RetryConfig::standard().with_max_attempts(AVP_CLIENT_MAX_ATTEMPTS))
-            .sleep_impl(SharedAsyncSleep::new(TokioSleep::new()))
-            .behavior_version(BehaviorVersion::latest())
-            .build(),
-    )
+/// The parsed ARN of an Amazon Verified Permissions policy store, in the form
+/// `arn:<partition>:verifiedpermissions:<region>:<account>:policy-store/<policy store id>`.
+///
+/// Policy store ARNs usually leave the region empty, in which case the region of the `Client`
+/// must be given separately.
+///
+/// ```
+/// use avp_local_agent::public::client::{AssumeRoleOptionsBuilder, PolicyStoreArn};
+/// use aws_types::region::Region;
+///
+/// let arn: PolicyStoreArn = "arn:aws:verifiedpermissions::123456789012:policy-store/ps-1"
+///     .parse()
+///     .unwrap();
+/// let region = arn.region_or(Region::new("us-east-1"));
+/// let assume_role = AssumeRoleOptionsBuilder::default()
+///     .role_arn(arn.role_arn("avp-reader"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(assume_role.role_arn, "arn:aws:iam::123456789012:role/avp-reader");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyStoreArn {
+    /// The partition of the policy store, such as `aws`
+    pub partition: String,
+    /// The region of the policy store, if the ARN has one
+    pub region: Option<Region>,
+    /// The account that owns the policy store
+    pub account_id: String,
+    /// The id of the policy store
+    pub policy_store_id: String,
+}
+
+impl FromStr for PolicyStoreArn {
+    type Err = ClientError;
+
+    fn from_str(arn: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: &str| ClientError::InvalidPolicyStoreArn(arn.to_string(), reason.to_string());
+
+        let parts = arn.splitn(6, ':').collect::<Vec<_>>();
+        let [prefix, partition, service, region, account_id, resource] = parts[..] else {
+            return Err(invalid("expected 6 colon separated parts"));
+        };
+        if prefix != "arn" || partition.is_empty() {
+            return Err(invalid("expected an arn:<partition> prefix"));
+        }
+        if service != "verifiedpermissions" {
+            return Err(invalid("expected the verifiedpermissions service"));
+        }
+        if account_id.is_empty() || !account_id.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid("expected a numeric account id"));
+        }
+        let Some(policy_store_id) = resource
+            .strip_prefix("policy-store/")
+            .filter(|id| !id.is_empty() && !id.contains('/'))
+        else {
+            return Err(invalid(
+                "expected a policy-store/<policy store id> resource",
+            ));
+        };
+
+        Ok(Self {
+            partition: partition.to_string(),
+            region: (!region.is_empty()).then(|| Region::new(region.to_string())),
+            account_id: account_id.to_string(),
+            policy_store_id: policy_store_id.to_string(),
+        })
+    }
+}
+
+impl Display for PolicyStoreArn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "arn:{}:verifiedpermissions:{}:{}:policy-store/{}",
+            self.partition,
+            self.region.as_ref().map_or("", Region::as_ref),
+            self.account_id,
+            self.policy_store_id
+        )
+    }
 }
+
+impl PolicyStoreArn {
+    /// Returns the region of the policy store, or the given region if the ARN has none.
+    pub fn region_or(&self, region: Region) -> Region {
+        self.region.clone().unwrap_or(region)
+    }
+
+    /// Returns the ARN of the given role in the account that owns the policy store.
+    pub fn role_arn(&self, role_name: &str) -> String {
+        format!(
+            "arn:{}:iam::{}:role/{role_name}",
+            self.partition, self.account_id
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use aws_config::default_provider::credentials::DefaultCredentialsChain;
+    use std::time::Duration;
+
     use aws_config::meta::region::ProvideRegion;
     use aws_credential_types::provider::SharedCredentialsProvider;
+    use aws_credential_types::Credentials;
+    use aws_sdk_verifiedpermissions::config::{AppName, IntoShared};
+    use std::str::FromStr;
+
+    use aws_smithy_runtime::client::http::test_util::{
+        capture_request, ReplayEvent, StaticReplayClient,
+    };
+    use aws_smithy_runtime_api::http::{Request, Response, StatusCode};
+    use aws_smithy_types::body::SdkBody;
     use aws_types::region::Region;
 
     use crate::public::client::{
-        verified_permissions_default_credentials, verified_permissions_with_credentials,
+        verified_permissions_assume_role, verified_permissions_default_credentials,
+        verified_permissions_with_credentials, verified_permissions_with_options,
+        AssumeRoleOptionsBuilder, ClientError, ClientOptions, ClientOptionsBuilder, PolicyStoreArn,
+        AVP_CLIENT_DEFAULT_TIMEOUT_MS, AVP_CLIENT_MAX_ATTEMPTS,
     };
 
+    const ASSUME_ROLE_RESPONSE: &str = r#"<AssumeRoleResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <AssumeRoleResult>
+    <AssumedRoleUser>
+      <AssumedRoleId>AROAEXAMPLE:avp-local-agent</AssumedRoleId>
+      <Arn>arn:aws:sts::123456789012:assumed-role/avp-reader/avp-local-agent</Arn>
+    </AssumedRoleUser>
+    <Credentials>
+      <AccessKeyId>ASIAASSUMED</AccessKeyId>
+      <SecretAccessKey>assumedsecret</SecretAccessKey>
+      <SessionToken>assumedtoken</SessionToken>
+      <Expiration>2100-01-01T00:00:00Z</Expiration>
+    </Credentials>
+  </AssumeRoleResult>
+</AssumeRoleResponse>"#;
+
     #[tokio::test]
     async fn build_client_with_region_and_creds() {
         let custom_region = Region::new("us-west-1");
@@ -120,4 +419,162 @@ mod test {
             custom_region
         );
     }
+
+    #[test]
+    fn default_options_keep_the_default_timeouts_and_attempts() {
+        let config = ClientOptions::default().sdk_config(
+            Region::new("us-east-1"),
+            SharedCredentialsProvider::new(Credentials::for_tests()),
+        );
+
+        let timeouts = config.timeout_config().unwrap();
+        assert_eq!(
+            timeouts.operation_attempt_timeout(),
+            Some(Duration::from_millis(AVP_CLIENT_DEFAULT_TIMEOUT_MS))
+        );
+        assert_eq!(
+            timeouts.operation_timeout(),
+            Some(Duration::from_millis(
+                AVP_CLIENT_DEFAULT_TIMEOUT_MS * u64::from(AVP_CLIENT_MAX_ATTEMPTS)
+            ))
+        );
+        assert_eq!(
+            config.retry_config().unwrap().max_attempts(),
+            AVP_CLIENT_MAX_ATTEMPTS
+        );
+        assert_eq!(config.endpoint_url(), None);
+        assert_eq!(config.use_fips(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn options_override_the_endpoint_and_http_client() {
+        let (http_client, request) = capture_request(None);
+        let options = ClientOptionsBuilder::default()
+            .endpoint_url("http://localhost:8080")
+            .http_client(http_client.into_shared())
+            .max_attempts(1)
+            .app_name(AppName::new("my-agent").unwrap())
+            .build()
+            .unwrap();
+        let client = verified_permissions_with_options(
+            Region::new("us-east-1"),
+            SharedCredentialsProvider::new(Credentials::for_tests()),
+            &options,
+        );
+
+        let _ = client.get_schema().policy_store_id("ps-1").send().await;
+
+        let request = request.expect_request();
+        assert!(request.uri().starts_with("http://localhost:8080"));
+        assert!(request
+            .headers()
+            .get("user-agent")
+            .into_iter()
+            .chain(request.headers().get("x-amz-user-agent"))
+            .any(|agent| agent.contains("app/my-agent")));
+    }
+
+    #[test]
+    fn policy_store_arn_is_parsed() {
+        let arn = PolicyStoreArn::from_str(
+            "arn:aws:verifiedpermissions::123456789012:policy-store/PSEXAMPLEabcdefg111111",
+        )
+        .unwrap();
+        assert_eq!(arn.partition, "aws");
+        assert_eq!(arn.region, None);
+        assert_eq!(arn.account_id, "123456789012");
+        assert_eq!(arn.policy_store_id, "PSEXAMPLEabcdefg111111");
+        assert_eq!(
+            arn.region_or(Region::new("us-east-1")),
+            Region::new("us-east-1")
+        );
+        assert_eq!(
+            arn.role_arn("avp-reader"),
+            "arn:aws:iam::123456789012:role/avp-reader"
+        );
+
+        let regional =
+            "arn:aws-us-gov:verifiedpermissions:us-gov-west-1:123456789012:policy-store/ps-1";
+        let arn = PolicyStoreArn::from_str(regional).unwrap();
+        assert_eq!(
+            arn.region_or(Region::new("us-east-1")),
+            Region::new("us-gov-west-1")
+        );
+        assert_eq!(arn.to_string(), regional);
+    }
+
+    #[test]
+    fn invalid_policy_store_arn_is_rejected() {
+        for arn in [
+            "ps-1",
+            "arn:aws:iam::123456789012:role/avp-reader",
+            "arn:aws:verifiedpermissions::account:policy-store/ps-1",
+            "arn:aws:verifiedpermissions::123456789012:policy/ps-1",
+            "arn:aws:verifiedpermissions::123456789012:policy-store/",
+        ] {
+            assert!(matches!(
+                PolicyStoreArn::from_str(arn),
+                Err(ClientError::InvalidPolicyStoreArn(..))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    #[allow(clippy::duration_suboptimal_units)]
+    async fn assume_role_signs_requests_with_the_assumed_credentials() {
+        let sts_http_client = StaticReplayClient::new(vec![ReplayEvent::new(
+            Request::new(SdkBody::empty()),
+            Response::new(
+                StatusCode::try_from(200).unwrap(),
+                SdkBody::from(ASSUME_ROLE_RESPONSE),
+            ),
+        )]);
+        let assume_role = AssumeRoleOptionsBuilder::default()
+            .role_arn("arn:aws:iam::123456789012:role/avp-reader")
+            .external_id("my-external-id")
+            .session_duration(Duration::from_secs(15 * 60))
+            .sts_options(
+                ClientOptionsBuilder::default()
+                    .endpoint_url("http://localhost:4566")
+                    .http_client(sts_http_client.clone().into_shared())
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let (avp_http_client, avp_request) = capture_request(None);
+        let client = verified_permissions_assume_role(
+            Region::new("us-east-1"),
+            SharedCredentialsProvider::new(Credentials::for_tests()),
+            &assume_role,
+            &ClientOptionsBuilder::default()
+                .http_client(avp_http_client.into_shared())
+                .max_attempts(1)
+                .build()
+                .unwrap(),
+        )
+        .await;
+
+        let _ = client.get_schema().policy_store_id("ps-1").send().await;
+
+        let sts_requests = sts_http_client.actual_requests().collect::<Vec<_>>();
+        assert_eq!(sts_requests.len(), 1);
+        assert!(sts_requests[0].uri().starts_with("http://localhost:4566"));
+        let sts_body = std::str::from_utf8(sts_requests[0].body().bytes().unwrap()).unwrap();
+        assert!(sts_body.contains("Action=AssumeRole"));
+        assert!(sts_body.contains("ExternalId=my-external-id"));
+        assert!(sts_body.contains("DurationSeconds=900"));
+        assert!(sts_body.contains("RoleSessionName=avp-local-agent"));
+
+        let avp_request = avp_request.expect_request();
+        assert!(avp_request
+            .headers()
+            .get("authorization")
+            .unwrap()
+            .contains("Credential=ASIAASSUMED/"));
+        assert_eq!(
+            avp_request.headers().get("x-amz-security-token"),
+            Some("assumedtoken")
+        );
+    }
 }
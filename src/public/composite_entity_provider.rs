@@ -0,0 +1,236 @@
+//! Combines the action entities of the Amazon Verified Permissions schema with application
+//! entities.
+//!
+//! A `CompositeEntityProvider` asks an `EntityResolver` for the principal, resource and other
+//! application entities of each `Request` and merges them with the action entities served by an
+//! `EntityProvider`. Resolved entities may not redefine an action entity, and can optionally be
+//! validated against the schema cached by the `EntityProvider`.
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cedar_local_agent::public::{
+    EntityProviderError, SimpleEntityProvider, UpdateProviderData, UpdateProviderDataError,
+};
+use cedar_policy::{entities_errors::EntitiesError, Entities, EntityUid, Request};
+use thiserror::Error;
+use tracing::{debug, instrument};
+
+use super::entity_provider::EntityProvider;
+
+/// `CompositeEntityProviderError` occurs when the resolved entities cannot be merged with the
+/// schema action entities.
+#[derive(Error, Debug)]
+pub enum CompositeEntityProviderError {
+    /// A resolved entity has the uid of an action entity derived from the schema
+    #[error("The resolved entity {0} conflicts with an action entity of the schema")]
+    ConflictingEntity(EntityUid),
+    /// The merged entities are invalid, or do not conform to the schema
+    #[error("The resolved entities are invalid: {0}")]
+    InvalidEntities(#[source] Box<EntitiesError>),
+}
+
+impl From<EntitiesError> for CompositeEntityProviderError {
+    fn from(value: EntitiesError) -> Self {
+        Self::InvalidEntities(Box::new(value))
+    }
+}
+
+impl From<CompositeEntityProviderError> for EntityProviderError {
+    fn from(value: CompositeEntityProviderError) -> Self {
+        Self::General(Box::new(value))
+    }
+}
+
+/// Resolves the application entities needed to evaluate a `Request`, such as the principal and
+/// resource entities with their attributes and parents.
+#[async_trait]
+pub trait EntityResolver: Debug + Send + Sync {
+    /// Returns the entities of the given request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `EntityProviderError` if the entities cannot be resolved.
+    async fn resolve(&self, request: &Request) -> Result<Entities, EntityProviderError>;
+}
+
+/// `CompositeEntityProvider` implements `SimpleEntityProvider` by merging the action entities of
+/// an `EntityProvider` with the entities of an `EntityResolver`.
+#[derive(Debug)]
+pub struct CompositeEntityProvider<R: EntityResolver> {
+    /// Serves the action entities derived from the schema
+    entity_provider: Arc<EntityProvider>,
+    /// Resolves the application entities of each request
+    resolver: R,
+    /// Whether the resolved entities are validated against the schema
+    validate: bool,
+}
+
+impl<R: EntityResolver> CompositeEntityProvider<R> {
+    /// Creates a `CompositeEntityProvider` that does not validate the resolved entities.
+    pub const fn new(entity_provider: Arc<EntityProvider>, resolver: R) -> Self {
+        Self {
+            entity_provider,
+            resolver,
+            validate: false,
+        }
+    }
+
+    /// Validates the resolved entities against the schema of the `EntityProvider`. Entities are not
+    /// validated while the policy store has no schema.
+    #[must_use]
+    pub const fn with_validation(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+}
+
+#[async_trait]
+impl<R: EntityResolver> SimpleEntityProvider for CompositeEntityProvider<R> {
+    #[instrument(skip_all, err(Debug))]
+    async fn get_entities(&self, request: &Request) -> Result<Arc<Entities>, EntityProviderError> {
+        let action_entities = self.entity_provider.get_entities(request).await?;
+        let resolved_entities = self.resolver.resolve(request).await?;
+
+        if let Some(entity) = resolved_entities
+            .iter()
+            .find(|entity| action_entities.get(&entity.uid()).is_some())
+        {
+            return Err(CompositeEntityProviderError::ConflictingEntity(entity.uid()).into());
+        }
+
+        let schema = if self.validate {
+            self.entity_provider.schema().await
+        } else {
+            None
+        };
+        debug!(
+            "Merging resolved entities with schema action entities: validate={}",
+            schema.is_some()
+        );
+
+        let entities = Entities::from_entities(
+            action_entities
+                .iter()
+                .chain(resolved_entities.iter())
+                .cloned(),
+            schema.as_deref(),
+        )
+        .map_err(CompositeEntityProviderError::from)?;
+        Ok(Arc::new(entities))
+    }
+}
+
+#[async_trait]
+impl<R: EntityResolver> UpdateProviderData for CompositeEntityProvider<R> {
+    #[instrument(skip(self), err(Debug))]
+    async fn update_provider_data(&self) -> Result<(), UpdateProviderDataError> {
+        self.entity_provider.update_provider_data().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use cedar_local_agent::public::{EntityProviderError, SimpleEntityProvider};
+    use cedar_policy::{Context, Entities, EntityUid, Request};
+
+    use crate::private::sources::test::build_client;
+    use crate::public::entity_provider::EntityProvider;
+    use crate::public::schema_provider::test::{build_schema_event, POLICY_STORE_ID, SCHEMA};
+
+    use super::{CompositeEntityProvider, CompositeEntityProviderError, EntityResolver};
+
+    #[derive(Debug)]
+    struct StaticResolver(&'static str);
+
+    #[async_trait::async_trait]
+    impl EntityResolver for StaticResolver {
+        async fn resolve(&self, _: &Request) -> Result<Entities, EntityProviderError> {
+            Entities::from_json_str(self.0, None)
+                .map_err(|e| EntityProviderError::General(e.into()))
+        }
+    }
+
+    fn build_entity_provider() -> Arc<EntityProvider> {
+        let client = build_client(vec![build_schema_event(SCHEMA, "2024-01-01T00:00:00Z")]);
+        Arc::new(EntityProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap())
+    }
+
+    fn build_request() -> Request {
+        Request::new(
+            EntityUid::from_str(r#"User::"alice""#).unwrap(),
+            EntityUid::from_str(r#"Action::"view""#).unwrap(),
+            EntityUid::from_str(r#"Photo::"vacation.jpg""#).unwrap(),
+            Context::empty(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn get_entities_merges_action_and_resolved_entities() {
+        let provider = CompositeEntityProvider::new(
+            build_entity_provider(),
+            StaticResolver(
+                r#"[{"uid": {"type": "User", "id": "alice"}, "attrs": {"name": "Alice"}, "parents": []}]"#,
+            ),
+        )
+        .with_validation(true);
+
+        let entities = provider.get_entities(&build_request()).await.unwrap();
+
+        assert!(entities
+            .get(&EntityUid::from_str(r#"Action::"view""#).unwrap())
+            .is_some());
+        assert!(entities
+            .get(&EntityUid::from_str(r#"User::"alice""#).unwrap())
+            .is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn get_entities_rejects_resolved_action_entities() {
+        let provider = CompositeEntityProvider::new(
+            build_entity_provider(),
+            StaticResolver(
+                r#"[{"uid": {"type": "Action", "id": "view"}, "attrs": {}, "parents": []}]"#,
+            ),
+        );
+
+        let Err(EntityProviderError::General(error)) =
+            provider.get_entities(&build_request()).await
+        else {
+            panic!("expected the resolved action entity to conflict");
+        };
+        assert!(matches!(
+            error.downcast_ref::<CompositeEntityProviderError>(),
+            Some(CompositeEntityProviderError::ConflictingEntity(uid))
+                if uid.to_string() == r#"Action::"view""#
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn get_entities_validates_resolved_entities_against_the_schema() {
+        let resolver =
+            r#"[{"uid": {"type": "User", "id": "alice"}, "attrs": {"name": 7}, "parents": []}]"#;
+
+        let unvalidated =
+            CompositeEntityProvider::new(build_entity_provider(), StaticResolver(resolver));
+        assert!(unvalidated.get_entities(&build_request()).await.is_ok());
+
+        let validated =
+            CompositeEntityProvider::new(build_entity_provider(), StaticResolver(resolver))
+                .with_validation(true);
+        let Err(EntityProviderError::General(error)) =
+            validated.get_entities(&build_request()).await
+        else {
+            panic!("expected the resolved entities to fail validation");
+        };
+        assert!(matches!(
+            error.downcast_ref::<CompositeEntityProviderError>(),
+            Some(CompositeEntityProviderError::InvalidEntities(_))
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+//! A guard that rejects `PolicySetProvider` refreshes removing a large part of the served
+//! `PolicySet`.
+//!
+//! This protects against most of a policy store being deleted by mistake, or a misconfigured
+//! filter matching nothing.
+use cedar_policy::PolicySet;
+
+/// Limits on the number of policies a single refresh may remove from the served `PolicySet`.
+/// A refresh exceeding any configured limit is rejected.
+///
+/// # Examples
+///
+/// ```
+/// use avp_local_agent::public::deletion_guard::DeletionGuard;
+///
+/// let guard = DeletionGuard {
+///     max_removed_policies: Some(100),
+///     max_removed_percent: Some(20),
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeletionGuard {
+    /// The maximum number of policies a refresh may remove
+    pub max_removed_policies: Option<usize>,
+    /// The maximum percentage of the served policies a refresh may remove
+    pub max_removed_percent: Option<u8>,
+}
+
+/// The policies a refresh would remove, reported when the `DeletionGuard` rejects it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemovedPolicies {
+    /// The number of served policies missing from the refreshed `PolicySet`
+    pub removed: usize,
+    /// The number of policies in the served `PolicySet`
+    pub total: usize,
+}
+
+impl DeletionGuard {
+    /// Compares the served `PolicySet` to a refreshed one.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `RemovedPolicies` if they exceed any of the configured limits.
+    pub fn check(&self, served: &PolicySet, refreshed: &PolicySet) -> Result<(), RemovedPolicies> {
+        let total = served.policies().count();
+        let removed = served
+            .policies()
+            .filter(|policy| refreshed.policy(policy.id()).is_none())
+            .count();
+
+        let exceeds_count = self
+            .max_removed_policies
+            .is_some_and(|max_removed| removed > max_removed);
+        let exceeds_percent = self
+            .max_removed_percent
+            .is_some_and(|max_percent| removed * 100 > usize::from(max_percent) * total);
+
+        if exceeds_count || exceeds_percent {
+            Err(RemovedPolicies { removed, total })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cedar_policy::PolicySet;
+
+    use crate::public::deletion_guard::{DeletionGuard, RemovedPolicies};
+    use crate::public::policy_set_history::test::policy_set;
+
+    #[test]
+    fn default_guard_allows_any_removal() {
+        assert!(DeletionGuard::default()
+            .check(&policy_set(10), &PolicySet::new())
+            .is_ok());
+    }
+
+    #[test]
+    fn count_limit_rejects_removals_above_the_limit() {
+        let guard = DeletionGuard {
+            max_removed_policies: Some(3),
+            max_removed_percent: None,
+        };
+
+        assert!(guard.check(&policy_set(10), &policy_set(7)).is_ok());
+        assert_eq!(
+            guard.check(&policy_set(10), &policy_set(6)),
+            Err(RemovedPolicies {
+                removed: 4,
+                total: 10
+            })
+        );
+    }
+
+    #[test]
+    fn percent_limit_rejects_removals_above_the_limit() {
+        let guard = DeletionGuard {
+            max_removed_policies: None,
+            max_removed_percent: Some(50),
+        };
+
+        assert!(guard.check(&policy_set(10), &policy_set(5)).is_ok());
+        assert_eq!(
+            guard.check(&policy_set(10), &policy_set(4)),
+            Err(RemovedPolicies {
+                removed: 6,
+                total: 10
+            })
+        );
+    }
+
+    #[test]
+    fn added_policies_do_not_count_as_removals() {
+        let guard = DeletionGuard {
+            max_removed_policies: Some(0),
+            max_removed_percent: Some(0),
+        };
+
+        assert!(guard.check(&policy_set(2), &policy_set(5)).is_ok());
+        assert!(guard.check(&PolicySet::new(), &PolicySet::new()).is_ok());
+    }
+}
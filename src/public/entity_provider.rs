@@ -1,6 +1,5 @@
 //! Provides an Amazon Verified Permissions Entity provider!
 use std::fmt::Debug;
-use std::str::FromStr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -8,21 +7,20 @@ use aws_sdk_verifiedpermissions::Client;
 use cedar_policy::{
     entities_errors::EntitiesError, CedarSchemaError, Entities, Request, Schema, SchemaError,
 };
-use derive_builder::Builder;
 use thiserror::Error;
 use tokio::runtime::Handle;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::RwLock;
 use tokio::task;
-use tracing::{error, info, instrument};
+use tracing::{debug, info, instrument};
 
 use cedar_local_agent::public::{
     EntityProviderError, SimpleEntityProvider, UpdateProviderData, UpdateProviderDataError,
 };
 
-use crate::private::sources::schema::core::VerifiedPermissionsSchemaSource;
 use crate::private::sources::schema::error::SchemaException;
-use crate::private::sources::Read;
-use crate::private::types::policy_selector::PolicySelector;
+use crate::private::translator::error::TranslatorException;
+
+use super::schema_provider::{ProviderError as SchemaProviderError, SchemaProvider};
 
 /// `ProviderError` can occur during construction of the `EntityProvider`
 #[derive(Error, Debug)]
@@ -35,40 +33,55 @@ pub enum ProviderError {
     RetrieveException(#[from] SchemaException),
     /// Schema file is malformed in some way
     #[error("The Schema file failed to be parsed")]
-    SchemaParse(#[from] SchemaError),
+    SchemaParse(#[source] Box<SchemaError>),
     /// Cannot extract entities from the schema
     #[error("Failed to extract entities from the schema")]
-    ExtractEntities(#[from] EntitiesError),
+    ExtractEntities(#[source] Box<EntitiesError>),
     /// Cannot parse Cedar schema
     #[error("Cedar schema cadnno be parsed")]
-    CedarSchemaError(#[from] CedarSchemaError),
+    CedarSchemaError(#[source] Box<CedarSchemaError>),
+    /// The schema cannot be parsed in its JSON or Cedar format
+    #[error("The schema failed to be parsed: {0}")]
+    ParseSchema(#[from] TranslatorException),
+}
+
+impl From<SchemaError> for ProviderError {
+    fn from(value: SchemaError) -> Self {
+        Self::SchemaParse(Box::new(value))
+    }
 }
 
-impl From<ConfigBuilderError> for ProviderError {
-    fn from(value: ConfigBuilderError) -> Self {
-        Self::Configuration(value.to_string())
+impl From<CedarSchemaError> for ProviderError {
+    fn from(value: CedarSchemaError) -> Self {
+        Self::CedarSchemaError(Box::new(value))
     }
 }
 
-/// Configuration for the Entity Provider used internally for constructing the `EntityProvider`
-#[derive(Builder, Debug)]
-#[builder(pattern = "owned")]
-struct Config {
-    /// Retrieves Schema from Amazon Verified Permissions
-    pub schema_source: VerifiedPermissionsSchemaSource,
-    /// The policy store id to retrieve the schema for
-    pub policy_selector: PolicySelector,
+impl From<EntitiesError> for ProviderError {
+    fn from(value: EntitiesError) -> Self {
+        Self::ExtractEntities(Box::new(value))
+    }
+}
+
+impl From<SchemaProviderError> for ProviderError {
+    fn from(value: SchemaProviderError) -> Self {
+        match value {
+            SchemaProviderError::Configuration(error) => Self::Configuration(error),
+            SchemaProviderError::RetrieveException(error) => Self::RetrieveException(error),
+            SchemaProviderError::ParseSchema(error) => Self::ParseSchema(error),
+        }
+    }
 }
 
 /// `EntityProvider` structure implements the `SimpleEntityProvider` trait.
 #[derive(Debug)]
 pub struct EntityProvider {
-    /// Entities path, stored to allow refreshing from disk.
-    policy_selector: PolicySelector,
-    /// Schema Source
-    schema_source: Arc<Mutex<VerifiedPermissionsSchemaSource>>,
+    /// Provides the schema the action entities are derived from.
+    schema_provider: Arc<SchemaProvider>,
     /// Entities can be updated through a back ground thread.
     entities: RwLock<Arc<Entities>>,
+    /// The schema the entities were derived from, to skip the update while it is unchanged.
+    entities_schema: RwLock<Option<Arc<Schema>>>,
 }
 
 /// Implementation for the Entity Provider
@@ -86,65 +99,57 @@ impl EntityProvider {
         policy_store_id: String,
         verified_permissions_client: Client,
     ) -> Result<Self, ProviderError> {
-        Self::new(
-            ConfigBuilder::default()
-                .policy_selector(PolicySelector::from(policy_store_id))
-                .schema_source(VerifiedPermissionsSchemaSource::from(
-                    verified_permissions_client,
-                ))
-                .build()?,
-        )
+        let schema_provider = Arc::new(SchemaProvider::from_client(
+            policy_store_id,
+            verified_permissions_client,
+        )?);
+        task::block_in_place(move || {
+            Handle::current().block_on(Self::from_schema_provider(schema_provider))
+        })
     }
 
-    #[instrument(skip(config), err(Debug))]
-    fn new(config: Config) -> Result<Self, ProviderError> {
-        let Config {
-            policy_selector,
-            schema_source,
-        } = config;
-
-        let schema_source = Arc::new(Mutex::new(schema_source));
-        let schema_source_ref = schema_source.clone();
-        let policy_store_id_clone = policy_selector.clone();
-        let fetch_schema_result = task::block_in_place(move || {
-            Handle::current().block_on(async move {
-                schema_source_ref
-                    .lock()
-                    .await
-                    .reader
-                    .read(policy_store_id_clone.clone())
-                    .await
-            })
-        });
-
-        match fetch_schema_result {
-            Ok(get_schema_output) => {
-                let schema = Schema::from_str(&get_schema_output.schema)?;
-
-                Ok(Self {
-                    policy_selector,
-                    schema_source,
-                    entities: RwLock::new(Arc::new(schema.action_entities()?)),
-                })
-            }
-            Err(error) => match error {
-                SchemaException::AccessDenied(_)
-                | SchemaException::Validation(_)
-                | SchemaException::Retryable(_)
-                | SchemaException::Unhandled(_) => {
-                    error!("Failed to get the schema on initialization: {error:?}");
-                    Err(ProviderError::RetrieveException(error))
-                }
-                SchemaException::ResourceNotFound(_) => Ok(Self {
-                    policy_selector,
-                    schema_source,
-                    entities: RwLock::new(Arc::new(Entities::empty())),
-                }),
-            },
-        }
+    /// Builds an `EntityProvider` deriving its action entities from the schema of the given
+    /// `SchemaProvider`, without fetching the schema again.
+    ///
+    /// Refreshing the `EntityProvider` refreshes the `SchemaProvider`, so only the
+    /// `EntityProvider` needs an update task when both are used.
+    ///
+    /// # Errors
+    ///
+    /// Can error if the action entities cannot be extracted from the schema.
+    #[instrument(skip_all, err(Debug))]
+    pub async fn from_schema_provider(
+        schema_provider: Arc<SchemaProvider>,
+    ) -> Result<Self, ProviderError> {
+        let schema = schema_provider.get_schema().await;
+        let entities = action_entities(schema.as_deref())?;
+        Ok(Self {
+            schema_provider,
+            entities: RwLock::new(Arc::new(entities)),
+            entities_schema: RwLock::new(schema),
+        })
+    }
+
+    /// Returns the schema most recently retrieved from Amazon Verified Permissions, or `None` if
+    /// the policy store has no schema.
+    pub async fn schema(&self) -> Option<Arc<Schema>> {
+        self.schema_provider.get_schema().await
+    }
+
+    /// Returns the `SchemaProvider` the action entities are derived from.
+    pub fn schema_provider(&self) -> Arc<SchemaProvider> {
+        self.schema_provider.clone()
     }
 }
 
+/// Extracts the action entities of the schema, no entities if there is no schema.
+fn action_entities(schema: Option<&Schema>) -> Result<Entities, ProviderError> {
+    Ok(match schema {
+        Some(schema) => schema.action_entities()?,
+        None => Entities::empty(),
+    })
+}
+
 #[async_trait]
 impl SimpleEntityProvider for EntityProvider {
     #[instrument(skip_all, err(Debug))]
@@ -157,42 +162,115 @@ impl SimpleEntityProvider for EntityProvider {
 impl UpdateProviderData for EntityProvider {
     #[instrument(skip(self), err(Debug))]
     async fn update_provider_data(&self) -> Result<(), UpdateProviderDataError> {
-        let fetch_schema_result = self
-            .schema_source
-            .lock()
-            .await
-            .reader
-            .read(self.policy_selector.clone())
-            .await;
-
-        let entities = match fetch_schema_result {
-            Ok(get_schema_output) => {
-                let schema = Schema::from_str(&get_schema_output.schema).map_err(|e| match e {
-                    CedarSchemaError::Schema(err) => {
-                        UpdateProviderDataError::General(Box::new(ProviderError::from(err)))
-                    }
-                    _ => UpdateProviderDataError::General(Box::new(e)),
-                })?;
-                schema.action_entities().map_err(|e| {
-                    UpdateProviderDataError::General(Box::new(ProviderError::from(e)))
-                })?
-            }
-            Err(error) => match error {
-                SchemaException::AccessDenied(_)
-                | SchemaException::Validation(_)
-                | SchemaException::Retryable(_)
-                | SchemaException::Unhandled(_) => {
-                    return Err(UpdateProviderDataError::General(Box::new(error)));
-                }
-                SchemaException::ResourceNotFound(_) => Entities::empty(),
-            },
+        self.schema_provider.update_provider_data().await?;
+        let schema = self.schema_provider.get_schema().await;
+
+        let mut entities_schema = self.entities_schema.write().await;
+        let unchanged = match (entities_schema.as_ref(), schema.as_ref()) {
+            (Some(current), Some(latest)) => Arc::ptr_eq(current, latest),
+            (None, None) => true,
+            _ => false,
         };
+        if unchanged {
+            debug!("Schema is unchanged, keeping the current entities");
+            return Ok(());
+        }
 
+        let entities = action_entities(schema.as_deref())
+            .map_err(|e| UpdateProviderDataError::General(Box::new(e)))?;
         {
             let mut entities_data = self.entities.write().await;
             *entities_data = Arc::new(entities);
         }
+        *entities_schema = schema;
+        drop(entities_schema);
         info!("Updated Entity Provider");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use cedar_local_agent::public::{SimpleEntityProvider, UpdateProviderData};
+    use cedar_policy::{Context, EntityUid, Request};
+
+    use crate::private::sources::test::build_client;
+    use crate::public::schema_provider::test::{build_schema_event, POLICY_STORE_ID, SCHEMA};
+    use crate::public::schema_provider::SchemaProvider;
+
+    use super::EntityProvider;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn from_schema_provider_shares_the_schema_fetch() {
+        let client = build_client(vec![
+            build_schema_event(SCHEMA, "2024-01-01T00:00:00Z"),
+            build_schema_event(
+                &format!(
+                    "{SCHEMA} action edit appliesTo {{ principal: [User], resource: [Photo] }};"
+                ),
+                "2024-01-02T00:00:00Z",
+            ),
+        ]);
+        let schema_provider =
+            Arc::new(SchemaProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap());
+        let entity_provider = EntityProvider::from_schema_provider(schema_provider.clone())
+            .await
+            .unwrap();
+        let request = Request::new(
+            EntityUid::from_str(r#"User::"alice""#).unwrap(),
+            EntityUid::from_str(r#"Action::"view""#).unwrap(),
+            EntityUid::from_str(r#"Photo::"vacation.jpg""#).unwrap(),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        let edit = EntityUid::from_str(r#"Action::"edit""#).unwrap();
+
+        let entities = entity_provider.get_entities(&request).await.unwrap();
+        assert_eq!(entities.iter().count(), 1);
+        assert!(entities.get(&edit).is_none());
+
+        entity_provider.update_provider_data().await.unwrap();
+
+        let entities = entity_provider.get_entities(&request).await.unwrap();
+        assert!(entities.get(&edit).is_some());
+        assert_eq!(
+            schema_provider
+                .get_schema()
+                .await
+                .unwrap()
+                .actions()
+                .count(),
+            2
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn update_provider_data_keeps_entities_when_schema_is_unchanged() {
+        let client = build_client(vec![
+            build_schema_event(SCHEMA, "2024-01-01T00:00:00Z"),
+            build_schema_event(SCHEMA, "2024-01-01T00:00:00Z"),
+        ]);
+        let entity_provider =
+            EntityProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+        let request = Request::new(
+            EntityUid::from_str(r#"User::"alice""#).unwrap(),
+            EntityUid::from_str(r#"Action::"view""#).unwrap(),
+            EntityUid::from_str(r#"Photo::"vacation.jpg""#).unwrap(),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        let entities = entity_provider.get_entities(&request).await.unwrap();
+
+        entity_provider.update_provider_data().await.unwrap();
+
+        assert!(Arc::ptr_eq(
+            &entities,
+            &entity_provider.get_entities(&request).await.unwrap()
+        ));
+    }
+}
@@ -0,0 +1,760 @@
+//! An Envoy external authorization (`ext_authz`) gRPC service evaluating the requests proxied by
+//! Envoy against the providers of a policy store, without a call to Amazon Verified Permissions.
+//!
+//! `ExtAuthz::check` maps the HTTP attributes of a `CheckRequest` to a Cedar request with
+//! `ExtAuthzOptions`:
+//!
+//! * the principal comes from a bearer token verified by a `TokenVerifier`, a header set by Envoy
+//!   after it verified a JWT, or the authenticated peer of the connection,
+//! * the action and resource ids are templates over `{method}`, `{path}`, `{host}`, `{scheme}`,
+//!   `{header:<name>}` and `{extension:<name>}`, the last reading the `context_extensions` of the
+//!   route,
+//! * the context holds the access token claims when the principal comes from a token, and the
+//!   `method`, `path`, `host` and `scheme` of the request and the configured `headers`, which
+//!   replace claims of the same name.
+//!
+//! Allowed requests are forwarded with the `x-avp-decision` and `x-avp-determining-policies`
+//! headers, denied requests are answered with the configured status and the same headers.
+//! Requests without a principal are answered with `401 Unauthorized`.
+pub mod proto;
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use aws_sdk_verifiedpermissions::types::AttributeValue;
+use cedar_local_agent::public::simple::{Authorizer, AuthorizerConfigBuilder, AuthorizerError};
+use cedar_policy::Decision;
+use derive_builder::Builder;
+use thiserror::Error;
+use tonic::codegen::{http, Body, BoxFuture, Context, Poll, Service, StdError};
+use tonic::server::{Grpc, NamedService, UnaryService};
+use tonic_prost::ProstCodec;
+use tracing::{debug, info, instrument};
+
+use crate::private::translator::http_request::{
+    cedar_request, header_record, HttpAttributes, Principal, RequestTemplates,
+};
+
+use self::proto::check_response::HttpResponse;
+use self::proto::{
+    AttributeContext, CheckRequest, CheckResponse, DeniedHttpResponse, HeaderValue,
+    HeaderValueOption, HttpRequest, HttpStatus, OkHttpResponse, Status,
+};
+use super::agent_config::PolicyStoreProviders;
+use super::policy_set_provider::PolicySetProvider;
+use super::token_verifier::TokenVerifier;
+
+/// The gRPC path of the `Check` method of the Envoy authorization service.
+const CHECK_PATH: &str = "/envoy.service.auth.v3.Authorization/Check";
+/// The header carrying the decision.
+pub const DECISION_HEADER: &str = "x-avp-decision";
+/// The header carrying the comma separated ids of the determining policies.
+pub const DETERMINING_POLICIES_HEADER: &str = "x-avp-determining-policies";
+/// `HeaderValueOption.append_action` replacing a header sent by the client.
+const OVERWRITE_IF_EXISTS_OR_ADD: i32 = 2;
+/// The gRPC code `PERMISSION_DENIED`.
+const PERMISSION_DENIED: i32 = 7;
+/// The gRPC code `UNAUTHENTICATED`.
+const UNAUTHENTICATED: i32 = 16;
+
+/// `ExtAuthzError` can occur when building the adapter or evaluating a request.
+#[derive(Error, Debug)]
+pub enum ExtAuthzError {
+    /// The authorizer of the adapter cannot be built
+    #[error("Invalid ext_authz configuration: {0}")]
+    Configuration(String),
+    /// The providers failed to evaluate the request
+    #[error("The authorizer failed to evaluate the request: {0}")]
+    Authorizer(#[from] AuthorizerError),
+    /// The gRPC server failed
+    #[error("The ext_authz server failed: {0}")]
+    Transport(#[from] tonic::transport::Error),
+}
+
+/// Where the principal of a request is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrincipalMapping {
+    /// The bearer token of the `authorization` header is verified as an access token by the
+    /// `TokenVerifier` of the adapter, which also adds its claims to the context
+    AccessToken,
+    /// The bearer token of the `authorization` header is verified as an identity token by the
+    /// `TokenVerifier` of the adapter
+    IdentityToken,
+    /// The principal id is read from a header, for example one set by the Envoy `jwt_authn`
+    /// filter from a claim of the token it verified
+    Header {
+        /// The lowercase name of the header
+        header: String,
+        /// The entity type of the principal
+        entity_type: String,
+    },
+    /// The principal id is the authenticated identity of the downstream peer, such as the
+    /// SPIFFE id of its mTLS certificate
+    Peer {
+        /// The entity type of the principal
+        entity_type: String,
+    },
+}
+
+/// How the `CheckRequest` attributes map to a Cedar request.
+///
+/// ```
+/// use avp_local_agent::public::ext_authz::{ExtAuthzOptionsBuilder, PrincipalMapping};
+///
+/// let options = ExtAuthzOptionsBuilder::default()
+///     .principal(PrincipalMapping::Header {
+///         header: "x-user-id".to_string(),
+///         entity_type: "MyApp::User".to_string(),
+///     })
+///     .action_type("MyApp::Action")
+///     .action_id("{method} {extension:route}")
+///     .resource_type("MyApp::Document")
+///     .resource_id("{path}")
+///     .context_headers(vec!["x-tenant".to_string()])
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned")]
+pub struct ExtAuthzOptions {
+    /// Where the principal is read from
+    pub principal: PrincipalMapping,
+    /// The entity type of the action
+    #[builder(default = "\"Action\".to_string()", setter(into))]
+    pub action_type: String,
+    /// The template of the action id
+    #[builder(default = "\"{method}\".to_string()", setter(into))]
+    pub action_id: String,
+    /// The entity type of the resource
+    #[builder(setter(into))]
+    pub resource_type: String,
+    /// The template of the resource id
+    #[builder(default = "\"{path}\".to_string()", setter(into))]
+    pub resource_id: String,
+    /// The lowercase names of the headers added to the `headers` record of the context
+    #[builder(default)]
+    pub context_headers: Vec<String>,
+    /// The HTTP status of denied requests
+    #[builder(default = "403")]
+    pub denied_status: u16,
+}
+
+/// The attributes of a `CheckRequest` resolving the placeholders of the id templates.
+struct CheckAttributes<'a> {
+    /// The HTTP attributes of the request
+    http: &'a HttpRequest,
+    /// The `context_extensions` of the route
+    extensions: &'a HashMap<String, String>,
+    /// The lowercase names of the headers added to the context
+    context_headers: &'a [String],
+}
+
+impl HttpAttributes for CheckAttributes<'_> {
+    fn placeholder(&self, placeholder: &str) -> Result<Option<String>, String> {
+        Ok(match placeholder.split_once(':') {
+            None if placeholder == "method" => Some(self.http.method.clone()),
+            None if placeholder == "path" => Some(path(self.http)),
+            None if placeholder == "host" => Some(self.http.host.clone()),
+            None if placeholder == "scheme" => Some(self.http.scheme.clone()),
+            Some(("header", name)) => self.http.headers.get(name).cloned(),
+            Some(("extension", name)) => self.extensions.get(name).cloned(),
+            _ => return Err(format!("unknown placeholder {{{placeholder}}}")),
+        })
+    }
+
+    fn context(&self) -> HashMap<String, AttributeValue> {
+        let string = |value: &String| AttributeValue::String(value.clone());
+        HashMap::from([
+            ("method".to_string(), string(&self.http.method)),
+            ("path".to_string(), AttributeValue::String(path(self.http))),
+            ("host".to_string(), string(&self.http.host)),
+            ("scheme".to_string(), string(&self.http.scheme)),
+            (
+                "headers".to_string(),
+                header_record(self.context_headers, &self.http.headers),
+            ),
+        ])
+    }
+}
+
+/// Evaluates Envoy `CheckRequest`s against the providers of one policy store.
+///
+/// # Examples
+///
+/// ```no_run
+/// use avp_local_agent::public::agent_config::{AgentConfig, AgentProviders};
+/// use avp_local_agent::public::ext_authz::{ExtAuthz, ExtAuthzOptionsBuilder, PrincipalMapping};
+///
+/// # async fn example() {
+/// let config = AgentConfig::from_file("agent.toml").unwrap();
+/// let providers = AgentProviders::from_config(&config).await.unwrap();
+/// providers.spawn_refresh_tasks();
+///
+/// let ext_authz = ExtAuthz::new(
+///     providers.policy_stores()[0].clone(),
+///     ExtAuthzOptionsBuilder::default()
+///         .principal(PrincipalMapping::Peer {
+///             entity_type: "Service".to_string(),
+///         })
+///         .resource_type("Endpoint")
+///         .build()
+///         .unwrap(),
+/// )
+/// .unwrap();
+/// ext_authz.serve("127.0.0.1:9191".parse().unwrap()).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ExtAuthz {
+    /// Evaluates the mapped requests with the providers of the policy store
+    authorizer: Authorizer<PolicySetProvider, PolicyStoreProviders>,
+    /// Verifies the bearer tokens of the token principal mappings
+    token_verifier: Option<Arc<TokenVerifier>>,
+    /// How the requests are mapped
+    options: ExtAuthzOptions,
+}
+
+impl ExtAuthz {
+    /// Creates an `ExtAuthz` evaluating requests against `providers`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExtAuthzError::Configuration` if the authorizer cannot be built.
+    pub fn new(
+        providers: PolicyStoreProviders,
+        options: ExtAuthzOptions,
+    ) -> Result<Self, ExtAuthzError> {
+        let authorizer = Authorizer::new(
+            AuthorizerConfigBuilder::default()
+                .policy_set_provider(providers.policy_set_provider.clone())
+                .entity_provider(Arc::new(providers))
+                .build()
+                .map_err(|e| ExtAuthzError::Configuration(e.to_string()))?,
+        );
+
+        Ok(Self {
+            authorizer,
+            token_verifier: None,
+            options,
+        })
+    }
+
+    /// Verifies the bearer tokens of the `AccessToken` and `IdentityToken` principal mappings
+    /// with `token_verifier`.
+    #[must_use]
+    pub fn with_token_verifier(mut self, token_verifier: Arc<TokenVerifier>) -> Self {
+        self.token_verifier = Some(token_verifier);
+        self
+    }
+
+    /// Evaluates a `CheckRequest`. Requests that cannot be mapped are denied.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExtAuthzError::Authorizer` if a provider fails, Envoy then applies its
+    /// `failure_mode_allow` setting.
+    #[instrument(skip_all, err(Debug))]
+    pub async fn check(
+        &self,
+        check_request: &CheckRequest,
+    ) -> Result<CheckResponse, ExtAuthzError> {
+        let default_attributes = AttributeContext::default();
+        let attributes = check_request
+            .attributes
+            .as_ref()
+            .unwrap_or(&default_attributes);
+        let default_http = HttpRequest::default();
+        let http = attributes
+            .request
+            .as_ref()
+            .and_then(|request| request.http.as_ref())
+            .unwrap_or(&default_http);
+
+        let principal = match self.principal(attributes, http).await {
+            Ok(principal) => principal,
+            Err(message) => {
+                debug!("Unauthenticated request: {message}");
+                return Ok(denied(401, UNAUTHENTICATED, message, Vec::new()));
+            }
+        };
+
+        let mapped = self.cedar_request(attributes, http, principal);
+        let (request, entities) = match mapped {
+            Ok(mapped) => mapped,
+            Err(message) => {
+                debug!("Unmapped request: {message}");
+                return Ok(denied(
+                    self.options.denied_status,
+                    PERMISSION_DENIED,
+                    message,
+                    Vec::new(),
+                ));
+            }
+        };
+
+        let response = self.authorizer.is_authorized(&request, &entities).await?;
+        let mut policy_ids = response
+            .diagnostics()
+            .reason()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        policy_ids.sort();
+        let decision = response.decision();
+        let headers = vec![
+            header(
+                DECISION_HEADER,
+                if decision == Decision::Allow {
+                    "ALLOW"
+                } else {
+                    "DENY"
+                },
+            ),
+            header(DETERMINING_POLICIES_HEADER, &policy_ids.join(",")),
+        ];
+
+        Ok(match decision {
+            Decision::Allow => CheckResponse {
+                status: Some(Status::default()),
+                http_response: Some(HttpResponse::OkResponse(OkHttpResponse {
+                    headers,
+                    headers_to_remove: Vec::new(),
+                })),
+            },
+            Decision::Deny => denied(
+                self.options.denied_status,
+                PERMISSION_DENIED,
+                String::new(),
+                headers,
+            ),
+        })
+    }
+
+    /// Returns the gRPC service of the adapter, to be added to a `tonic` server.
+    pub fn into_server(self) -> AuthorizationServer {
+        AuthorizationServer {
+            inner: Arc::new(self),
+        }
+    }
+
+    /// Serves the adapter over gRPC on `addr` until the server fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExtAuthzError::Transport` if the address cannot be bound or the server fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), ExtAuthzError> {
+        info!("Serving the ext_authz service: address={addr}");
+        tonic::transport::Server::builder()
+            .add_service(self.into_server())
+            .serve(addr)
+            .await?;
+        Ok(())
+    }
+
+    /// Reads the principal of the request, or describes why it is missing.
+    async fn principal(
+        &self,
+        attributes: &AttributeContext,
+        http: &HttpRequest,
+    ) -> Result<Principal, String> {
+        let token = || {
+            http.headers
+                .get("authorization")
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or_else(|| "the request has no bearer token".to_string())
+        };
+        let token_verifier = || {
+            self.token_verifier
+                .as_ref()
+                .ok_or_else(|| "no token verifier is configured".to_string())
+        };
+
+        match &self.options.principal {
+            PrincipalMapping::AccessToken => Ok(token_verifier()?
+                .verify(None, Some(token()?))
+                .await
+                .map_err(|e| e.to_string())?
+                .into()),
+            PrincipalMapping::IdentityToken => Ok(token_verifier()?
+                .verify(Some(token()?), None)
+                .await
+                .map_err(|e| e.to_string())?
+                .into()),
+            PrincipalMapping::Header {
+                header,
+                entity_type,
+            } => match http.headers.get(header) {
+                Some(id) if !id.is_empty() => Principal::new(entity_type, id),
+                _ => Err(format!("the request has no {header} header")),
+            },
+            PrincipalMapping::Peer { entity_type } => match attributes.source.as_ref() {
+                Some(source) if !source.principal.is_empty() => {
+                    Principal::new(entity_type, &source.principal)
+                }
+                _ => Err("the downstream peer is not authenticated".to_string()),
+            },
+        }
+    }
+
+    /// Maps the request to a Cedar request and the entities of its principal.
+    fn cedar_request(
+        &self,
+        attributes: &AttributeContext,
+        http: &HttpRequest,
+        principal: Principal,
+    ) -> Result<(cedar_policy::Request, cedar_policy::Entities), String> {
+        cedar_request(
+            RequestTemplates {
+                action_type: &self.options.action_type,
+                action_id: &self.options.action_id,
+                resource_type: &self.options.resource_type,
+                resource_id: &self.options.resource_id,
+            },
+            &CheckAttributes {
+                http,
+                extensions: &attributes.context_extensions,
+                context_headers: &self.options.context_headers,
+            },
+            principal,
+        )
+    }
+}
+
+/// Returns the path of the request without its query string.
+fn path(http: &HttpRequest) -> String {
+    http.path
+        .split_once('?')
+        .map_or(http.path.as_str(), |(path, _)| path)
+        .to_string()
+}
+
+/// Builds a header replacing any header of the same name.
+fn header(key: &str, value: &str) -> HeaderValueOption {
+    HeaderValueOption {
+        header: Some(HeaderValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+        append_action: OVERWRITE_IF_EXISTS_OR_ADD,
+    }
+}
+
+/// Builds a response denying the request.
+fn denied(
+    http_status: u16,
+    code: i32,
+    message: String,
+    headers: Vec<HeaderValueOption>,
+) -> CheckResponse {
+    CheckResponse {
+        status: Some(Status {
+            code,
+            message: message.clone(),
+        }),
+        http_response: Some(HttpResponse::DeniedResponse(DeniedHttpResponse {
+            status: Some(HttpStatus {
+                code: i32::from(http_status),
+            }),
+            headers,
+            body: message,
+        })),
+    }
+}
+
+/// The `envoy.service.auth.v3.Authorization` gRPC service of an `ExtAuthz`.
+#[derive(Debug, Clone)]
+pub struct AuthorizationServer {
+    /// The adapter answering the `Check` calls
+    inner: Arc<ExtAuthz>,
+}
+
+/// Answers a `Check` call.
+struct CheckSvc(Arc<ExtAuthz>);
+
+impl UnaryService<CheckRequest> for CheckSvc {
+    type Response = CheckResponse;
+    type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+
+    fn call(&mut self, request: tonic::Request<CheckRequest>) -> Self::Future {
+        let inner = self.0.clone();
+        Box::pin(async move {
+            inner
+                .check(request.get_ref())
+                .await
+                .map(tonic::Response::new)
+                .map_err(|e| tonic::Status::internal(e.to_string()))
+        })
+    }
+}
+
+impl<B> Service<http::Request<B>> for AuthorizationServer
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        if request.uri().path() == CHECK_PATH {
+            let inner = self.inner.clone();
+            Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.unary(CheckSvc(inner), request).await)
+            })
+        } else {
+            Box::pin(async move { Ok(tonic::Status::unimplemented("Unknown method").into_http()) })
+        }
+    }
+}
+
+impl NamedService for AuthorizationServer {
+    const NAME: &'static str = "envoy.service.auth.v3.Authorization";
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use serde_json::json;
+    use tokio::net::TcpListener;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::{Channel, Server};
+    use tonic_prost::ProstCodec;
+
+    use crate::public::policy_set_provider::test::{build_policy_store_providers, POLICY_ID};
+    use crate::public::token_verifier::test::{build_verifier, sign};
+
+    use super::proto::check_response::HttpResponse;
+    use super::proto::{AttributeContext, CheckRequest, CheckResponse, HttpRequest, Peer, Request};
+    use super::{
+        ExtAuthz, ExtAuthzOptions, ExtAuthzOptionsBuilder, PrincipalMapping, CHECK_PATH,
+        DECISION_HEADER, DETERMINING_POLICIES_HEADER,
+    };
+
+    fn ext_authz(statement: &str, options: ExtAuthzOptions) -> ExtAuthz {
+        ExtAuthz::new(build_policy_store_providers(statement), options).unwrap()
+    }
+
+    fn check_request(method: &str, path: &str, headers: &[(&str, &str)]) -> CheckRequest {
+        CheckRequest {
+            attributes: Some(AttributeContext {
+                source: Some(Peer {
+                    principal: "spiffe://cluster/ns/default/sa/frontend".to_string(),
+                    ..Peer::default()
+                }),
+                request: Some(Request {
+                    http: Some(HttpRequest {
+                        method: method.to_string(),
+                        path: path.to_string(),
+                        host: "photos.example.com".to_string(),
+                        headers: headers
+                            .iter()
+                            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+                            .collect(),
+                        ..HttpRequest::default()
+                    }),
+                }),
+                context_extensions: HashMap::from([("album".to_string(), "vacation".to_string())]),
+                ..AttributeContext::default()
+            }),
+        }
+    }
+
+    fn headers(response: &CheckResponse) -> HashMap<String, String> {
+        let headers = match response.http_response.as_ref().unwrap() {
+            HttpResponse::OkResponse(ok) => &ok.headers,
+            HttpResponse::DeniedResponse(denied) => &denied.headers,
+        };
+        headers
+            .iter()
+            .map(|option| {
+                let header = option.header.as_ref().unwrap();
+                (header.key.clone(), header.value.clone())
+            })
+            .collect()
+    }
+
+    #[allow(clippy::literal_string_with_formatting_args)]
+    fn header_options() -> ExtAuthzOptions {
+        ExtAuthzOptionsBuilder::default()
+            .principal(PrincipalMapping::Header {
+                header: "x-user-id".to_string(),
+                entity_type: "User".to_string(),
+            })
+            .action_id("{method} {extension:album}")
+            .resource_type("Photo")
+            .context_headers(vec!["x-tenant".to_string()])
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn check_maps_the_http_attributes_to_a_cedar_request() {
+        let ext_authz = ext_authz(
+            r#"permit(
+                principal == User::"alice",
+                action == Action::"GET vacation",
+                resource == Photo::"/photos/beach.jpg"
+            ) when { context.headers["x-tenant"] == "acme" && context.host == "photos.example.com" };"#,
+            header_options(),
+        );
+
+        let allowed = ext_authz
+            .check(&check_request(
+                "GET",
+                "/photos/beach.jpg?size=large",
+                &[("x-user-id", "alice"), ("x-tenant", "acme")],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(allowed.status.as_ref().unwrap().code, 0);
+        assert!(matches!(
+            allowed.http_response,
+            Some(HttpResponse::OkResponse(_))
+        ));
+        assert_eq!(headers(&allowed)[DECISION_HEADER], "ALLOW");
+        assert_eq!(headers(&allowed)[DETERMINING_POLICIES_HEADER], POLICY_ID);
+
+        let denied = ext_authz
+            .check(&check_request(
+                "DELETE",
+                "/photos/beach.jpg",
+                &[("x-user-id", "alice"), ("x-tenant", "acme")],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(denied.status.as_ref().unwrap().code, 7);
+        let Some(HttpResponse::DeniedResponse(response)) = &denied.http_response else {
+            panic!("expected a denied response");
+        };
+        assert_eq!(response.status.as_ref().unwrap().code, 403);
+        assert_eq!(headers(&denied)[DECISION_HEADER], "DENY");
+
+        let unauthenticated = ext_authz
+            .check(&check_request("GET", "/photos/beach.jpg", &[]))
+            .await
+            .unwrap();
+        assert_eq!(unauthenticated.status.as_ref().unwrap().code, 16);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn check_reads_the_principal_from_a_verified_token_or_the_peer() {
+        let token = sign(
+            "access",
+            json!({
+                "sub": "alice",
+                "client_id": "client-1",
+                "scope": "photos/read",
+                "method": "GET",
+                "headers": { "x-tenant": "acme" },
+            }),
+        );
+        let ext_authz = ext_authz(
+            r#"permit(
+                principal == MyApp::User::"us-east-1_example|alice",
+                action,
+                resource
+            ) when { context.scope == "photos/read" && context.method == "GET" };"#,
+            ExtAuthzOptionsBuilder::default()
+                .principal(PrincipalMapping::AccessToken)
+                .resource_type("Photo")
+                .build()
+                .unwrap(),
+        )
+        .with_token_verifier(Arc::new(build_verifier()));
+
+        let bearer = format!("Bearer {token}");
+        let response = ext_authz
+            .check(&check_request(
+                "GET",
+                "/photos/beach.jpg",
+                &[("authorization", &bearer)],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(headers(&response)[DECISION_HEADER], "ALLOW");
+
+        let response = ext_authz
+            .check(&check_request(
+                "DELETE",
+                "/photos/beach.jpg",
+                &[("authorization", &bearer)],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(headers(&response)[DECISION_HEADER], "DENY");
+
+        let response = ext_authz
+            .check(&check_request(
+                "GET",
+                "/photos/beach.jpg",
+                &[("authorization", "Bearer not-a-token")],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status.as_ref().unwrap().code, 16);
+
+        let ext_authz = ext_authz_for_peer();
+        let response = ext_authz
+            .check(&check_request("GET", "/photos/beach.jpg", &[]))
+            .await
+            .unwrap();
+        assert_eq!(headers(&response)[DECISION_HEADER], "ALLOW");
+    }
+
+    fn ext_authz_for_peer() -> ExtAuthz {
+        ext_authz(
+            r#"permit(
+                principal == Service::"spiffe://cluster/ns/default/sa/frontend",
+                action == Action::"GET",
+                resource
+            );"#,
+            ExtAuthzOptionsBuilder::default()
+                .principal(PrincipalMapping::Peer {
+                    entity_type: "Service".to_string(),
+                })
+                .resource_type("Photo")
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn check_is_served_over_grpc() {
+        let server = ext_authz_for_peer().into_server();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(server)
+                .serve_with_incoming(TcpIncoming::from(listener)),
+        );
+
+        let channel = Channel::from_shared(format!("http://{addr}"))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut grpc = tonic::client::Grpc::new(channel);
+        grpc.ready().await.unwrap();
+        let response: tonic::Response<CheckResponse> = grpc
+            .unary(
+                tonic::Request::new(check_request("GET", "/photos/beach.jpg", &[])),
+                PathAndQuery::from_static(CHECK_PATH),
+                ProstCodec::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(headers(response.get_ref())[DECISION_HEADER], "ALLOW");
+    }
+}
@@ -0,0 +1,170 @@
+//! The messages of the Envoy `envoy.service.auth.v3.Authorization` service read and written by
+//! the adapter.
+//!
+//! Only the fields used by the adapter are declared, with the tags of the Envoy protos, so that
+//! the messages stay wire compatible with Envoy. Unknown fields sent by Envoy are skipped.
+use std::collections::HashMap;
+
+/// `envoy.service.auth.v3.CheckRequest`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct CheckRequest {
+    /// The attributes of the request to authorize
+    #[prost(message, optional, tag = "1")]
+    pub attributes: Option<AttributeContext>,
+}
+
+/// `envoy.service.auth.v3.AttributeContext`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct AttributeContext {
+    /// The downstream peer of the request
+    #[prost(message, optional, tag = "1")]
+    pub source: Option<Peer>,
+    /// The peer the request was received by
+    #[prost(message, optional, tag = "2")]
+    pub destination: Option<Peer>,
+    /// The request to authorize
+    #[prost(message, optional, tag = "4")]
+    pub request: Option<Request>,
+    /// The `context_extensions` of the `ext_authz` configuration of the route
+    #[prost(map = "string, string", tag = "10")]
+    pub context_extensions: HashMap<String, String>,
+}
+
+/// `envoy.service.auth.v3.AttributeContext.Peer`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct Peer {
+    /// The canonical service name of the peer
+    #[prost(string, tag = "2")]
+    pub service: String,
+    /// The labels of the peer
+    #[prost(map = "string, string", tag = "3")]
+    pub labels: HashMap<String, String>,
+    /// The authenticated identity of the peer, such as the URI SAN of its mTLS certificate
+    #[prost(string, tag = "4")]
+    pub principal: String,
+}
+
+/// `envoy.service.auth.v3.AttributeContext.Request`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct Request {
+    /// The HTTP attributes of the request
+    #[prost(message, optional, tag = "2")]
+    pub http: Option<HttpRequest>,
+}
+
+/// `envoy.service.auth.v3.AttributeContext.HttpRequest`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct HttpRequest {
+    /// The request id
+    #[prost(string, tag = "1")]
+    pub id: String,
+    /// The HTTP method
+    #[prost(string, tag = "2")]
+    pub method: String,
+    /// The headers of the request, keyed by lowercase name
+    #[prost(map = "string, string", tag = "3")]
+    pub headers: HashMap<String, String>,
+    /// The path of the request, including the query string
+    #[prost(string, tag = "4")]
+    pub path: String,
+    /// The host of the request
+    #[prost(string, tag = "5")]
+    pub host: String,
+    /// The scheme of the request
+    #[prost(string, tag = "6")]
+    pub scheme: String,
+    /// The HTTP protocol version
+    #[prost(string, tag = "10")]
+    pub protocol: String,
+}
+
+/// `envoy.service.auth.v3.CheckResponse`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct CheckResponse {
+    /// `OK` allows the request, any other code denies it
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<Status>,
+    /// The response Envoy applies to the request
+    #[prost(oneof = "check_response::HttpResponse", tags = "2, 3")]
+    pub http_response: Option<check_response::HttpResponse>,
+}
+
+/// Nested types of `CheckResponse`.
+pub mod check_response {
+    /// The response Envoy applies to the request.
+    #[derive(Clone, PartialEq, Eq, prost::Oneof)]
+    pub enum HttpResponse {
+        /// Sent to the downstream client when the request is denied
+        #[prost(message, tag = "2")]
+        DeniedResponse(super::DeniedHttpResponse),
+        /// Applied to the request forwarded upstream when the request is allowed
+        #[prost(message, tag = "3")]
+        OkResponse(super::OkHttpResponse),
+    }
+}
+
+/// `envoy.service.auth.v3.DeniedHttpResponse`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct DeniedHttpResponse {
+    /// The HTTP status returned to the downstream client
+    #[prost(message, optional, tag = "1")]
+    pub status: Option<HttpStatus>,
+    /// The headers returned to the downstream client
+    #[prost(message, repeated, tag = "2")]
+    pub headers: Vec<HeaderValueOption>,
+    /// The body returned to the downstream client
+    #[prost(string, tag = "3")]
+    pub body: String,
+}
+
+/// `envoy.service.auth.v3.OkHttpResponse`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct OkHttpResponse {
+    /// The headers added to the request forwarded upstream
+    #[prost(message, repeated, tag = "2")]
+    pub headers: Vec<HeaderValueOption>,
+    /// The headers removed from the request forwarded upstream
+    #[prost(string, repeated, tag = "5")]
+    pub headers_to_remove: Vec<String>,
+}
+
+/// `envoy.type.v3.HttpStatus`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct HttpStatus {
+    /// The HTTP status code
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+}
+
+/// `envoy.config.core.v3.HeaderValueOption`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct HeaderValueOption {
+    /// The header to add
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<HeaderValue>,
+    /// How the header is combined with an existing header of the same name
+    #[prost(int32, tag = "3")]
+    pub append_action: i32,
+}
+
+/// `envoy.config.core.v3.HeaderValue`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct HeaderValue {
+    /// The header name
+    #[prost(string, tag = "1")]
+    pub key: String,
+    /// The header value
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
+
+/// `google.rpc.Status`
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct Status {
+    /// The gRPC status code
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    /// A description of the status
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
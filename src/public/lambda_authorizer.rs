@@ -0,0 +1,918 @@
+//! An Amazon API Gateway Lambda authorizer evaluating API requests against the providers of a
+//! policy store, without a call to Amazon Verified Permissions.
+//!
+//! `LambdaAuthorizer::handle` accepts the event JSON of REST API `TOKEN` and `REQUEST` authorizers
+//! and of HTTP API authorizers with the 1.0 or 2.0 payload format, and maps it to a Cedar request
+//! with `LambdaAuthorizerOptions`:
+//!
+//! * the principal comes from the identity source token, verified by a `TokenVerifier` with the
+//!   `token` feature, or from a header,
+//! * the action and resource ids are templates over `{method}`, `{path}`, `{route}`, `{stage}`,
+//!   `{header:<name>}`, `{query:<name>}`, `{path_parameter:<name>}` and
+//!   `{stage_variable:<name>}`,
+//! * the context holds the access token claims when the principal comes from a token, and the
+//!   `method`, `path`, `route` and `stage` of the request and the configured `headers`, which
+//!   replace claims of the same name.
+//!
+//! The decision is returned as an IAM policy document for the method or route ARN, or in the
+//! simple response format for HTTP APIs with `simple_responses`. Requests without a principal fail
+//! with `LambdaAuthorizerError::Unauthorized`, whose message makes API Gateway answer
+//! `401 Unauthorized`.
+//!
+//! The providers are kept across invocations and refreshed before an evaluation once their
+//! refresh interval elapsed, since background tasks do not run while the Lambda execution
+//! environment is frozen.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use aws_sdk_verifiedpermissions::types::AttributeValue;
+use cedar_local_agent::public::simple::{Authorizer, AuthorizerConfigBuilder, AuthorizerError};
+use cedar_policy::Decision;
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tracing::{debug, error, instrument};
+
+use crate::private::translator::http_request::{
+    cedar_request, header_record, HttpAttributes, Principal, RequestTemplates,
+};
+
+use super::agent_config::PolicyStoreProviders;
+use super::policy_set_provider::PolicySetProvider;
+#[cfg(feature = "token")]
+use super::token_verifier::TokenVerifier;
+
+/// The version of the IAM policy documents.
+const POLICY_VERSION: &str = "2012-10-17";
+/// The IAM action allowing the invocation of an API method.
+const INVOKE_ACTION: &str = "execute-api:Invoke";
+
+/// `LambdaAuthorizerError` can occur when building the authorizer or authorizing an event.
+#[derive(Error, Debug)]
+pub enum LambdaAuthorizerError {
+    /// The authorizer cannot be built
+    #[error("Invalid Lambda authorizer configuration: {0}")]
+    Configuration(String),
+    /// The event is not an API Gateway authorizer event
+    #[error("Invalid authorizer event: {0}")]
+    InvalidEvent(#[from] serde_json::Error),
+    /// The request has no valid principal, API Gateway answers `401 Unauthorized` when the Lambda
+    /// function fails with this exact message
+    #[error("Unauthorized")]
+    Unauthorized,
+    /// The providers failed to evaluate the request
+    #[error("The authorizer failed to evaluate the request: {0}")]
+    Authorizer(#[from] AuthorizerError),
+}
+
+/// Where the principal of a request is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrincipalSource {
+    /// The identity source token is verified as an access token by the `TokenVerifier` of the
+    /// authorizer, which also adds its claims to the context
+    #[cfg(feature = "token")]
+    AccessToken,
+    /// The identity source token is verified as an identity token by the `TokenVerifier` of the
+    /// authorizer
+    #[cfg(feature = "token")]
+    IdentityToken,
+    /// The principal id is read from a header, for APIs authenticated upstream
+    Header {
+        /// The name of the header
+        header: String,
+        /// The entity type of the principal
+        entity_type: String,
+    },
+}
+
+/// How API Gateway authorizer events map to Cedar requests.
+///
+/// ```
+/// use avp_local_agent::public::lambda_authorizer::{
+///     LambdaAuthorizerOptionsBuilder, PrincipalSource,
+/// };
+///
+/// let options = LambdaAuthorizerOptionsBuilder::default()
+///     .principal(PrincipalSource::Header {
+///         header: "x-user-id".to_string(),
+///         entity_type: "MyApp::User".to_string(),
+///     })
+///     .action_type("MyApp::Action")
+///     .action_id("{method} {route}")
+///     .resource_type("MyApp::Photo")
+///     .resource_id("{path_parameter:photoId}")
+///     .simple_responses(true)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned")]
+pub struct LambdaAuthorizerOptions {
+    /// Where the principal is read from
+    pub principal: PrincipalSource,
+    /// The header holding the identity source token of `REQUEST` events, HTTP API events use
+    /// their first identity source
+    #[builder(default = "\"authorization\".to_string()", setter(into))]
+    pub token_header: String,
+    /// The entity type of the action
+    #[builder(default = "\"Action\".to_string()", setter(into))]
+    pub action_type: String,
+    /// The template of the action id
+    #[builder(default = "\"{method}\".to_string()", setter(into))]
+    pub action_id: String,
+    /// The entity type of the resource
+    #[builder(setter(into))]
+    pub resource_type: String,
+    /// The template of the resource id
+    #[builder(default = "\"{path}\".to_string()", setter(into))]
+    pub resource_id: String,
+    /// The names of the headers added to the `headers` record of the context
+    #[builder(default)]
+    pub context_headers: Vec<String>,
+    /// Answers HTTP API 2.0 events in the simple response format instead of an IAM policy
+    #[builder(default)]
+    pub simple_responses: bool,
+}
+
+/// The event of a REST API `TOKEN` authorizer.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenEvent {
+    /// The identity source token
+    pub authorization_token: String,
+    /// The ARN of the invoked method
+    pub method_arn: String,
+}
+
+/// The event of a REST API `REQUEST` authorizer, also sent to HTTP API authorizers with the 1.0
+/// payload format.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestEvent {
+    /// The ARN of the invoked method
+    pub method_arn: String,
+    /// The route of the method, such as `/photos/{photoId}`
+    pub resource: String,
+    /// The path of the request
+    pub path: String,
+    /// The HTTP method
+    pub http_method: String,
+    /// The headers of the request
+    #[serde(default)]
+    pub headers: Option<HashMap<String, String>>,
+    /// The query string parameters of the request
+    #[serde(default)]
+    pub query_string_parameters: Option<HashMap<String, String>>,
+    /// The path parameters of the request
+    #[serde(default)]
+    pub path_parameters: Option<HashMap<String, String>>,
+    /// The stage variables of the API
+    #[serde(default)]
+    pub stage_variables: Option<HashMap<String, String>>,
+}
+
+/// The HTTP description of an HTTP API 2.0 event.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HttpDescription {
+    /// The HTTP method
+    pub method: String,
+    /// The path of the request
+    pub path: String,
+}
+
+/// The request context of an HTTP API 2.0 event.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HttpRequestContext {
+    /// The HTTP description of the request
+    pub http: HttpDescription,
+    /// The stage of the API
+    pub stage: String,
+}
+
+/// The event of an HTTP API authorizer with the 2.0 payload format.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpApiEvent {
+    /// The payload format version, `2.0`
+    pub version: String,
+    /// The ARN of the invoked route
+    pub route_arn: String,
+    /// The route key, such as `GET /photos/{photoId}`
+    pub route_key: String,
+    /// The identity source values of the authorizer
+    #[serde(default)]
+    pub identity_source: Option<Vec<String>>,
+    /// The headers of the request, with lowercase names
+    #[serde(default)]
+    pub headers: Option<HashMap<String, String>>,
+    /// The query string parameters of the request
+    #[serde(default)]
+    pub query_string_parameters: Option<HashMap<String, String>>,
+    /// The path parameters of the request
+    #[serde(default)]
+    pub path_parameters: Option<HashMap<String, String>>,
+    /// The stage variables of the API
+    #[serde(default)]
+    pub stage_variables: Option<HashMap<String, String>>,
+    /// The context of the request
+    pub request_context: HttpRequestContext,
+}
+
+/// An API Gateway authorizer event, parsed by its payload format `version` and its `type`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "Value")]
+pub enum AuthorizerEvent {
+    /// An HTTP API event with the 2.0 payload format
+    Http(HttpApiEvent),
+    /// A REST API `TOKEN` event
+    Token(TokenEvent),
+    /// A REST API `REQUEST` event or an HTTP API event with the 1.0 payload format
+    Request(RequestEvent),
+}
+
+impl TryFrom<Value> for AuthorizerEvent {
+    type Error = serde_json::Error;
+
+    fn try_from(event: Value) -> Result<Self, Self::Error> {
+        if event.get("version").and_then(Value::as_str) == Some("2.0") {
+            return serde_json::from_value(event).map(Self::Http);
+        }
+        match event.get("type").and_then(Value::as_str) {
+            Some("TOKEN") => serde_json::from_value(event).map(Self::Token),
+            _ => serde_json::from_value(event).map(Self::Request),
+        }
+    }
+}
+
+/// A statement of an IAM policy document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PolicyStatement {
+    /// `execute-api:Invoke`
+    pub action: String,
+    /// `Allow` or `Deny`
+    pub effect: String,
+    /// The ARN of the method or route
+    pub resource: String,
+}
+
+/// An IAM policy document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PolicyDocument {
+    /// `2012-10-17`
+    pub version: String,
+    /// The statements of the document
+    pub statement: Vec<PolicyStatement>,
+}
+
+/// The context passed to the integration, with the `decision` and the comma separated
+/// `determiningPolicies`.
+pub type AuthorizerContext = HashMap<String, String>;
+
+/// An API Gateway authorizer response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum AuthorizerResponse {
+    /// An IAM policy document allowing or denying the method or route
+    #[serde(rename_all = "camelCase")]
+    Policy {
+        /// The principal id of the request
+        principal_id: String,
+        /// The IAM policy document
+        policy_document: PolicyDocument,
+        /// The context passed to the integration
+        context: AuthorizerContext,
+    },
+    /// The simple response format of HTTP APIs
+    #[serde(rename_all = "camelCase")]
+    Simple {
+        /// Whether the request is allowed
+        is_authorized: bool,
+        /// The context passed to the integration
+        context: AuthorizerContext,
+    },
+}
+
+/// The attributes of an authorizer event, independent of its format.
+#[derive(Debug, Default)]
+struct ApiRequest {
+    /// The ARN of the method or route
+    arn: String,
+    /// The HTTP method
+    method: String,
+    /// The path of the request
+    path: String,
+    /// The route of the request, such as `/photos/{photoId}`
+    route: String,
+    /// The stage of the API
+    stage: String,
+    /// The headers of the request, with lowercase names
+    headers: HashMap<String, String>,
+    /// The query string parameters
+    query: HashMap<String, String>,
+    /// The path parameters
+    path_parameters: HashMap<String, String>,
+    /// The stage variables
+    stage_variables: HashMap<String, String>,
+    /// The identity source token, without its `Bearer ` prefix
+    // Only read by the token principal sources.
+    #[cfg_attr(not(feature = "token"), allow(dead_code))]
+    token: Option<String>,
+    /// Whether the simple response format may be used
+    http_api: bool,
+}
+
+/// Returns the stage, method and path of an `execute-api` method ARN.
+fn parse_method_arn(arn: &str) -> (String, String, String) {
+    let resource = arn.splitn(6, ':').nth(5).unwrap_or_default();
+    let mut parts = resource.splitn(4, '/');
+    let _api_id = parts.next();
+    let stage = parts.next().unwrap_or_default().to_string();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = format!("/{}", parts.next().unwrap_or_default());
+    (stage, method, path)
+}
+
+/// Lowercases the header names.
+fn lowercase(headers: Option<&HashMap<String, String>>) -> HashMap<String, String> {
+    headers
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| (name.to_lowercase(), value.clone()))
+        .collect()
+}
+
+/// Removes the `Bearer ` prefix of a token.
+fn strip_bearer(token: &str) -> String {
+    token.strip_prefix("Bearer ").unwrap_or(token).to_string()
+}
+
+impl ApiRequest {
+    /// Normalizes an authorizer event.
+    fn new(event: &AuthorizerEvent, token_header: &str) -> Self {
+        match event {
+            AuthorizerEvent::Token(event) => {
+                let (stage, method, path) = parse_method_arn(&event.method_arn);
+                Self {
+                    arn: event.method_arn.clone(),
+                    method,
+                    route: path.clone(),
+                    path,
+                    stage,
+                    token: Some(strip_bearer(&event.authorization_token)),
+                    ..Self::default()
+                }
+            }
+            AuthorizerEvent::Request(event) => {
+                let (stage, _, _) = parse_method_arn(&event.method_arn);
+                let headers = lowercase(event.headers.as_ref());
+                Self {
+                    arn: event.method_arn.clone(),
+                    method: event.http_method.clone(),
+                    path: event.path.clone(),
+                    route: event.resource.clone(),
+                    stage,
+                    token: headers
+                        .get(&token_header.to_lowercase())
+                        .map(|t| strip_bearer(t)),
+                    headers,
+                    query: event.query_string_parameters.clone().unwrap_or_default(),
+                    path_parameters: event.path_parameters.clone().unwrap_or_default(),
+                    stage_variables: event.stage_variables.clone().unwrap_or_default(),
+                    http_api: false,
+                }
+            }
+            AuthorizerEvent::Http(event) => {
+                let headers = lowercase(event.headers.as_ref());
+                let token = event
+                    .identity_source
+                    .as_ref()
+                    .and_then(|sources| sources.first())
+                    .or_else(|| headers.get(&token_header.to_lowercase()))
+                    .map(|t| strip_bearer(t));
+                Self {
+                    arn: event.route_arn.clone(),
+                    method: event.request_context.http.method.clone(),
+                    path: event.request_context.http.path.clone(),
+                    route: event
+                        .route_key
+                        .split_once(' ')
+                        .map_or(event.route_key.as_str(), |(_, route)| route)
+                        .to_string(),
+                    stage: event.request_context.stage.clone(),
+                    headers,
+                    query: event.query_string_parameters.clone().unwrap_or_default(),
+                    path_parameters: event.path_parameters.clone().unwrap_or_default(),
+                    stage_variables: event.stage_variables.clone().unwrap_or_default(),
+                    token,
+                    http_api: true,
+                }
+            }
+        }
+    }
+}
+
+/// The attributes of an authorizer event resolving the placeholders of the id templates.
+struct EventAttributes<'a> {
+    /// The normalized event
+    api_request: &'a ApiRequest,
+    /// The names of the headers added to the context
+    context_headers: &'a [String],
+}
+
+impl HttpAttributes for EventAttributes<'_> {
+    fn placeholder(&self, placeholder: &str) -> Result<Option<String>, String> {
+        let api_request = self.api_request;
+        Ok(match placeholder.split_once(':') {
+            None if placeholder == "method" => Some(&api_request.method),
+            None if placeholder == "path" => Some(&api_request.path),
+            None if placeholder == "route" => Some(&api_request.route),
+            None if placeholder == "stage" => Some(&api_request.stage),
+            Some(("header", name)) => api_request.headers.get(&name.to_lowercase()),
+            Some(("query", name)) => api_request.query.get(name),
+            Some(("path_parameter", name)) => api_request.path_parameters.get(name),
+            Some(("stage_variable", name)) => api_request.stage_variables.get(name),
+            _ => return Err(format!("unknown placeholder {{{placeholder}}}")),
+        }
+        .cloned())
+    }
+
+    fn context(&self) -> HashMap<String, AttributeValue> {
+        let string = |value: &String| AttributeValue::String(value.clone());
+        HashMap::from([
+            ("method".to_string(), string(&self.api_request.method)),
+            ("path".to_string(), string(&self.api_request.path)),
+            ("route".to_string(), string(&self.api_request.route)),
+            ("stage".to_string(), string(&self.api_request.stage)),
+            (
+                "headers".to_string(),
+                header_record(self.context_headers, &self.api_request.headers),
+            ),
+        ])
+    }
+}
+
+/// Authorizes API Gateway authorizer events against the providers of one policy store.
+///
+/// # Examples
+///
+/// ```no_run
+/// use avp_local_agent::public::agent_config::{AgentConfig, AgentProviders};
+/// use avp_local_agent::public::lambda_authorizer::{
+///     LambdaAuthorizer, LambdaAuthorizerOptionsBuilder, PrincipalSource,
+/// };
+/// use serde_json::Value;
+///
+/// # async fn example(event: Value) {
+/// // Built once per execution environment, outside of the handler
+/// let config = AgentConfig::from_file("agent.json").unwrap();
+/// let providers = AgentProviders::from_config(&config).await.unwrap();
+/// let authorizer = LambdaAuthorizer::new(
+///     providers.policy_stores()[0].clone(),
+///     LambdaAuthorizerOptionsBuilder::default()
+///         .principal(PrincipalSource::Header {
+///             header: "x-user-id".to_string(),
+///             entity_type: "User".to_string(),
+///         })
+///         .resource_type("Photo")
+///         .build()
+///         .unwrap(),
+/// )
+/// .unwrap();
+///
+/// // In the handler
+/// let response = authorizer.handle(event).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LambdaAuthorizer {
+    /// The providers of the policy store
+    providers: PolicyStoreProviders,
+    /// Evaluates the mapped requests with the providers
+    authorizer: Authorizer<PolicySetProvider, PolicyStoreProviders>,
+    /// Verifies the identity source tokens of the token principal sources
+    #[cfg(feature = "token")]
+    token_verifier: Option<Arc<TokenVerifier>>,
+    /// How the events are mapped
+    options: LambdaAuthorizerOptions,
+    /// When the providers were last refreshed
+    last_refresh: Mutex<Instant>,
+}
+
+impl LambdaAuthorizer {
+    /// Creates a `LambdaAuthorizer` evaluating events against `providers`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LambdaAuthorizerError::Configuration` if the authorizer cannot be built.
+    pub fn new(
+        providers: PolicyStoreProviders,
+        options: LambdaAuthorizerOptions,
+    ) -> Result<Self, LambdaAuthorizerError> {
+        let authorizer = Authorizer::new(
+            AuthorizerConfigBuilder::default()
+                .policy_set_provider(providers.policy_set_provider.clone())
+                .entity_provider(Arc::new(providers.clone()))
+                .build()
+                .map_err(|e| LambdaAuthorizerError::Configuration(e.to_string()))?,
+        );
+
+        Ok(Self {
+            providers,
+            authorizer,
+            #[cfg(feature = "token")]
+            token_verifier: None,
+            options,
+            last_refresh: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Verifies the identity source tokens of the `AccessToken` and `IdentityToken` principal
+    /// sources with `token_verifier`.
+    #[cfg(feature = "token")]
+    #[must_use]
+    pub fn with_token_verifier(mut self, token_verifier: Arc<TokenVerifier>) -> Self {
+        self.token_verifier = Some(token_verifier);
+        self
+    }
+
+    /// Authorizes the JSON of an authorizer event and returns the JSON of the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LambdaAuthorizerError::InvalidEvent` if the event is not an authorizer event, and
+    /// the errors of `authorize` otherwise.
+    pub async fn handle(&self, event: Value) -> Result<Value, LambdaAuthorizerError> {
+        let event = serde_json::from_value(event)?;
+        let response = self.authorize(&event).await?;
+        Ok(serde_json::to_value(response)?)
+    }
+
+    /// Authorizes an authorizer event. Requests that cannot be mapped are denied.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LambdaAuthorizerError::Unauthorized` if the request has no valid principal, and
+    /// `LambdaAuthorizerError::Authorizer` if a provider fails.
+    #[instrument(skip_all, err(Debug))]
+    pub async fn authorize(
+        &self,
+        event: &AuthorizerEvent,
+    ) -> Result<AuthorizerResponse, LambdaAuthorizerError> {
+        self.refresh_if_stale().await;
+        let api_request = ApiRequest::new(event, &self.options.token_header);
+
+        let principal = match self.principal(&api_request).await {
+            Ok(principal) => principal,
+            Err(message) => {
+                debug!("Unauthenticated request: {message}");
+                return Err(LambdaAuthorizerError::Unauthorized);
+            }
+        };
+        let principal_id = principal.identifier.entity_id().to_string();
+
+        let (decision, policy_ids) = match self.cedar_request(&api_request, principal) {
+            Ok((request, entities)) => {
+                let response = self.authorizer.is_authorized(&request, &entities).await?;
+                let mut policy_ids = response
+                    .diagnostics()
+                    .reason()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                policy_ids.sort();
+                (response.decision(), policy_ids)
+            }
+            Err(message) => {
+                debug!("Unmapped request: {message}");
+                (Decision::Deny, Vec::new())
+            }
+        };
+
+        let allowed = decision == Decision::Allow;
+        let context = HashMap::from([
+            (
+                "decision".to_string(),
+                if allowed { "ALLOW" } else { "DENY" }.to_string(),
+            ),
+            ("determiningPolicies".to_string(), policy_ids.join(",")),
+        ]);
+
+        Ok(if api_request.http_api && self.options.simple_responses {
+            AuthorizerResponse::Simple {
+                is_authorized: allowed,
+                context,
+            }
+        } else {
+            AuthorizerResponse::Policy {
+                principal_id,
+                policy_document: PolicyDocument {
+                    version: POLICY_VERSION.to_string(),
+                    statement: vec![PolicyStatement {
+                        action: INVOKE_ACTION.to_string(),
+                        effect: if allowed { "Allow" } else { "Deny" }.to_string(),
+                        resource: api_request.arn,
+                    }],
+                },
+                context,
+            }
+        })
+    }
+
+    /// Refreshes the providers once their refresh interval elapsed. A failed refresh is logged
+    /// and the cached data keeps being served.
+    async fn refresh_if_stale(&self) {
+        let mut last_refresh = self.last_refresh.lock().await;
+        if last_refresh.elapsed() < self.providers.refresh_interval {
+            return;
+        }
+        if let Err(e) = self.providers.refresh().await {
+            error!("Failed to refresh the policy store providers: {e:?}");
+        }
+        *last_refresh = Instant::now();
+    }
+
+    /// Reads the principal of the request, or describes why it is missing.
+    // Only the token principal sources await.
+    #[cfg_attr(not(feature = "token"), allow(clippy::unused_async))]
+    async fn principal(&self, api_request: &ApiRequest) -> Result<Principal, String> {
+        #[cfg(feature = "token")]
+        let token = || {
+            api_request
+                .token
+                .as_deref()
+                .ok_or_else(|| "the request has no identity source token".to_string())
+        };
+        #[cfg(feature = "token")]
+        let token_verifier = || {
+            self.token_verifier
+                .as_ref()
+                .ok_or_else(|| "no token verifier is configured".to_string())
+        };
+
+        match &self.options.principal {
+            #[cfg(feature = "token")]
+            PrincipalSource::AccessToken => Ok(token_verifier()?
+                .verify(None, Some(token()?))
+                .await
+                .map_err(|e| e.to_string())?
+                .into()),
+            #[cfg(feature = "token")]
+            PrincipalSource::IdentityToken => Ok(token_verifier()?
+                .verify(Some(token()?), None)
+                .await
+                .map_err(|e| e.to_string())?
+                .into()),
+            PrincipalSource::Header {
+                header,
+                entity_type,
+            } => match api_request.headers.get(&header.to_lowercase()) {
+                Some(id) if !id.is_empty() => Principal::new(entity_type, id),
+                _ => Err(format!("the request has no {header} header")),
+            },
+        }
+    }
+
+    /// Maps the request to a Cedar request and the entities of its principal.
+    fn cedar_request(
+        &self,
+        api_request: &ApiRequest,
+        principal: Principal,
+    ) -> Result<(cedar_policy::Request, cedar_policy::Entities), String> {
+        cedar_request(
+            RequestTemplates {
+                action_type: &self.options.action_type,
+                action_id: &self.options.action_id,
+                resource_type: &self.options.resource_type,
+                resource_id: &self.options.resource_id,
+            },
+            &EventAttributes {
+                api_request,
+                context_headers: &self.options.context_headers,
+            },
+            principal,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "token")]
+    use std::sync::Arc;
+
+    use serde_json::{json, Value};
+
+    use crate::public::policy_set_provider::test::build_policy_store_providers;
+    #[cfg(feature = "token")]
+    use crate::public::policy_set_provider::test::POLICY_ID;
+    #[cfg(feature = "token")]
+    use crate::public::token_verifier::test::{build_verifier, sign};
+
+    #[cfg(feature = "token")]
+    use super::LambdaAuthorizerError;
+    use super::{
+        AuthorizerEvent, LambdaAuthorizer, LambdaAuthorizerOptions, LambdaAuthorizerOptionsBuilder,
+        PrincipalSource,
+    };
+
+    const REST_TOKEN_EVENT: &str =
+        include_str!("../../tests/data/api_gateway/rest-token-event.json");
+    const REST_REQUEST_EVENT: &str =
+        include_str!("../../tests/data/api_gateway/rest-request-event.json");
+    const HTTP_REQUEST_EVENT: &str =
+        include_str!("../../tests/data/api_gateway/http-request-event.json");
+    const HTTP_REQUEST_EVENT_V1: &str =
+        include_str!("../../tests/data/api_gateway/http-request-event-v1.json");
+
+    fn authorizer(statement: &str, options: LambdaAuthorizerOptions) -> LambdaAuthorizer {
+        let authorizer =
+            LambdaAuthorizer::new(build_policy_store_providers(statement), options).unwrap();
+        #[cfg(feature = "token")]
+        let authorizer = authorizer.with_token_verifier(Arc::new(build_verifier()));
+        authorizer
+    }
+
+    /// Replaces the `Bearer alice` placeholders of the sample event with a signed access token.
+    #[cfg(feature = "token")]
+    fn with_access_token(event: &str) -> Value {
+        let token = sign(
+            "access",
+            json!({ "sub": "alice", "client_id": "client-1", "scope": "photos/read" }),
+        );
+        serde_json::from_str(&event.replace("Bearer alice", &format!("Bearer {token}"))).unwrap()
+    }
+
+    #[test]
+    fn events_are_parsed_by_format() {
+        let parse = |event: &str| serde_json::from_str::<AuthorizerEvent>(event).unwrap();
+
+        assert!(matches!(parse(REST_TOKEN_EVENT), AuthorizerEvent::Token(_)));
+        assert!(matches!(
+            parse(REST_REQUEST_EVENT),
+            AuthorizerEvent::Request(_)
+        ));
+        assert!(matches!(
+            parse(HTTP_REQUEST_EVENT),
+            AuthorizerEvent::Http(_)
+        ));
+        let AuthorizerEvent::Request(event) = parse(HTTP_REQUEST_EVENT_V1) else {
+            panic!("expected a REQUEST event");
+        };
+        assert_eq!(event.path_parameters.unwrap()["photoId"], "beach.jpg");
+    }
+
+    #[cfg(feature = "token")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn token_event_returns_an_iam_policy() {
+        let authorizer = authorizer(
+            r#"permit(
+                principal == MyApp::User::"us-east-1_example|alice",
+                action == Action::"GET",
+                resource == Photo::"/photos/beach.jpg"
+            ) when { context.scope == "photos/read" && context.stage == "prod" };"#,
+            LambdaAuthorizerOptionsBuilder::default()
+                .principal(PrincipalSource::AccessToken)
+                .resource_type("Photo")
+                .build()
+                .unwrap(),
+        );
+
+        let response = authorizer
+            .handle(with_access_token(REST_TOKEN_EVENT))
+            .await
+            .unwrap();
+        assert_eq!(
+            response,
+            json!({
+                "principalId": "us-east-1_example|alice",
+                "policyDocument": {
+                    "Version": "2012-10-17",
+                    "Statement": [{
+                        "Action": "execute-api:Invoke",
+                        "Effect": "Allow",
+                        "Resource": "arn:aws:execute-api:us-east-1:123456789012:abcdef1234/prod/GET/photos/beach.jpg"
+                    }]
+                },
+                "context": { "decision": "ALLOW", "determiningPolicies": POLICY_ID }
+            })
+        );
+
+        let error = authorizer
+            .handle(serde_json::from_str(REST_TOKEN_EVENT).unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, LambdaAuthorizerError::Unauthorized));
+        assert_eq!(error.to_string(), "Unauthorized");
+    }
+
+    #[cfg(feature = "token")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn token_claims_do_not_replace_request_attributes() {
+        let authorizer = authorizer(
+            r#"permit(
+                principal == MyApp::User::"us-east-1_example|alice",
+                action == Action::"GET",
+                resource == Photo::"/photos/beach.jpg"
+            ) when { context.stage == "test" || context.path == "/photos/public.jpg" };"#,
+            LambdaAuthorizerOptionsBuilder::default()
+                .principal(PrincipalSource::AccessToken)
+                .resource_type("Photo")
+                .build()
+                .unwrap(),
+        );
+
+        let token = sign(
+            "access",
+            json!({
+                "sub": "alice",
+                "client_id": "client-1",
+                "stage": "test",
+                "path": "/photos/public.jpg"
+            }),
+        );
+        let event = serde_json::from_str(
+            &REST_TOKEN_EVENT.replace("Bearer alice", &format!("Bearer {token}")),
+        )
+        .unwrap();
+        let response = authorizer.handle(event).await.unwrap();
+        assert_eq!(response["policyDocument"]["Statement"][0]["Effect"], "Deny");
+        assert_eq!(response["context"]["decision"], "DENY");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    #[allow(clippy::literal_string_with_formatting_args)]
+    async fn request_event_maps_headers_and_path_parameters() {
+        let authorizer = authorizer(
+            r#"permit(
+                principal == User::"alice",
+                action == Action::"DELETE /photos/{photoId}",
+                resource == Photo::"beach.jpg"
+            ) when { context.headers["X-Tenant"] == "acme" };"#,
+            LambdaAuthorizerOptionsBuilder::default()
+                .principal(PrincipalSource::Header {
+                    header: "X-User-Id".to_string(),
+                    entity_type: "User".to_string(),
+                })
+                .action_id("{method} {route}")
+                .resource_type("Photo")
+                .resource_id("{path_parameter:photoId}")
+                .context_headers(vec!["X-Tenant".to_string()])
+                .build()
+                .unwrap(),
+        );
+
+        let response = authorizer
+            .handle(serde_json::from_str(REST_REQUEST_EVENT).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response["principalId"], "alice");
+        assert_eq!(
+            response["policyDocument"]["Statement"][0]["Effect"],
+            "Allow"
+        );
+
+        let mut event: Value = serde_json::from_str(REST_REQUEST_EVENT).unwrap();
+        event["headers"]["X-Tenant"] = json!("other");
+        let response = authorizer.handle(event).await.unwrap();
+        assert_eq!(response["policyDocument"]["Statement"][0]["Effect"], "Deny");
+        assert_eq!(response["context"]["decision"], "DENY");
+    }
+
+    #[cfg(feature = "token")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn http_event_returns_a_simple_response() {
+        let authorizer = authorizer(
+            r#"permit(
+                principal == MyApp::User::"us-east-1_example|alice",
+                action == Action::"GET",
+                resource == Photo::"/photos/beach.jpg"
+            );"#,
+            LambdaAuthorizerOptionsBuilder::default()
+                .principal(PrincipalSource::AccessToken)
+                .resource_type("Photo")
+                .simple_responses(true)
+                .build()
+                .unwrap(),
+        );
+
+        let response = authorizer
+            .handle(with_access_token(HTTP_REQUEST_EVENT))
+            .await
+            .unwrap();
+        assert_eq!(
+            response,
+            json!({
+                "isAuthorized": true,
+                "context": { "decision": "ALLOW", "determiningPolicies": POLICY_ID }
+            })
+        );
+
+        assert!(matches!(
+            authorizer.handle(json!({ "foo": "bar" })).await,
+            Err(LambdaAuthorizerError::InvalidEvent(_))
+        ));
+    }
+}
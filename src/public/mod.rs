@@ -1,5 +1,29 @@
 //! Public providers to be used with an Authorizer
+pub mod agent_config;
+#[cfg(feature = "tower")]
+pub mod authorization_layer;
+pub mod avp_authorizer;
+#[cfg(feature = "cli")]
+pub mod cli;
 pub mod client;
+pub mod composite_entity_provider;
+pub mod deletion_guard;
 pub mod entity_provider;
+#[cfg(feature = "envoy")]
+pub mod ext_authz;
+pub mod lambda_authorizer;
+pub mod policy_metadata;
+pub mod policy_overlay;
 pub mod policy_set_filter;
+pub mod policy_set_history;
 pub mod policy_set_provider;
+pub mod policy_set_slicer;
+pub mod policy_store_validation;
+pub mod principal_policy_cache;
+pub mod schema_provider;
+#[cfg(feature = "sidecar")]
+pub mod sidecar;
+pub mod tenant_router;
+#[cfg(feature = "token")]
+pub mod token_verifier;
+pub mod translator;
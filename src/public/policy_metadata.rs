@@ -0,0 +1,98 @@
+//! Describes the Amazon Verified Permissions policies and templates served by a
+//! `PolicySetProvider`.
+//!
+//! Metadata is built from the `GetPolicy` and `GetPolicyTemplate` outputs cached by the last
+//! refresh, so decisions can be enriched without calling Amazon Verified Permissions. The effect
+//! and action scope come from the served Cedar policy, since the supported SDK does not return
+//! them.
+use aws_sdk_verifiedpermissions::operation::get_policy::GetPolicyOutput;
+use aws_sdk_verifiedpermissions::operation::get_policy_template::GetPolicyTemplateOutput;
+use aws_sdk_verifiedpermissions::primitives::DateTime;
+use aws_sdk_verifiedpermissions::types::{EntityIdentifier, PolicyDefinitionDetail, PolicyType};
+use cedar_policy::{ActionConstraint, Effect};
+
+/// The metadata of an Amazon Verified Permissions policy.
+#[derive(Debug, Clone)]
+pub struct PolicyMetadata {
+    /// The id of the policy
+    pub policy_id: String,
+    /// Whether the policy is static or template-linked
+    pub policy_type: PolicyType,
+    /// The description of a static policy
+    pub description: Option<String>,
+    /// The template a template-linked policy is linked to
+    pub policy_template_id: Option<String>,
+    /// The principal the policy applies to, if it is scoped to one
+    pub principal: Option<EntityIdentifier>,
+    /// The resource the policy applies to, if it is scoped to one
+    pub resource: Option<EntityIdentifier>,
+    /// The effect of the policy, `None` if the policy is not in the served `PolicySet`
+    pub effect: Option<Effect>,
+    /// The actions of the policy, `None` if the policy is not in the served `PolicySet`
+    pub actions: Option<ActionConstraint>,
+    /// When the policy was created
+    pub created_date: DateTime,
+    /// When the policy was last updated
+    pub last_updated_date: DateTime,
+}
+
+impl PolicyMetadata {
+    /// Builds the metadata of a policy from its `GetPolicy` output and its served Cedar policy.
+    pub(crate) fn new(output: &GetPolicyOutput, policy: Option<&cedar_policy::Policy>) -> Self {
+        let (description, policy_template_id) = match output.definition() {
+            Some(PolicyDefinitionDetail::Static(detail)) => (detail.description.clone(), None),
+            Some(PolicyDefinitionDetail::TemplateLinked(detail)) => {
+                (None, Some(detail.policy_template_id.clone()))
+            }
+            _ => (None, None),
+        };
+
+        Self {
+            policy_id: output.policy_id.clone(),
+            policy_type: output.policy_type.clone(),
+            description,
+            policy_template_id,
+            principal: output.principal.clone(),
+            resource: output.resource.clone(),
+            effect: policy.map(cedar_policy::Policy::effect),
+            actions: policy.map(cedar_policy::Policy::action_constraint),
+            created_date: output.created_date,
+            last_updated_date: output.last_updated_date,
+        }
+    }
+}
+
+/// The metadata of an Amazon Verified Permissions policy template.
+#[derive(Debug, Clone)]
+pub struct TemplateMetadata {
+    /// The id of the template
+    pub policy_template_id: String,
+    /// The description of the template
+    pub description: Option<String>,
+    /// The effect of the template, `None` if the template is not in the served `PolicySet`
+    pub effect: Option<Effect>,
+    /// The actions of the template, `None` if the template is not in the served `PolicySet`
+    pub actions: Option<ActionConstraint>,
+    /// When the template was created
+    pub created_date: DateTime,
+    /// When the template was last updated
+    pub last_updated_date: DateTime,
+}
+
+impl TemplateMetadata {
+    /// Builds the metadata of a template from its `GetPolicyTemplate` output and its served Cedar
+    /// template.
+    pub(crate) fn new(
+        output: &GetPolicyTemplateOutput,
+        template: Option<&cedar_policy::Template>,
+    ) -> Self {
+        Self {
+            policy_template_id: output.policy_template_id.clone(),
+            description: output.description.clone(),
+            effect: template.map(cedar_policy::Template::effect),
+            actions: template.map(cedar_policy::Template::action_constraint),
+            created_date: output.created_date,
+            last_updated_date: output.last_updated_date,
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Local Cedar static policies merged on top of the Amazon Verified Permissions policies served
+//! by a `PolicySetProvider`.
+//!
+//! An overlay provides a break-glass mechanism that does not depend on editing the policy store,
+//! such as a local `forbid` for a compromised principal. Overlay policies are added to every
+//! published `PolicySet` under the `OVERLAY_POLICY_ID_PREFIX`, survive refreshes and can be
+//! replaced at runtime.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use cedar_policy::{ParseErrors, Policy, PolicyId, PolicySet};
+use thiserror::Error;
+use tokio::sync::watch::Receiver;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, instrument};
+
+use super::policy_set_provider::{PolicySetError, PolicySetProvider};
+
+/// The reserved prefix of the ids of overlay policies in the served `PolicySet`.
+pub const OVERLAY_POLICY_ID_PREFIX: &str = "overlay::";
+
+/// `OverlayError` occurs when building a `PolicyOverlay`.
+#[derive(Error, Debug)]
+pub enum OverlayError {
+    /// The overlay policies failed to parse
+    #[error("The overlay policies failed to be parsed: {0}")]
+    Parse(#[source] Box<ParseErrors>),
+    /// The overlay file could not be read
+    #[error("The overlay file could not be read: {0}")]
+    Io(#[from] std::io::Error),
+    /// The overlay contains a template, only static policies are supported
+    #[error("Overlay policies must be static, found template: {0}")]
+    Template(String),
+    /// Two overlay policies have the same id
+    #[error(
+        "The overlay{} defines the policy id {id} more than once",
+        path.as_ref().map(|path| format!(" {}", path.display())).unwrap_or_default()
+    )]
+    DuplicateId {
+        /// The duplicated id, without the `OVERLAY_POLICY_ID_PREFIX`
+        id: String,
+        /// The overlay file, if the overlay was read from a file
+        path: Option<PathBuf>,
+    },
+}
+
+impl From<ParseErrors> for OverlayError {
+    fn from(value: ParseErrors) -> Self {
+        Self::Parse(Box::new(value))
+    }
+}
+
+/// A set of local Cedar static policies. Each policy is identified by its `@id` annotation, or
+/// its position in the source when it has none, prefixed with `OVERLAY_POLICY_ID_PREFIX`.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+///
+/// use avp_local_agent::public::policy_overlay::PolicyOverlay;
+///
+/// let overlay = PolicyOverlay::from_str(
+///     r#"@id("block-mallory") forbid(principal == User::"mallory", action, resource);"#,
+/// )
+/// .unwrap();
+/// assert_eq!(overlay.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PolicyOverlay {
+    /// Overlay policies with their prefixed ids
+    policies: Vec<Policy>,
+}
+
+impl PolicyOverlay {
+    /// Creates an overlay without policies.
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Reads and parses an overlay from a Cedar policy file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `OverlayError` if the file cannot be read, fails to parse, contains a template
+    /// or defines an id more than once.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, OverlayError> {
+        let path = path.as_ref();
+        Self::from_str(&std::fs::read_to_string(path)?).map_err(|error| match error {
+            OverlayError::DuplicateId { id, .. } => OverlayError::DuplicateId {
+                id,
+                path: Some(path.to_path_buf()),
+            },
+            error => error,
+        })
+    }
+
+    /// The number of policies in the overlay.
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    /// Returns true if the overlay has no policies.
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Returns a copy of `policy_set` with the overlay policies added.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `PolicySetError` if an overlay policy id is already used in the `policy_set`.
+    pub fn merge(&self, policy_set: &PolicySet) -> Result<PolicySet, PolicySetError> {
+        let mut merged = policy_set.clone();
+        for policy in &self.policies {
+            merged
+                .add(policy.clone())
+                .map_err(|_| PolicySetError::StaticPolicy(policy.id().to_string()))?;
+        }
+        Ok(merged)
+    }
+}
+
+/// Parses an overlay from Cedar policy source, failing if the source contains a template or
+/// defines an id more than once.
+impl FromStr for PolicyOverlay {
+    type Err = OverlayError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let policy_set = PolicySet::from_str(src)?;
+        if let Some(template) = policy_set.templates().next() {
+            return Err(OverlayError::Template(template.id().to_string()));
+        }
+
+        let mut ids = HashSet::new();
+        let mut policies = Vec::new();
+        for policy in policy_set.policies() {
+            let id = policy
+                .annotation("id")
+                .map_or_else(|| policy.id().to_string(), ToString::to_string);
+            if !ids.insert(id.clone()) {
+                return Err(OverlayError::DuplicateId { id, path: None });
+            }
+            policies.push(policy.new_id(PolicyId::new(format!("{OVERLAY_POLICY_ID_PREFIX}{id}"))));
+        }
+        Ok(Self { policies })
+    }
+}
+
+/// Starts a background task that replaces the overlay of the `provider` each time a new
+/// `PolicyOverlay` is sent on the `receiver`. The task ends when the sender is dropped.
+#[instrument(skip_all)]
+pub fn overlay_update_task(
+    provider: Arc<PolicySetProvider>,
+    mut receiver: Receiver<PolicyOverlay>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while receiver.changed().await.is_ok() {
+            let overlay = receiver.borrow_and_update().clone();
+            match provider.set_overlay(overlay).await {
+                Ok(()) => debug!("Successfully replaced the policy overlay"),
+                Err(error) => error!("Failed to replace the policy overlay: error={error:?}"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use cedar_policy::{PolicyId, PolicySet};
+
+    use crate::public::policy_overlay::{OverlayError, PolicyOverlay};
+
+    const OVERLAY: &str = r#"
+        @id("block-mallory")
+        forbid(principal == User::"mallory", action, resource);
+        permit(principal == User::"admin", action, resource);
+    "#;
+
+    #[test]
+    fn from_str_prefixes_policy_ids() {
+        let overlay = PolicyOverlay::from_str(OVERLAY).unwrap();
+        let merged = overlay.merge(&PolicySet::new()).unwrap();
+
+        assert_eq!(overlay.len(), 2);
+        assert!(merged
+            .policy(&PolicyId::new("overlay::block-mallory"))
+            .is_some());
+        assert!(merged.policy(&PolicyId::new("overlay::policy1")).is_some());
+    }
+
+    #[test]
+    fn from_str_rejects_templates() {
+        let error = PolicyOverlay::from_str("permit(principal == ?principal, action, resource);")
+            .unwrap_err();
+        assert!(matches!(error, OverlayError::Template(..)));
+    }
+
+    #[test]
+    fn from_str_rejects_invalid_policies() {
+        let error = PolicyOverlay::from_str("forbid(principal,").unwrap_err();
+        assert!(matches!(error, OverlayError::Parse(..)));
+    }
+
+    #[test]
+    fn from_file_rejects_duplicate_ids() {
+        let path = std::env::temp_dir().join(format!(
+            "avp-local-agent-overlay-{}.cedar",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"
+                @id("block") forbid(principal == User::"mallory", action, resource);
+                @id("block") forbid(principal == User::"eve", action, resource);
+            "#,
+        )
+        .unwrap();
+        let error = PolicyOverlay::from_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            &error,
+            OverlayError::DuplicateId { id, path: Some(error_path) }
+                if id == "block" && error_path == &path
+        ));
+        assert!(error.to_string().contains(&path.display().to_string()));
+    }
+
+    #[test]
+    fn from_file_that_does_not_exist_errors() {
+        let error = PolicyOverlay::from_file("does/not/exist.cedar").unwrap_err();
+        assert!(matches!(error, OverlayError::Io(..)));
+    }
+
+    #[test]
+    fn merge_keeps_the_base_policies() {
+        let base = PolicySet::from_str("permit(principal, action, resource);").unwrap();
+        let merged = PolicyOverlay::from_str(OVERLAY)
+            .unwrap()
+            .merge(&base)
+            .unwrap();
+
+        assert_eq!(merged.policies().count(), 3);
+        assert_eq!(base.policies().count(), 1);
+    }
+}
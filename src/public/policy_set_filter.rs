@@ -14,6 +14,38 @@ pub enum PolicySetFilter<'a> {
     Value(Value),
 }
 
+/// An owned `PolicySetFilter`, kept to build providers after it was configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedPolicySetFilter {
+    /// Cli shorthand representation
+    Cli(String),
+    /// JSON representation
+    Json(String),
+    /// `serde_json::Value`
+    Value(Value),
+}
+
+impl OwnedPolicySetFilter {
+    /// Returns the `PolicySetFilter` borrowing this filter.
+    pub fn to_policy_set_filter(&self) -> PolicySetFilter<'_> {
+        match self {
+            Self::Cli(cli) => PolicySetFilter::Cli(cli),
+            Self::Json(json) => PolicySetFilter::Json(json),
+            Self::Value(value) => PolicySetFilter::Value(value.clone()),
+        }
+    }
+}
+
+impl From<PolicySetFilter<'_>> for OwnedPolicySetFilter {
+    fn from(filter: PolicySetFilter<'_>) -> Self {
+        match filter {
+            PolicySetFilter::Cli(cli) => Self::Cli(cli.to_string()),
+            PolicySetFilter::Json(json) => Self::Json(json.to_string()),
+            PolicySetFilter::Value(value) => Self::Value(value),
+        }
+    }
+}
+
 impl TryInto<PolicyStoreFilter> for PolicySetFilter<'_> {
     type Error = ProviderError;
 
@@ -55,6 +87,12 @@ mod tests {
         assert_eq!(p.to_string(), "policyTemplateId=12345");
     }
     #[test]
+    fn test_owned() {
+        let owned = OwnedPolicySetFilter::from(PolicySetFilter::Cli("policyTemplateId=12345"));
+        let p: PolicyStoreFilter = owned.to_policy_set_filter().try_into().unwrap();
+        assert_eq!(p.to_string(), "policyTemplateId=12345");
+    }
+    #[test]
     fn test_cli_syntax_error() {
         let p: Result<PolicyStoreFilter, _> = PolicySetFilter::Cli("policyTemplateId=").try_into();
         let e = p.unwrap_err();
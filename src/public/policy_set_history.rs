@@ -0,0 +1,237 @@
+//! Retains the `PolicySet`s published by a `PolicySetProvider` so that it can be rolled back to
+//! an earlier version.
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use cedar_policy::PolicySet;
+use chrono::{DateTime, Utc};
+
+/// A `PolicySet` built by the `PolicySetProvider` along with its generation metadata.
+#[derive(Debug, Clone)]
+pub struct PolicySetVersion {
+    /// Monotonically increasing generation, starting at 1 for the initial load
+    generation: u64,
+    /// The time the version was built from Amazon Verified Permissions data
+    created_at: DateTime<Utc>,
+    /// The policy set of this version
+    policy_set: Arc<PolicySet>,
+}
+
+impl PolicySetVersion {
+    /// The generation of this version, generations start at 1 and increase each time a refresh
+    /// builds a `PolicySet` that differs from the previous one.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The time this version was built.
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// The `PolicySet` of this version.
+    pub fn policy_set(&self) -> Arc<PolicySet> {
+        self.policy_set.clone()
+    }
+}
+
+/// A bounded history of `PolicySetVersion`s, the version being served is never evicted.
+#[derive(Debug)]
+pub(crate) struct PolicySetHistory {
+    /// The maximum number of retained versions
+    capacity: usize,
+    /// Retained versions ordered from oldest to newest
+    versions: VecDeque<PolicySetVersion>,
+    /// The generation of the version currently served
+    served_generation: u64,
+    /// A frozen history does not serve newly recorded versions
+    frozen: bool,
+}
+
+impl PolicySetHistory {
+    /// Creates a history serving `policy_set` as generation 1.
+    pub(crate) fn new(capacity: usize, policy_set: Arc<PolicySet>) -> Self {
+        let mut versions = VecDeque::new();
+        versions.push_back(PolicySetVersion {
+            generation: 1,
+            created_at: Utc::now(),
+            policy_set,
+        });
+        Self {
+            capacity: capacity.max(1),
+            versions,
+            served_generation: 1,
+            frozen: false,
+        }
+    }
+
+    /// Records a newly built `PolicySet` and returns the version that should be served, if the
+    /// served version must change. A policy set equal to the latest version does not create a
+    /// new generation.
+    pub(crate) fn record(&mut self, policy_set: PolicySet) -> Option<PolicySetVersion> {
+        let latest = self.latest();
+        if latest.policy_set.as_ref() != &policy_set {
+            let generation = latest.generation + 1;
+            self.versions.push_back(PolicySetVersion {
+                generation,
+                created_at: Utc::now(),
+                policy_set: Arc::new(policy_set),
+            });
+            self.evict();
+        }
+
+        let latest = self.latest().clone();
+        if self.frozen || latest.generation == self.served_generation {
+            return None;
+        }
+        self.served_generation = latest.generation;
+        Some(latest)
+    }
+
+    /// Pins the history to `generation` and returns its version, freezing the history so that
+    /// recorded versions are not served.
+    pub(crate) fn rollback_to(&mut self, generation: u64) -> Option<PolicySetVersion> {
+        let version = self.version(generation)?;
+        self.served_generation = generation;
+        self.frozen = true;
+        Some(version)
+    }
+
+    pub(crate) fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    pub(crate) fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub(crate) fn served_generation(&self) -> u64 {
+        self.served_generation
+    }
+
+    /// Returns the retained version of `generation`.
+    pub(crate) fn version(&self, generation: u64) -> Option<PolicySetVersion> {
+        self.versions
+            .iter()
+            .find(|version| version.generation == generation)
+            .cloned()
+    }
+
+    /// Returns the version currently served, which is never evicted.
+    pub(crate) fn served(&self) -> PolicySetVersion {
+        self.version(self.served_generation)
+            .expect("the served version is never evicted")
+    }
+
+    pub(crate) fn versions(&self) -> Vec<PolicySetVersion> {
+        self.versions.iter().cloned().collect()
+    }
+
+    fn latest(&self) -> &PolicySetVersion {
+        self.versions
+            .back()
+            .expect("the history always retains at least one version")
+    }
+
+    /// Drops the oldest versions above capacity, skipping the served version.
+    fn evict(&mut self) {
+        while self.versions.len() > self.capacity {
+            let Some(index) = self
+                .versions
+                .iter()
+                .position(|version| version.generation != self.served_generation)
+            else {
+                return;
+            };
+            self.versions.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use cedar_policy::PolicySet;
+
+    use crate::public::policy_set_history::{PolicySetHistory, PolicySetVersion};
+
+    /// Builds a `PolicySet` of `count` static policies, each permitting a different principal.
+    pub fn policy_set(count: usize) -> PolicySet {
+        let src = (0..count)
+            .map(|i| format!(r#"permit(principal == User::"{i}", action, resource);"#))
+            .collect::<Vec<_>>()
+            .join("\n");
+        PolicySet::from_str(&src).unwrap()
+    }
+
+    #[test]
+    fn record_serves_changed_policy_sets_as_new_generations() {
+        let mut history = PolicySetHistory::new(3, Arc::new(policy_set(1)));
+
+        let version = history.record(policy_set(2)).unwrap();
+        assert_eq!(version.generation(), 2);
+        assert_eq!(history.served_generation(), 2);
+    }
+
+    #[test]
+    fn record_does_not_create_a_generation_for_an_unchanged_policy_set() {
+        let mut history = PolicySetHistory::new(3, Arc::new(policy_set(1)));
+
+        assert!(history.record(policy_set(1)).is_none());
+        assert_eq!(history.versions().len(), 1);
+    }
+
+    #[test]
+    fn record_evicts_the_oldest_versions() {
+        let mut history = PolicySetHistory::new(2, Arc::new(policy_set(1)));
+        history.record(policy_set(2));
+        history.record(policy_set(3));
+
+        let generations = history
+            .versions()
+            .iter()
+            .map(PolicySetVersion::generation)
+            .collect::<Vec<_>>();
+        assert_eq!(generations, vec![2, 3]);
+    }
+
+    #[test]
+    fn frozen_history_records_without_serving() {
+        let mut history = PolicySetHistory::new(3, Arc::new(policy_set(1)));
+        history.set_frozen(true);
+
+        assert!(history.record(policy_set(2)).is_none());
+        assert_eq!(history.served_generation(), 1);
+        assert_eq!(history.versions().len(), 2);
+
+        history.set_frozen(false);
+        assert_eq!(history.record(policy_set(2)).unwrap().generation(), 2);
+    }
+
+    #[test]
+    fn rollback_freezes_and_keeps_the_served_version_on_eviction() {
+        let mut history = PolicySetHistory::new(2, Arc::new(policy_set(1)));
+        history.record(policy_set(2));
+
+        assert_eq!(history.rollback_to(1).unwrap().generation(), 1);
+        assert!(history.is_frozen());
+
+        history.record(policy_set(3));
+        let generations = history
+            .versions()
+            .iter()
+            .map(PolicySetVersion::generation)
+            .collect::<Vec<_>>();
+        assert_eq!(generations, vec![1, 3]);
+        assert_eq!(history.served_generation(), 1);
+    }
+
+    #[test]
+    fn rollback_to_an_unknown_generation_returns_none() {
+        let mut history = PolicySetHistory::new(2, Arc::new(policy_set(1)));
+        assert!(history.rollback_to(7).is_none());
+        assert!(!history.is_frozen());
+    }
+}
@@ -1,9 +1,12 @@
 //! Provides an Amazon Verified Permissions Policy Set Provider!
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::types::PolicyType;
 use aws_sdk_verifiedpermissions::Client;
 use cedar_policy::{PolicyId, PolicySet, Request};
 use derive_builder::Builder;
@@ -11,7 +14,7 @@ use thiserror::Error;
 use tokio::runtime::Handle;
 use tokio::sync::{Mutex, RwLock};
 use tokio::task;
-use tracing::{error, info, instrument};
+use tracing::{debug, info, instrument, warn};
 
 use cedar_local_agent::public::{
     PolicySetProviderError, SimplePolicySetProvider, UpdateProviderData, UpdateProviderDataError,
@@ -21,11 +24,26 @@ use crate::private::sources::policy::core::{PolicySource, VerifiedPermissionsPol
 use crate::private::sources::policy::error::PolicySourceException;
 use crate::private::sources::template::core::{TemplateSource, VerifiedPermissionsTemplateSource};
 use crate::private::sources::template::error::TemplateSourceException;
-use crate::private::translator::avp_to_cedar::Policy;
+pub use crate::private::sources::{CacheChange, PendingChange};
+use crate::private::translator::avp_to_cedar::{Policy, Template};
+use crate::private::types::policy_id;
 use crate::private::types::policy_selector::PolicySelector;
 use crate::private::types::policy_store_filter::{PolicyFilterInputError, PolicyStoreFilter};
+use crate::private::types::template_id::TemplateId;
 
+use super::deletion_guard::{DeletionGuard, RemovedPolicies};
+use super::policy_metadata::{PolicyMetadata, TemplateMetadata};
+use super::policy_overlay::PolicyOverlay;
 use super::policy_set_filter::PolicySetFilter;
+use super::policy_set_history::{PolicySetHistory, PolicySetVersion};
+use super::policy_set_slicer::PolicySetSlicer;
+use super::policy_store_validation::{
+    PolicyStoreSettings, PolicyStoreValidationError, PolicyStoreValidator,
+};
+use super::principal_policy_cache::{self, PrincipalLoading, PrincipalPolicyCache};
+
+/// The default number of `PolicySet` versions retained by the `PolicySetProvider`.
+pub const DEFAULT_HISTORY_DEPTH: usize = 5;
 
 /// `ProviderError` thrown by the constructor of the provider
 #[derive(Error, Debug)]
@@ -33,6 +51,9 @@ pub enum ProviderError {
     /// Configuration error
     #[error("The configuration didn't build: {0}")]
     Configuration(String),
+    /// The policy store settings cannot be read, or the policies fail its validation
+    #[error("Policy store validation failed: {0}")]
+    Validation(#[from] PolicyStoreValidationError),
     /// Cannot create the policy set
     #[error("Cannot create the PolicySet with source Amazon Verified Permissions: {0}")]
     PolicySet(#[from] PolicySetError),
@@ -45,6 +66,16 @@ pub enum ProviderError {
     /// A policy set filter expression is invalid
     #[error("Invalid Policy Store Filter expression: {0}")]
     PolicyFilterInputError(#[from] PolicyFilterInputError),
+    /// The requested policy set generation is not retained in the history
+    #[error("Policy set generation {0} is not retained in the history")]
+    GenerationNotFound(u64),
+    /// A refresh would remove more policies than the `DeletionGuard` allows
+    #[error(
+        "Refresh rejected by the deletion guard, it would remove {} of {} policies",
+        .0.removed,
+        .0.total
+    )]
+    DeletionGuard(RemovedPolicies),
 }
 
 /// The enum for errors that occur when building the `PolicySet`
@@ -61,12 +92,106 @@ pub enum PolicySetError {
     Template(String),
 }
 
+/// The changes a refresh of the `PolicySetProvider` would apply, computed by
+/// `PolicySetProvider::dry_run` without modifying the provider.
+#[derive(Debug, Default)]
+pub struct RefreshPreview {
+    /// Pending changes to policies keyed by AVP policy id
+    pub policies: HashMap<PolicyId, PendingChange<PolicySourceException>>,
+    /// Pending changes to templates keyed by AVP policy template id
+    pub templates: HashMap<PolicyId, PendingChange<TemplateSourceException>>,
+}
+
+impl RefreshPreview {
+    /// Returns true if a refresh would not change the served `PolicySet`.
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty() && self.templates.is_empty()
+    }
+
+    /// Returns true if any validated policy or template failed to translate to Cedar.
+    pub fn has_errors(&self) -> bool {
+        self.policies.values().any(|change| change.error.is_some())
+            || self.templates.values().any(|change| change.error.is_some())
+    }
+}
+
 impl From<ConfigBuilderError> for ProviderError {
     fn from(value: ConfigBuilderError) -> Self {
         Self::Configuration(value.to_string())
     }
 }
 
+impl From<ProviderOptionsBuilderError> for ProviderError {
+    fn from(value: ProviderOptionsBuilderError) -> Self {
+        Self::Configuration(value.to_string())
+    }
+}
+
+/// Optional settings for the `PolicySetProvider`. Favor the builder to create this object, any
+/// setting that is not provided uses its default.
+///
+/// # Examples
+///
+/// ```
+/// use avp_local_agent::public::policy_set_provider::ProviderOptionsBuilder;
+///
+/// let options = ProviderOptionsBuilder::default()
+///     .history_depth(10)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned")]
+pub struct ProviderOptions {
+    /// The number of published `PolicySet` versions retained for `rollback_to`
+    #[builder(default = "DEFAULT_HISTORY_DEPTH")]
+    pub history_depth: usize,
+    /// Rejects refreshes that would remove too many policies, disabled by default
+    #[builder(default, setter(strip_option))]
+    pub deletion_guard: Option<DeletionGuard>,
+    /// Local policies merged on top of the Amazon Verified Permissions policies
+    #[builder(default, setter(strip_option))]
+    pub overlay: Option<PolicyOverlay>,
+    /// Returns only the policies that could apply to each request from `get_policy_set`
+    #[builder(default)]
+    pub slice_by_request: bool,
+    /// Loads template-linked policies per request principal instead of on every refresh
+    #[builder(default, setter(strip_option))]
+    pub principal_loading: Option<PrincipalLoading>,
+    /// Reads the validation mode of the policy store on every refresh and, in `STRICT` stores,
+    /// validates the served `PolicySet` and incoming requests against the schema
+    #[builder(default)]
+    pub honor_validation_settings: bool,
+}
+
+impl Default for ProviderOptions {
+    fn default() -> Self {
+        Self {
+            history_depth: DEFAULT_HISTORY_DEPTH,
+            deletion_guard: None,
+            overlay: None,
+            slice_by_request: false,
+            principal_loading: None,
+            honor_validation_settings: false,
+        }
+    }
+}
+
+/// The state of a `PolicySetProvider` returned by `PolicySetProvider::health`.
+#[derive(Debug, Clone)]
+pub struct ProviderHealth {
+    /// The generation of the served `PolicySet`
+    pub generation: u64,
+    /// Whether refreshes are prevented from replacing the served `PolicySet`
+    pub frozen: bool,
+    /// The number of served policies, including the overlay
+    pub policies: usize,
+    /// The number of served templates
+    pub templates: usize,
+    /// The settings of the policy store, set when honoring its validation settings
+    pub policy_store: Option<PolicyStoreSettings>,
+}
+
 #[derive(Builder, Debug)]
 #[builder(pattern = "owned")]
 struct Config {
@@ -76,6 +201,15 @@ struct Config {
     pub template_source: VerifiedPermissionsTemplateSource,
     /// Policy Store Id to gather policies and templates from
     pub policy_selector: PolicySelector,
+    /// Optional provider settings
+    #[builder(default)]
+    pub options: ProviderOptions,
+    /// Loads the template-linked policies of request principals
+    #[builder(default)]
+    pub principal_policies: Option<PrincipalPolicyCache>,
+    /// Applies the validation settings of the policy store
+    #[builder(default)]
+    pub validator: Option<PolicyStoreValidator>,
 }
 
 /// `PolicySetProvider` structure implements the `SimplePolicySetProvider` trait.
@@ -89,6 +223,20 @@ pub struct PolicySetProvider {
     template_source: Arc<Mutex<VerifiedPermissionsTemplateSource>>,
     /// Policy Set data that can be updated in a background thread
     policy_set: RwLock<Arc<PolicySet>>,
+    /// Previously built policy sets and the freeze state
+    history: Mutex<PolicySetHistory>,
+    /// Limits on the policies a refresh may remove
+    deletion_guard: Option<DeletionGuard>,
+    /// Lets the next refresh bypass the deletion guard
+    deletion_guard_override: AtomicBool,
+    /// Local policies merged into every served `PolicySet`
+    overlay: RwLock<PolicyOverlay>,
+    /// Index of the served `PolicySet`, set when slicing by request
+    slicer: Option<RwLock<Arc<PolicySetSlicer>>>,
+    /// Template-linked policies of request principals, set when loading them per principal
+    principal_policies: Option<PrincipalPolicyCache>,
+    /// Validation settings of the policy store, set when honoring them
+    validator: Option<PolicyStoreValidator>,
 }
 
 impl PolicySetProvider {
@@ -97,18 +245,53 @@ impl PolicySetProvider {
         policy_store_id: String,
         policy_store_filters: Option<PolicyStoreFilter>,
         verified_permissions_client: Client,
+        options: ProviderOptions,
     ) -> Result<Self, ProviderError> {
+        let mut policy_selector =
+            PolicySelector::from(policy_store_id).with_filters(policy_store_filters);
+        let principal_policies = match options.principal_loading {
+            Some(_) if options.slice_by_request => {
+                return Err(ProviderError::Configuration(
+                    "slice_by_request cannot be combined with principal_loading".into(),
+                ));
+            }
+            Some(loading) => {
+                let principal_policies = PrincipalPolicyCache::new(
+                    verified_permissions_client.clone(),
+                    &policy_selector,
+                    loading,
+                )?;
+                let static_filter = PolicyStoreFilter::narrow_policy_type(
+                    policy_selector.filters().cloned(),
+                    PolicyType::Static,
+                )?;
+                policy_selector = policy_selector.with_filters(Some(static_filter));
+                Some(principal_policies)
+            }
+            None => None,
+        };
+        let validator = options
+            .honor_validation_settings
+            .then(|| {
+                PolicyStoreValidator::from_client(
+                    policy_selector.clone(),
+                    verified_permissions_client.clone(),
+                )
+            })
+            .transpose()?;
+
         Self::new(
             ConfigBuilder::default()
-                .policy_selector(
-                    PolicySelector::from(policy_store_id).with_filters(policy_store_filters),
-                )
+                .policy_selector(policy_selector)
+                .principal_policies(principal_policies)
+                .validator(validator)
                 .policy_source(VerifiedPermissionsPolicySource::from(
                     verified_permissions_client.clone(),
                 ))
                 .template_source(VerifiedPermissionsTemplateSource::from(
                     verified_permissions_client,
                 ))
+                .options(options)
                 .build()?,
         )
     }
@@ -124,7 +307,12 @@ impl PolicySetProvider {
         policy_store_id: String,
         verified_permissions_client: Client,
     ) -> Result<Self, ProviderError> {
-        Self::from_all(policy_store_id, None, verified_permissions_client)
+        Self::from_all(
+            policy_store_id,
+            None,
+            verified_permissions_client,
+            ProviderOptions::default(),
+        )
     }
 
     /// Provides a helper to build the `PolicySetProvider` from an Amazon Verified Permissions
@@ -139,9 +327,36 @@ impl PolicySetProvider {
         policy_store_id: String,
         policy_store_filters: Option<PolicySetFilter<'a>>,
         verified_permissions_client: Client,
+    ) -> Result<Self, ProviderError> {
+        Self::from_client_with_options(
+            policy_store_id,
+            policy_store_filters,
+            verified_permissions_client,
+            ProviderOptions::default(),
+        )
+    }
+
+    /// Provides a helper to build the `PolicySetProvider` from an Amazon Verified Permissions
+    /// client and policy store id with optional policy filtering and `ProviderOptions`
+    ///
+    /// # Errors
+    ///
+    /// Can error if the builder is incorrect, if the `new` constructor fails to gather the
+    /// applicable data on initialization, or if the `PolicySetFilter` expression is not valid.
+    #[instrument(skip(verified_permissions_client), err(Debug))]
+    pub fn from_client_with_options<'a>(
+        policy_store_id: String,
+        policy_store_filters: Option<PolicySetFilter<'a>>,
+        verified_permissions_client: Client,
+        options: ProviderOptions,
     ) -> Result<Self, ProviderError> {
         let filters = policy_store_filters.map(TryInto::try_into).transpose()?;
-        Self::from_all(policy_store_id, filters, verified_permissions_client)
+        Self::from_all(
+            policy_store_id,
+            filters,
+            verified_permissions_client,
+            options,
+        )
     }
 
     #[instrument(skip(config), err(Debug))]
@@ -150,12 +365,14 @@ impl PolicySetProvider {
             policy_selector,
             template_source,
             policy_source,
+            options,
+            principal_policies,
+            validator,
         } = config;
 
         let template_source = Arc::new(Mutex::new(template_source));
         let policy_source = Arc::new(Mutex::new(policy_source));
 
-        let mut policy_set = PolicySet::new();
         let policy_selector_clone = policy_selector.clone();
         let template_source_ref = template_source.clone();
         let templates = task::block_in_place(move || {
@@ -170,7 +387,7 @@ impl PolicySetProvider {
 
         let policy_selector_clone = policy_selector.clone();
         let policy_source_ref = policy_source.clone();
-        let policies = task::block_in_place(move || {
+        let mut policies = task::block_in_place(move || {
             Handle::current().block_on(async move {
                 policy_source_ref
                     .lock()
@@ -179,62 +396,402 @@ impl PolicySetProvider {
                     .await
             })
         })?;
-
-        for (_, template) in templates {
-            policy_set
-                .add_template(template.0.clone())
-                .map_err(|_| PolicySetError::Template(template.0.id().to_string()))?;
+        if let Some(principal_policies) = &principal_policies {
+            policies.extend(task::block_in_place(|| {
+                Handle::current().block_on(principal_policies.fetch_shared(&templates))
+            })?);
         }
 
-        for (_, policy) in policies {
-            match policy {
-                Policy::Static(cedar_policy) => {
-                    let cedar_policy_id = &cedar_policy.id().clone();
-                    policy_set
-                        .add(cedar_policy)
-                        .map_err(|_| PolicySetError::StaticPolicy(cedar_policy_id.to_string()))?;
-                }
-                Policy::TemplateLinked(policy_id, template_id, entity_map) => {
-                    let cedar_policy_id =
-                        PolicyId::from_str(&policy_id.to_string()).map_err(|_| {
-                            PolicySetError::TemplateLinkedPolicy(
-                                policy_id.to_string(),
-                                template_id.to_string(),
-                            )
-                        })?;
-                    let cedar_template_id =
-                        PolicyId::from_str(&template_id.to_string()).map_err(|_| {
-                            PolicySetError::TemplateLinkedPolicy(
-                                policy_id.to_string(),
-                                template_id.to_string(),
-                            )
-                        })?;
-                    policy_set
-                        .link(cedar_template_id, cedar_policy_id, entity_map)
-                        .map_err(|_| {
-                            PolicySetError::TemplateLinkedPolicy(
-                                policy_id.to_string(),
-                                template_id.to_string(),
-                            )
-                        })?;
-                }
-            }
+        let policy_set = build_policy_set(templates, policies)?;
+        let overlay = options.overlay.unwrap_or_default();
+        let served = overlay.merge(&policy_set)?;
+        if let Some(validator) = &validator {
+            task::block_in_place(|| {
+                Handle::current().block_on(validator.validate_policy_set(&served))
+            })?;
         }
+        let slicer = options
+            .slice_by_request
+            .then(|| RwLock::new(Arc::new(PolicySetSlicer::new(&served))));
 
         Ok(Self {
             policy_selector,
             template_source,
             policy_source,
-            policy_set: RwLock::new(Arc::new(policy_set)),
+            policy_set: RwLock::new(Arc::new(served)),
+            history: Mutex::new(PolicySetHistory::new(
+                options.history_depth,
+                Arc::new(policy_set),
+            )),
+            deletion_guard: options.deletion_guard,
+            deletion_guard_override: AtomicBool::new(false),
+            overlay: RwLock::new(overlay),
+            slicer,
+            principal_policies,
+            validator,
         })
     }
+
+    /// Computes the changes the next `update_provider_data` call would apply by listing the
+    /// policies and templates in Amazon Verified Permissions and comparing them to the cached
+    /// data. Neither the caches nor the served `PolicySet` are modified.
+    ///
+    /// When `validate` is set, created and updated policies and templates are also fetched and
+    /// translated to Cedar, and translation failures are reported on the pending change.
+    ///
+    /// # Errors
+    ///
+    /// Can error if listing or reading the policies and templates from Amazon Verified
+    /// Permissions fails.
+    #[instrument(skip(self), err(Debug))]
+    pub async fn dry_run(&self, validate: bool) -> Result<RefreshPreview, ProviderError> {
+        let templates = self
+            .template_source
+            .lock()
+            .await
+            .preview(self.policy_selector.clone(), validate)
+            .await?;
+
+        let policies = self
+            .policy_source
+            .lock()
+            .await
+            .preview(self.policy_selector.clone(), validate)
+            .await?;
+
+        let preview = RefreshPreview {
+            policies: policies
+                .into_iter()
+                .map(|(policy_id, change)| (PolicyId::new(policy_id.0), change))
+                .collect(),
+            templates: templates
+                .into_iter()
+                .map(|(template_id, change)| (PolicyId::new(template_id.0), change))
+                .collect(),
+        };
+        info!(
+            "Previewed Policy Set Provider refresh: policy_changes={}, template_changes={}",
+            preview.policies.len(),
+            preview.templates.len()
+        );
+        Ok(preview)
+    }
+
+    /// Returns the metadata of an Amazon Verified Permissions policy, such as its description,
+    /// principal, resource and dates, from the data cached by the last refresh. Returns `None` for
+    /// unknown policies, overlay policies and policies loaded per principal.
+    pub async fn policy_metadata(&self, policy_id: &PolicyId) -> Option<PolicyMetadata> {
+        let policy_source = self.policy_source.lock().await;
+        let output = policy_source.cached_policy(&policy_id::PolicyId(policy_id.to_string()))?;
+        let policy_set = self.policy_set.read().await.clone();
+        let metadata = PolicyMetadata::new(output, policy_set.policy(policy_id));
+        drop(policy_source);
+        Some(metadata)
+    }
+
+    /// Returns the metadata of an Amazon Verified Permissions policy template from the data cached
+    /// by the last refresh, or `None` if the template is unknown.
+    pub async fn template_metadata(&self, template_id: &PolicyId) -> Option<TemplateMetadata> {
+        let template_source = self.template_source.lock().await;
+        let output = template_source.cached_template(&TemplateId(template_id.to_string()))?;
+        let policy_set = self.policy_set.read().await.clone();
+        let metadata = TemplateMetadata::new(output, policy_set.template(template_id));
+        drop(template_source);
+        Some(metadata)
+    }
+
+    /// Reports the state of the provider and, when its validation settings are honored, the
+    /// settings of the policy store.
+    pub async fn health(&self) -> ProviderHealth {
+        let policy_set = self.policy_set.read().await.clone();
+        let history = self.history.lock().await;
+        let (generation, frozen) = (history.served_generation(), history.is_frozen());
+        drop(history);
+        let policy_store = match &self.validator {
+            Some(validator) => Some(validator.settings().await),
+            None => None,
+        };
+
+        ProviderHealth {
+            generation,
+            frozen,
+            policies: policy_set.policies().count(),
+            templates: policy_set.templates().count(),
+            policy_store,
+        }
+    }
+
+    /// Returns the whole served `PolicySet`, including the overlay and before any request slicing.
+    /// Template-linked policies loaded per principal are not included.
+    pub async fn policy_set(&self) -> Arc<PolicySet> {
+        self.policy_set.read().await.clone()
+    }
+
+    /// Returns the retained `PolicySet` versions ordered from oldest to newest. Versions only
+    /// contain the Amazon Verified Permissions policies, the overlay is merged when served.
+    pub async fn history(&self) -> Vec<PolicySetVersion> {
+        self.history.lock().await.versions()
+    }
+
+    /// Returns the generation of the `PolicySet` currently served by the provider.
+    pub async fn current_generation(&self) -> u64 {
+        self.history.lock().await.served_generation()
+    }
+
+    /// Serves the retained `PolicySet` of `generation` and freezes the provider, so that
+    /// refreshes do not replace it until `unfreeze` is called.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::GenerationNotFound` if the generation is not retained.
+    #[instrument(skip(self), err(Debug))]
+    pub async fn rollback_to(&self, generation: u64) -> Result<(), ProviderError> {
+        let mut history = self.history.lock().await;
+        let version = history
+            .version(generation)
+            .ok_or(ProviderError::GenerationNotFound(generation))?;
+        let served = self.overlay.read().await.merge(&version.policy_set())?;
+        history.rollback_to(generation);
+        self.serve(served).await;
+        drop(history);
+        info!("Rolled back Policy Set Provider: generation={generation}");
+        Ok(())
+    }
+
+    /// Stops refreshes from replacing the served `PolicySet`. Refreshes still record newly built
+    /// versions in the history.
+    #[instrument(skip(self))]
+    pub async fn freeze(&self) {
+        self.history.lock().await.set_frozen(true);
+        info!("Froze Policy Set Provider");
+    }
+
+    /// Allows refreshes to replace the served `PolicySet` again, the next refresh serves the
+    /// latest version.
+    #[instrument(skip(self))]
+    pub async fn unfreeze(&self) {
+        self.history.lock().await.set_frozen(false);
+        info!("Unfroze Policy Set Provider");
+    }
+
+    /// Returns true if refreshes are prevented from replacing the served `PolicySet`.
+    pub async fn is_frozen(&self) -> bool {
+        self.history.lock().await.is_frozen()
+    }
+
+    /// Returns the local policies merged on top of the Amazon Verified Permissions policies.
+    pub async fn overlay(&self) -> PolicyOverlay {
+        self.overlay.read().await.clone()
+    }
+
+    /// Replaces the local policy overlay and serves it immediately, including while the provider
+    /// is frozen.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ProviderError::PolicySet` if an overlay policy id conflicts with a policy of the
+    /// served `PolicySet`, the previous overlay is kept.
+    #[instrument(skip_all, err(Debug))]
+    pub async fn set_overlay(&self, overlay: PolicyOverlay) -> Result<(), ProviderError> {
+        let history = self.history.lock().await;
+        let served = overlay.merge(&history.served().policy_set())?;
+        self.validate_policy_set(&served).await?;
+        let overlay_len = overlay.len();
+        *self.overlay.write().await = overlay;
+        self.serve(served).await;
+        drop(history);
+        info!("Replaced policy overlay: policies={overlay_len}");
+        Ok(())
+    }
+
+    /// Drops the cached template-linked policies of every principal, so that they are listed
+    /// again from Amazon Verified Permissions on their next request.
+    #[instrument(skip(self))]
+    pub async fn clear_principal_cache(&self) {
+        if let Some(principal_policies) = &self.principal_policies {
+            principal_policies.clear().await;
+            info!("Cleared principal policy cache");
+        }
+    }
+
+    /// Lets the next refresh replace the served `PolicySet` even if the `DeletionGuard` would
+    /// reject it, accepting the removal of policies from Amazon Verified Permissions.
+    #[instrument(skip(self))]
+    pub fn override_deletion_guard(&self) {
+        self.deletion_guard_override.store(true, Ordering::SeqCst);
+        info!("Deletion guard will be overridden on the next refresh");
+    }
+
+    /// Rejects the refreshed `PolicySet` if it removes more policies from the served
+    /// `PolicySet` than the `DeletionGuard` allows, unless the guard was overridden. The override
+    /// is only used up by a refresh it lets through.
+    async fn check_deletion_guard(&self, refreshed: &PolicySet) -> Result<(), ProviderError> {
+        let Some(deletion_guard) = self.deletion_guard else {
+            return Ok(());
+        };
+
+        let served = self.history.lock().await.served().policy_set();
+        let Err(removed) = deletion_guard.check(&served, refreshed) else {
+            return Ok(());
+        };
+        if self.deletion_guard_override.swap(false, Ordering::SeqCst) {
+            info!(
+                "Deletion guard overridden for this refresh: removed={}, total={}",
+                removed.removed, removed.total
+            );
+            return Ok(());
+        }
+        warn!(
+            "Deletion guard rejected refresh: removed={}, total={}",
+            removed.removed, removed.total
+        );
+        Err(ProviderError::DeletionGuard(removed))
+    }
+
+    /// Fetches the templates and policies into the locked sources, and publishes the built
+    /// `PolicySet` if the `DeletionGuard` accepts it.
+    async fn refresh_policy_set(
+        &self,
+        template_source: &mut VerifiedPermissionsTemplateSource,
+        policy_source: &mut VerifiedPermissionsPolicySource,
+    ) -> Result<(), ProviderError> {
+        let templates = template_source.fetch(self.policy_selector.clone()).await?;
+        let mut policies = policy_source.fetch(self.policy_selector.clone()).await?;
+        if let Some(principal_policies) = &self.principal_policies {
+            policies.extend(principal_policies.fetch_shared(&templates).await?);
+        }
+
+        let policy_set = build_policy_set(templates, policies)?;
+        self.check_deletion_guard(&policy_set).await?;
+        self.publish(policy_set).await
+    }
+
+    /// Validates a `PolicySet` against the schema when the policy store is `STRICT` and its
+    /// validation settings are honored.
+    async fn validate_policy_set(&self, policy_set: &PolicySet) -> Result<(), ProviderError> {
+        if let Some(validator) = &self.validator {
+            validator.validate_policy_set(policy_set).await?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the served `PolicySet`, re-indexing it when slicing by request.
+    async fn serve(&self, policy_set: PolicySet) {
+        if let Some(slicer) = &self.slicer {
+            *slicer.write().await = Arc::new(PolicySetSlicer::new(&policy_set));
+        }
+        *self.policy_set.write().await = Arc::new(policy_set);
+    }
+
+    /// Records a newly built `PolicySet` and serves it merged with the overlay unless the
+    /// provider is frozen.
+    async fn publish(&self, policy_set: PolicySet) -> Result<(), ProviderError> {
+        let mut history = self.history.lock().await;
+        let served = self.overlay.read().await.merge(&policy_set)?;
+        self.validate_policy_set(&served).await?;
+        if let Some(version) = history.record(policy_set) {
+            self.serve(served).await;
+            drop(history);
+            info!("Published Policy Set: generation={}", version.generation());
+        } else if history.is_frozen() {
+            let served_generation = history.served_generation();
+            drop(history);
+            info!("Policy Set Provider is frozen: generation={served_generation}");
+        }
+        Ok(())
+    }
+}
+
+/// Builds a `PolicySet` from translated AVP templates and policies.
+fn build_policy_set(
+    templates: HashMap<TemplateId, Template>,
+    policies: HashMap<policy_id::PolicyId, Policy>,
+) -> Result<PolicySet, PolicySetError> {
+    let mut policy_set = PolicySet::new();
+    for (_, template) in templates {
+        policy_set
+            .add_template(template.0.clone())
+            .map_err(|_| PolicySetError::Template(template.0.id().to_string()))?;
+    }
+
+    for (_, policy) in policies {
+        add_policy(&mut policy_set, policy)?;
+    }
+    Ok(policy_set)
+}
+
+/// Adds a translated AVP policy to the `policy_set`, linking template-linked policies to their
+/// template which must already be in the `policy_set`.
+pub(crate) fn add_policy(policy_set: &mut PolicySet, policy: Policy) -> Result<(), PolicySetError> {
+    match policy {
+        Policy::Static(cedar_policy) => {
+            let cedar_policy_id = &cedar_policy.id().clone();
+            policy_set
+                .add(cedar_policy)
+                .map_err(|_| PolicySetError::StaticPolicy(cedar_policy_id.to_string()))?;
+        }
+        Policy::TemplateLinked(policy_id, template_id, entity_map) => {
+            let cedar_policy_id = PolicyId::from_str(&policy_id.to_string()).map_err(|_| {
+                PolicySetError::TemplateLinkedPolicy(policy_id.to_string(), template_id.to_string())
+            })?;
+            let cedar_template_id = PolicyId::from_str(&template_id.to_string()).map_err(|_| {
+                PolicySetError::TemplateLinkedPolicy(policy_id.to_string(), template_id.to_string())
+            })?;
+            policy_set
+                .link(cedar_template_id, cedar_policy_id, entity_map)
+                .map_err(|_| {
+                    PolicySetError::TemplateLinkedPolicy(
+                        policy_id.to_string(),
+                        template_id.to_string(),
+                    )
+                })?;
+        }
+    }
+    Ok(())
 }
 
 #[async_trait]
 impl SimplePolicySetProvider for PolicySetProvider {
     #[instrument(skip_all, err(Debug))]
-    async fn get_policy_set(&self, _: &Request) -> Result<Arc<PolicySet>, PolicySetProviderError> {
-        Ok(self.policy_set.read().await.clone())
+    async fn get_policy_set(
+        &self,
+        request: &Request,
+    ) -> Result<Arc<PolicySet>, PolicySetProviderError> {
+        if let Some(validator) = &self.validator {
+            validator
+                .validate_request(request)
+                .await
+                .map_err(|e| PolicySetProviderError::General(Box::new(e)))?;
+        }
+
+        if let (Some(principal_policies), Some(principal)) =
+            (&self.principal_policies, request.principal())
+        {
+            let generation = self.current_generation().await;
+            let policies = principal_policies
+                .get(principal, generation)
+                .await
+                .map_err(|e| PolicySetProviderError::General(Box::new(e)))?;
+            let served = self.policy_set.read().await.clone();
+            return principal_policy_cache::link(&served, &policies)
+                .map(Arc::new)
+                .map_err(|e| PolicySetProviderError::General(Box::new(e)));
+        }
+
+        let Some(slicer) = &self.slicer else {
+            return Ok(self.policy_set.read().await.clone());
+        };
+
+        let slicer = slicer.read().await.clone();
+        let slice = slicer
+            .slice(request)
+            .map_err(|e| PolicySetProviderError::General(Box::new(e)))?;
+        debug!(
+            "Sliced Policy Set for request: policies={}, indexed={}",
+            slice.policies().count(),
+            slicer.len()
+        );
+        Ok(Arc::new(slice))
     }
 }
 
@@ -242,87 +799,935 @@ impl SimplePolicySetProvider for PolicySetProvider {
 impl UpdateProviderData for PolicySetProvider {
     #[instrument(skip(self), err(Debug))]
     async fn update_provider_data(&self) -> Result<(), UpdateProviderDataError> {
-        let templates;
-        {
-            templates = self
-                .template_source
-                .lock()
-                .await
-                .fetch(self.policy_selector.clone())
+        if let Some(validator) = &self.validator {
+            validator
+                .refresh()
                 .await
                 .map_err(|e| UpdateProviderDataError::General(Box::new(ProviderError::from(e))))?;
-        };
+        }
+
+        // The cache entries changed by the fetches are reverted when the refreshed policy set is
+        // not published, so that `dry_run` and the metadata keep describing the served policy set.
+        // The changes of earlier fetches are served, and a failing refresh may not fetch both.
+        let mut template_source = self.template_source.lock().await;
+        let mut policy_source = self.policy_source.lock().await;
+        template_source.accept_fetch();
+        policy_source.accept_fetch();
 
-        let policies;
+        if let Err(e) = self
+            .refresh_policy_set(&mut template_source, &mut policy_source)
+            .await
         {
-            policies = self
-                .policy_source
-                .lock()
-                .await
-                .fetch(self.policy_selector.clone())
-                .await
-                .map_err(|e| UpdateProviderDataError::General(Box::new(ProviderError::from(e))))?;
+            template_source.revert_fetch();
+            policy_source.revert_fetch();
+            return Err(UpdateProviderDataError::General(Box::new(e)));
         }
+        drop(policy_source);
+        drop(template_source);
+        info!("Updated Policy Set Provider");
+        Ok(())
+    }
+}
 
-        let mut policy_set_data = PolicySet::new();
-        for (_, template) in templates {
-            policy_set_data
-                .add_template(template.0.clone())
-                .map_err(|_| {
-                    UpdateProviderDataError::General(Box::new(ProviderError::from(
-                        PolicySetError::Template(template.0.id().to_string()),
-                    )))
-                })?;
-        }
+#[cfg(test)]
+pub(crate) mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::time::Duration;
 
-        for (_, policy) in policies {
-            match policy {
-                Policy::Static(cedar_policy) => {
-                    let cedar_policy_id = &cedar_policy.id().clone();
-                    policy_set_data.add(cedar_policy).map_err(|_| {
-                        UpdateProviderDataError::General(Box::new(PolicySetError::StaticPolicy(
-                            cedar_policy_id.to_string(),
-                        )))
-                    })?;
-                }
-                Policy::TemplateLinked(policy_id, template_id, entity_map) => {
-                    let cedar_policy_id =
-                        PolicyId::from_str(&policy_id.to_string()).map_err(|_| {
-                            UpdateProviderDataError::General(Box::new(
-                                PolicySetError::TemplateLinkedPolicy(
-                                    policy_id.to_string(),
-                                    template_id.to_string(),
-                                ),
-                            ))
-                        })?;
-                    let cedar_template_id =
-                        PolicyId::from_str(&template_id.to_string()).map_err(|_| {
-                            UpdateProviderDataError::General(Box::new(
-                                PolicySetError::TemplateLinkedPolicy(
-                                    policy_id.to_string(),
-                                    template_id.to_string(),
-                                ),
-                            ))
-                        })?;
-                    policy_set_data
-                        .link(cedar_template_id, cedar_policy_id, entity_map)
-                        .map_err(|_| {
-                            UpdateProviderDataError::General(Box::new(
-                                PolicySetError::TemplateLinkedPolicy(
-                                    policy_id.to_string(),
-                                    template_id.to_string(),
-                                ),
-                            ))
-                        })?;
-                }
-            }
+    use aws_sdk_verifiedpermissions::types::{PolicyType, ValidationMode};
+    use aws_smithy_runtime::client::http::test_util::ReplayEvent;
+    use cedar_policy::{ActionConstraint, Context, Effect, EntityUid, PolicyId, Request};
+    use tokio::sync::watch;
+
+    use crate::private::sources::policy::core::test::{
+        build_entity_identifier, build_get_policy_response, build_policy_item, GetPolicyRequest,
+        ListPoliciesRequest, ListPoliciesResponse, PolicyDefinitionDetailRaw,
+        StaticPolicyDefinitionDetailRaw, TemplateLinkedPolicyDefinitionDetailRaw,
+    };
+    use crate::private::sources::policy_store::reader::test::build_policy_store_event;
+    use crate::private::sources::template::core::test::{
+        build_get_policy_template_response, build_policy_template, GetPolicyTemplateRequest,
+        ListPolicyTemplatesRequest, ListPolicyTemplatesResponse,
+    };
+    use crate::private::sources::test::{build_client, build_event, StatusCode};
+    use crate::private::types::policy_id;
+    use crate::private::types::policy_selector::PolicySelector;
+    use crate::private::types::template_id::TemplateId;
+    use cedar_local_agent::public::{
+        SimplePolicySetProvider, UpdateProviderData, UpdateProviderDataError,
+    };
+
+    use crate::public::agent_config::{PolicyStoreProviders, DEFAULT_REFRESH_INTERVAL_SECS};
+    use crate::public::deletion_guard::{DeletionGuard, RemovedPolicies};
+    use crate::public::policy_overlay::{overlay_update_task, PolicyOverlay};
+    use crate::public::policy_set_provider::{
+        CacheChange, PolicySetProvider, ProviderError, ProviderOptionsBuilder,
+    };
+    use crate::public::policy_store_validation::PolicyStoreValidationError;
+    use crate::public::principal_policy_cache::PrincipalLoading;
+    use crate::public::schema_provider::test::{build_schema_event, SCHEMA};
+
+    pub const POLICY_STORE_ID: &str = "ps-1";
+    pub const POLICY_ID: &str = "policy-1";
+    pub const STATIC_POLICY: &str = r#"permit(principal == User::"alice", action, resource);"#;
+    pub const OTHER_POLICY_ID: &str = "policy-2";
+    pub const OTHER_STATIC_POLICY: &str = r#"permit(principal == User::"bob", action, resource);"#;
+    const LAST_UPDATED_DATE: &str = "2024-01-01T00:00:00Z";
+    const OVERLAY: &str =
+        r#"@id("block-mallory") forbid(principal == User::"mallory", action, resource);"#;
+    const OVERLAY_POLICY_ID: &str = "overlay::block-mallory";
+
+    /// Builds a `ListPolicyTemplates` event of `POLICY_STORE_ID` returning no templates.
+    pub fn list_templates_event() -> ReplayEvent {
+        build_event(
+            &ListPolicyTemplatesRequest {
+                policy_store_id: POLICY_STORE_ID.to_string(),
+                next_token: None,
+                max_results: 1,
+            },
+            &ListPolicyTemplatesResponse {
+                next_token: None,
+                policy_templates: None,
+            },
+            StatusCode::OK,
+        )
+    }
+
+    /// Builds a `ListPolicies` event of `POLICY_STORE_ID` returning the given static policies.
+    pub fn list_policies_event(policy_ids: &[&str]) -> ReplayEvent {
+        let policy_selector = PolicySelector::from(POLICY_STORE_ID.to_string());
+        build_event(
+            &ListPoliciesRequest {
+                policy_store_id: POLICY_STORE_ID.to_string(),
+                next_token: None,
+                max_results: 1,
+                filter: None,
+            },
+            &ListPoliciesResponse {
+                policies: Some(
+                    policy_ids
+                        .iter()
+                        .map(|id| {
+                            let mut item = build_policy_item(
+                                &policy_id::PolicyId((*id).to_string()),
+                                &policy_selector,
+                                Some("STATIC".to_string()),
+                                None,
+                                None,
+                                None,
+                            );
+                            item.last_updated_date = Some(LAST_UPDATED_DATE.to_string());
+                            item
+                        })
+                        .collect(),
+                ),
+                next_token: None,
+            },
+            StatusCode::OK,
+        )
+    }
+
+    /// Builds a `GetPolicy` event returning the given static policy statement.
+    pub fn get_policy_event(policy_id: &str, statement: &str) -> ReplayEvent {
+        let policy_selector = PolicySelector::from(POLICY_STORE_ID.to_string());
+        let mut response = build_get_policy_response(
+            &policy_id::PolicyId(policy_id.to_string()),
+            &policy_selector,
+            "STATIC",
+            build_entity_identifier("User", "alice"),
+            build_entity_identifier("Photo", "photo"),
+            PolicyDefinitionDetailRaw::Static(StaticPolicyDefinitionDetailRaw {
+                description: None,
+                statement: Some(statement.to_string()),
+            }),
+        );
+        response.last_updated_date = Some(LAST_UPDATED_DATE.to_string());
+        build_event(
+            &GetPolicyRequest {
+                policy_id: policy_id.to_string(),
+                policy_store_id: POLICY_STORE_ID.to_string(),
+            },
+            &response,
+            StatusCode::OK,
+        )
+    }
+
+    /// Builds the providers of `POLICY_STORE_ID` serving the static policy `statement` as
+    /// `POLICY_ID`, without an `EntityProvider`.
+    pub fn build_policy_store_providers(statement: &str) -> PolicyStoreProviders {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, statement),
+        ]);
+        PolicyStoreProviders {
+            policy_store_id: POLICY_STORE_ID.to_string(),
+            policy_set_provider: Arc::new(
+                PolicySetProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap(),
+            ),
+            entity_provider: None,
+            refresh_interval: Duration::from_secs(DEFAULT_REFRESH_INTERVAL_SECS),
+            snapshot_path: None,
         }
+    }
 
-        {
-            let mut policy_set = self.policy_set.write().await;
-            *policy_set = Arc::new(policy_set_data);
+    fn policy_ids(provider_policy_set: &cedar_policy::PolicySet) -> Vec<String> {
+        let mut ids = provider_policy_set
+            .policies()
+            .map(|policy| policy.id().to_string())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn dry_run_reports_changes_without_updating_the_policy_set() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            list_templates_event(),
+            list_policies_event(&[]),
+        ]);
+        let provider = PolicySetProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+
+        let preview = provider.dry_run(true).await.unwrap();
+
+        assert!(!preview.is_empty());
+        assert!(!preview.has_errors());
+        assert!(preview.templates.is_empty());
+        let change = preview
+            .policies
+            .get(&PolicyId::from_str(POLICY_ID).unwrap())
+            .unwrap();
+        assert_eq!(change.change, CacheChange::Deleted);
+
+        let policy_set = provider.policy_set.read().await.clone();
+        assert!(policy_set
+            .policy(&PolicyId::from_str(POLICY_ID).unwrap())
+            .is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn metadata_describes_the_cached_policies_and_templates() {
+        let policy_selector = PolicySelector::from(POLICY_STORE_ID.to_string());
+        let template_id = TemplateId("template-1".to_string());
+        let mut policy = build_get_policy_response(
+            &policy_id::PolicyId(POLICY_ID.to_string()),
+            &policy_selector,
+            "STATIC",
+            build_entity_identifier("User", "alice"),
+            build_entity_identifier("Photo", "photo"),
+            PolicyDefinitionDetailRaw::Static(StaticPolicyDefinitionDetailRaw {
+                description: Some("Alice can do anything".to_string()),
+                statement: Some(STATIC_POLICY.to_string()),
+            }),
+        );
+        policy.last_updated_date = Some(LAST_UPDATED_DATE.to_string());
+        let client = build_client(vec![
+            build_event(
+                &ListPolicyTemplatesRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    next_token: None,
+                    max_results: 1,
+                },
+                &ListPolicyTemplatesResponse {
+                    next_token: None,
+                    policy_templates: Some(vec![build_policy_template(
+                        &policy_selector,
+                        &template_id,
+                        "viewers",
+                    )]),
+                },
+                StatusCode::OK,
+            ),
+            build_event(
+                &GetPolicyTemplateRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    policy_template_id: template_id.to_string(),
+                },
+                &build_get_policy_template_response(
+                    &policy_selector,
+                    &template_id,
+                    "viewers",
+                    r#"permit(principal == ?principal, action == Action::"view", resource);"#,
+                ),
+                StatusCode::OK,
+            ),
+            list_policies_event(&[POLICY_ID]),
+            build_event(
+                &GetPolicyRequest {
+                    policy_id: POLICY_ID.to_string(),
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                },
+                &policy,
+                StatusCode::OK,
+            ),
+        ]);
+        let provider = PolicySetProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+
+        let policy = provider
+            .policy_metadata(&PolicyId::from_str(POLICY_ID).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(policy.policy_type, PolicyType::Static);
+        assert_eq!(policy.description.as_deref(), Some("Alice can do anything"));
+        assert_eq!(policy.principal.unwrap().entity_id, "alice");
+        assert_eq!(policy.effect, Some(Effect::Permit));
+        assert_eq!(policy.actions, Some(ActionConstraint::Any));
+
+        let template = provider
+            .template_metadata(&PolicyId::from_str("template-1").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(template.description.as_deref(), Some("viewers"));
+        assert_eq!(
+            template.actions,
+            Some(ActionConstraint::Eq(
+                EntityUid::from_str(r#"Action::"view""#).unwrap()
+            ))
+        );
+
+        assert!(provider
+            .policy_metadata(&PolicyId::from_str(OTHER_POLICY_ID).unwrap())
+            .await
+            .is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn strict_policy_store_rejects_policies_outside_the_schema() {
+        let client = build_client(vec![
+            build_policy_store_event(POLICY_STORE_ID, "STRICT"),
+            build_schema_event(SCHEMA, LAST_UPDATED_DATE),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(
+                POLICY_ID,
+                r#"permit(principal == Admin::"root", action, resource);"#,
+            ),
+        ]);
+        let options = ProviderOptionsBuilder::default()
+            .honor_validation_settings(true)
+            .build()
+            .unwrap();
+
+        let result = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            client,
+            options,
+        );
+
+        assert!(matches!(
+            result,
+            Err(ProviderError::Validation(
+                PolicyStoreValidationError::InvalidPolicySet(_)
+            ))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn strict_policy_store_validates_requests_and_reports_its_settings() {
+        let client = build_client(vec![
+            build_policy_store_event(POLICY_STORE_ID, "STRICT"),
+            build_schema_event(SCHEMA, LAST_UPDATED_DATE),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+        ]);
+        let options = ProviderOptionsBuilder::default()
+            .honor_validation_settings(true)
+            .build()
+            .unwrap();
+        let provider = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            client,
+            options,
+        )
+        .unwrap();
+        let request = |principal: &str| {
+            Request::new(
+                EntityUid::from_str(principal).unwrap(),
+                EntityUid::from_str(r#"Action::"view""#).unwrap(),
+                EntityUid::from_str(r#"Photo::"vacation.jpg""#).unwrap(),
+                Context::empty(),
+                None,
+            )
+            .unwrap()
+        };
+
+        assert!(provider
+            .get_policy_set(&request(r#"User::"alice""#))
+            .await
+            .is_ok());
+        assert!(provider
+            .get_policy_set(&request(r#"Photo::"alice""#))
+            .await
+            .is_err());
+
+        let health = provider.health().await;
+        assert_eq!(health.policies, 1);
+        assert_eq!(
+            health.policy_store.unwrap().validation_mode,
+            ValidationMode::Strict
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn rollback_pins_the_served_policy_set_until_unfrozen() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            get_policy_event(OTHER_POLICY_ID, OTHER_STATIC_POLICY),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+        ]);
+        let provider = PolicySetProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+        assert_eq!(provider.current_generation().await, 1);
+
+        provider.update_provider_data().await.unwrap();
+        assert_eq!(provider.current_generation().await, 2);
+        assert_eq!(provider.history().await.len(), 2);
+
+        provider.rollback_to(1).await.unwrap();
+        assert!(provider.is_frozen().await);
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()),
+            vec![POLICY_ID]
+        );
+
+        provider.update_provider_data().await.unwrap();
+        assert_eq!(provider.current_generation().await, 1);
+
+        provider.unfreeze().await;
+        provider.update_provider_data().await.unwrap();
+        assert_eq!(provider.current_generation().await, 2);
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()),
+            vec![POLICY_ID, OTHER_POLICY_ID]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn rollback_to_unknown_generation_errors() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+        ]);
+        let provider = PolicySetProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+
+        assert!(matches!(
+            provider.rollback_to(3).await,
+            Err(ProviderError::GenerationNotFound(3))
+        ));
+        assert!(!provider.is_frozen().await);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn failed_refresh_keeps_the_changes_of_served_refreshes() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            get_policy_event(OTHER_POLICY_ID, OTHER_STATIC_POLICY),
+        ]);
+        let provider = PolicySetProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+        provider.update_provider_data().await.unwrap();
+
+        // Listing the templates fails, so the policies are not fetched again
+        assert!(provider.update_provider_data().await.is_err());
+        assert!(provider
+            .policy_metadata(&PolicyId::new(OTHER_POLICY_ID))
+            .await
+            .is_some());
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()).len(),
+            2
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn deletion_guard_rejects_refresh_until_overridden() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            get_policy_event(OTHER_POLICY_ID, OTHER_STATIC_POLICY),
+            list_templates_event(),
+            list_policies_event(&[]),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            list_templates_event(),
+            list_policies_event(&[]),
+        ]);
+        let options = ProviderOptionsBuilder::default()
+            .deletion_guard(DeletionGuard {
+                max_removed_policies: None,
+                max_removed_percent: Some(50),
+            })
+            .build()
+            .unwrap();
+        let provider = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            client,
+            options,
+        )
+        .unwrap();
+
+        let error = provider.update_provider_data().await.unwrap_err();
+        let UpdateProviderDataError::General(error) = error;
+        assert!(matches!(
+            error.downcast_ref::<ProviderError>(),
+            Some(ProviderError::DeletionGuard(RemovedPolicies {
+                removed: 2,
+                total: 2
+            }))
+        ));
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()).len(),
+            2
+        );
+        // The caches still describe the served policy set after the rejected refresh
+        assert!(provider
+            .policy_metadata(&PolicyId::new(POLICY_ID))
+            .await
+            .is_some());
+        assert!(provider.dry_run(false).await.unwrap().is_empty());
+
+        // A refresh the guard accepts does not use up the override
+        provider.override_deletion_guard();
+        provider.update_provider_data().await.unwrap();
+        provider.update_provider_data().await.unwrap();
+        assert!(policy_ids(&provider.policy_set.read().await.clone()).is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn overlay_is_merged_into_every_published_policy_set() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            get_policy_event(OTHER_POLICY_ID, OTHER_STATIC_POLICY),
+        ]);
+        let options = ProviderOptionsBuilder::default()
+            .overlay(PolicyOverlay::from_str(OVERLAY).unwrap())
+            .build()
+            .unwrap();
+        let provider = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            client,
+            options,
+        )
+        .unwrap();
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()),
+            vec![OVERLAY_POLICY_ID, POLICY_ID]
+        );
+
+        provider.update_provider_data().await.unwrap();
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()),
+            vec![OVERLAY_POLICY_ID, POLICY_ID, OTHER_POLICY_ID]
+        );
+        let history = provider.history().await;
+        assert_eq!(
+            policy_ids(&history[1].policy_set()),
+            vec![POLICY_ID, OTHER_POLICY_ID]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn set_overlay_replaces_the_overlay_while_frozen() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+        ]);
+        let provider = PolicySetProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+        provider.freeze().await;
+
+        provider
+            .set_overlay(PolicyOverlay::from_str(OVERLAY).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(provider.overlay().await.len(), 1);
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()),
+            vec![OVERLAY_POLICY_ID, POLICY_ID]
+        );
+
+        provider.set_overlay(PolicyOverlay::empty()).await.unwrap();
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()),
+            vec![POLICY_ID]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn overlay_update_task_applies_sent_overlays() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+        ]);
+        let provider =
+            Arc::new(PolicySetProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap());
+        let (sender, receiver) = watch::channel(PolicyOverlay::empty());
+        let handle = overlay_update_task(provider.clone(), receiver);
+
+        sender
+            .send(PolicyOverlay::from_str(OVERLAY).unwrap())
+            .unwrap();
+        drop(sender);
+        handle.await.unwrap();
+
+        assert_eq!(provider.overlay().await.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn slice_by_request_returns_policies_matching_the_request() {
+        let client = build_client(vec![
+            list_templates_event(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            get_policy_event(OTHER_POLICY_ID, OTHER_STATIC_POLICY),
+        ]);
+        let options = ProviderOptionsBuilder::default()
+            .slice_by_request(true)
+            .build()
+            .unwrap();
+        let provider = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            client,
+            options,
+        )
+        .unwrap();
+        let request = Request::new(
+            EntityUid::from_str(r#"User::"bob""#).unwrap(),
+            EntityUid::from_str(r#"Action::"view""#).unwrap(),
+            EntityUid::from_str(r#"Photo::"beach""#).unwrap(),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+
+        let policy_set = provider.get_policy_set(&request).await.unwrap();
+        assert_eq!(policy_ids(&policy_set), vec![OTHER_POLICY_ID]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn principal_loading_links_and_caches_the_request_principal_policies() {
+        let policy_selector = PolicySelector::from(POLICY_STORE_ID.to_string());
+        let template_id = TemplateId("template-1".to_string());
+        let linked_policy_id = "linked-bob";
+        let mut linked_policy = build_get_policy_response(
+            &policy_id::PolicyId(linked_policy_id.to_string()),
+            &policy_selector,
+            "TEMPLATE_LINKED",
+            build_entity_identifier("User", "bob"),
+            build_entity_identifier("Photo", "photo"),
+            PolicyDefinitionDetailRaw::TemplateLinked(TemplateLinkedPolicyDefinitionDetailRaw {
+                policy_template_id: Some(template_id.to_string()),
+                principal: Some(build_entity_identifier("User", "bob")),
+                resource: None,
+            }),
+        );
+        linked_policy.last_updated_date = Some(LAST_UPDATED_DATE.to_string());
+        let client = build_client(vec![
+            build_event(
+                &ListPolicyTemplatesRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    next_token: None,
+                    max_results: 1,
+                },
+                &ListPolicyTemplatesResponse {
+                    next_token: None,
+                    policy_templates: Some(vec![build_policy_template(
+                        &policy_selector,
+                        &template_id,
+                        "viewers",
+                    )]),
+                },
+                StatusCode::OK,
+            ),
+            build_event(
+                &GetPolicyTemplateRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    policy_template_id: template_id.to_string(),
+                },
+                &build_get_policy_template_response(
+                    &policy_selector,
+                    &template_id,
+                    "viewers",
+                    "permit(principal == ?principal, action, resource);",
+                ),
+                StatusCode::OK,
+            ),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            list_policies_event(&[linked_policy_id]),
+            build_event(
+                &GetPolicyRequest {
+                    policy_id: linked_policy_id.to_string(),
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                },
+                &linked_policy,
+                StatusCode::OK,
+            ),
+        ]);
+        let options = ProviderOptionsBuilder::default()
+            .principal_loading(PrincipalLoading::default())
+            .build()
+            .unwrap();
+        let provider = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            client,
+            options,
+        )
+        .unwrap();
+        let request = Request::new(
+            EntityUid::from_str(r#"User::"bob""#).unwrap(),
+            EntityUid::from_str(r#"Action::"view""#).unwrap(),
+            EntityUid::from_str(r#"Photo::"beach""#).unwrap(),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+
+        let (first, second) = tokio::join!(
+            provider.get_policy_set(&request),
+            provider.get_policy_set(&request)
+        );
+        for policy_set in [first.unwrap(), second.unwrap()] {
+            assert_eq!(policy_ids(&policy_set), vec![linked_policy_id, POLICY_ID]);
         }
-        info!("Updated Policy Set Provider");
-        Ok(())
+        let policy_set = provider.get_policy_set(&request).await.unwrap();
+        assert_eq!(policy_ids(&policy_set), vec![linked_policy_id, POLICY_ID]);
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()),
+            vec![POLICY_ID]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn principal_loading_lists_the_principal_policies_again_for_a_new_generation() {
+        let policy_selector = PolicySelector::from(POLICY_STORE_ID.to_string());
+        let template_id = TemplateId("template-1".to_string());
+        let linked_policy_id = "linked-bob";
+        let mut linked_policy = build_get_policy_response(
+            &policy_id::PolicyId(linked_policy_id.to_string()),
+            &policy_selector,
+            "TEMPLATE_LINKED",
+            build_entity_identifier("User", "bob"),
+            build_entity_identifier("Photo", "photo"),
+            PolicyDefinitionDetailRaw::TemplateLinked(TemplateLinkedPolicyDefinitionDetailRaw {
+                policy_template_id: Some(template_id.to_string()),
+                principal: Some(build_entity_identifier("User", "bob")),
+                resource: None,
+            }),
+        );
+        linked_policy.last_updated_date = Some(LAST_UPDATED_DATE.to_string());
+        let list_templates = || {
+            build_event(
+                &ListPolicyTemplatesRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    next_token: None,
+                    max_results: 1,
+                },
+                &ListPolicyTemplatesResponse {
+                    next_token: None,
+                    policy_templates: Some(vec![build_policy_template(
+                        &policy_selector,
+                        &template_id,
+                        "viewers",
+                    )]),
+                },
+                StatusCode::OK,
+            )
+        };
+        let get_template = || {
+            build_event(
+                &GetPolicyTemplateRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    policy_template_id: template_id.to_string(),
+                },
+                &build_get_policy_template_response(
+                    &policy_selector,
+                    &template_id,
+                    "viewers",
+                    "permit(principal == ?principal, action, resource);",
+                ),
+                StatusCode::OK,
+            )
+        };
+        let client = build_client(vec![
+            list_templates(),
+            get_template(),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            list_policies_event(&[linked_policy_id]),
+            build_event(
+                &GetPolicyRequest {
+                    policy_id: linked_policy_id.to_string(),
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                },
+                &linked_policy,
+                StatusCode::OK,
+            ),
+            list_templates(),
+            get_template(),
+            list_policies_event(&[POLICY_ID, OTHER_POLICY_ID]),
+            get_policy_event(OTHER_POLICY_ID, OTHER_STATIC_POLICY),
+            list_policies_event(&[]),
+        ]);
+        let options = ProviderOptionsBuilder::default()
+            .principal_loading(PrincipalLoading::default())
+            .build()
+            .unwrap();
+        let provider = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            client,
+            options,
+        )
+        .unwrap();
+        let request = Request::new(
+            EntityUid::from_str(r#"User::"bob""#).unwrap(),
+            EntityUid::from_str(r#"Action::"view""#).unwrap(),
+            EntityUid::from_str(r#"Photo::"beach""#).unwrap(),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+
+        let policy_set = provider.get_policy_set(&request).await.unwrap();
+        assert_eq!(policy_ids(&policy_set), vec![linked_policy_id, POLICY_ID]);
+
+        provider.update_provider_data().await.unwrap();
+        let policy_set = provider.get_policy_set(&request).await.unwrap();
+        assert_eq!(policy_ids(&policy_set), vec![POLICY_ID, OTHER_POLICY_ID]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn principal_loading_eagerly_loads_group_template_policies() {
+        let policy_selector = PolicySelector::from(POLICY_STORE_ID.to_string());
+        let template_id = TemplateId("template-1".to_string());
+        let linked_policy_id = "linked-blocked";
+        let mut linked_policy = build_get_policy_response(
+            &policy_id::PolicyId(linked_policy_id.to_string()),
+            &policy_selector,
+            "TEMPLATE_LINKED",
+            build_entity_identifier("Group", "blocked"),
+            build_entity_identifier("Photo", "photo"),
+            PolicyDefinitionDetailRaw::TemplateLinked(TemplateLinkedPolicyDefinitionDetailRaw {
+                policy_template_id: Some(template_id.to_string()),
+                principal: Some(build_entity_identifier("Group", "blocked")),
+                resource: None,
+            }),
+        );
+        linked_policy.last_updated_date = Some(LAST_UPDATED_DATE.to_string());
+        let client = build_client(vec![
+            build_event(
+                &ListPolicyTemplatesRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    next_token: None,
+                    max_results: 1,
+                },
+                &ListPolicyTemplatesResponse {
+                    next_token: None,
+                    policy_templates: Some(vec![build_policy_template(
+                        &policy_selector,
+                        &template_id,
+                        "blocked",
+                    )]),
+                },
+                StatusCode::OK,
+            ),
+            build_event(
+                &GetPolicyTemplateRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    policy_template_id: template_id.to_string(),
+                },
+                &build_get_policy_template_response(
+                    &policy_selector,
+                    &template_id,
+                    "blocked",
+                    "forbid(principal in ?principal, action, resource);",
+                ),
+                StatusCode::OK,
+            ),
+            list_policies_event(&[POLICY_ID]),
+            get_policy_event(POLICY_ID, STATIC_POLICY),
+            list_policies_event(&[linked_policy_id]),
+            build_event(
+                &GetPolicyRequest {
+                    policy_id: linked_policy_id.to_string(),
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                },
+                &linked_policy,
+                StatusCode::OK,
+            ),
+            list_policies_event(&[]),
+        ]);
+        let options = ProviderOptionsBuilder::default()
+            .principal_loading(PrincipalLoading::default())
+            .build()
+            .unwrap();
+        let provider = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            client,
+            options,
+        )
+        .unwrap();
+        let request = Request::new(
+            EntityUid::from_str(r#"User::"bob""#).unwrap(),
+            EntityUid::from_str(r#"Action::"view""#).unwrap(),
+            EntityUid::from_str(r#"Photo::"beach""#).unwrap(),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            policy_ids(&provider.policy_set.read().await.clone()),
+            vec![linked_policy_id, POLICY_ID]
+        );
+        let policy_set = provider.get_policy_set(&request).await.unwrap();
+        assert_eq!(policy_ids(&policy_set), vec![linked_policy_id, POLICY_ID]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn principal_loading_cannot_be_combined_with_slicing() {
+        let options = ProviderOptionsBuilder::default()
+            .principal_loading(PrincipalLoading::default())
+            .slice_by_request(true)
+            .build()
+            .unwrap();
+        let result = PolicySetProvider::from_client_with_options(
+            POLICY_STORE_ID.to_string(),
+            None,
+            build_client(vec![]),
+            options,
+        );
+        assert!(matches!(result, Err(ProviderError::Configuration(..))));
     }
 }
@@ -0,0 +1,329 @@
+//! Slices a `PolicySet` down to the policies that could apply to an authorization `Request`.
+//!
+//! Policies are indexed by the scope constraints of their parsed Cedar text, not by the
+//! `PolicyItem` metadata returned by Amazon Verified Permissions: their action, and their
+//! principal and resource entity type or UID. Template-linked policies are indexed by their
+//! template scope with the linked entities.
+//!
+//! Scope constraints that depend on the entity hierarchy, such as `principal in Group::"admins"`,
+//! cannot be resolved from the `Request` alone so these policies are always part of the slice.
+//! Likewise `action in [..]` is treated as matching any action, even when the list only names
+//! actions, since an action group may contain the request action.
+use std::collections::HashMap;
+
+use cedar_policy::{
+    ActionConstraint, EntityTypeName, EntityUid, Policy, PolicyId, PolicySet, PolicySetError,
+    PrincipalConstraint, Request, ResourceConstraint,
+};
+
+/// The principal or resource scope of an indexed policy.
+#[derive(Debug, Clone)]
+enum EntityScope {
+    /// Matches any entity, or entities in a hierarchy unknown to the slicer
+    Any,
+    /// Matches a single entity
+    Eq(EntityUid),
+    /// Matches entities of a type
+    Is(EntityTypeName),
+}
+
+impl EntityScope {
+    fn matches(&self, entity: Option<&EntityUid>) -> bool {
+        match (self, entity) {
+            (Self::Any, _) | (_, None) => true,
+            (Self::Eq(uid), Some(entity)) => uid == entity,
+            (Self::Is(entity_type), Some(entity)) => entity_type == entity.type_name(),
+        }
+    }
+}
+
+impl From<PrincipalConstraint> for EntityScope {
+    fn from(value: PrincipalConstraint) -> Self {
+        match value {
+            PrincipalConstraint::Any | PrincipalConstraint::In(_) => Self::Any,
+            PrincipalConstraint::Eq(uid) => Self::Eq(uid),
+            PrincipalConstraint::Is(entity_type) | PrincipalConstraint::IsIn(entity_type, _) => {
+                Self::Is(entity_type)
+            }
+        }
+    }
+}
+
+impl From<ResourceConstraint> for EntityScope {
+    fn from(value: ResourceConstraint) -> Self {
+        match value {
+            ResourceConstraint::Any | ResourceConstraint::In(_) => Self::Any,
+            ResourceConstraint::Eq(uid) => Self::Eq(uid),
+            ResourceConstraint::Is(entity_type) | ResourceConstraint::IsIn(entity_type, _) => {
+                Self::Is(entity_type)
+            }
+        }
+    }
+}
+
+/// A policy along with its indexed principal and resource scope.
+#[derive(Debug, Clone)]
+struct IndexedPolicy {
+    policy: Policy,
+    principal: EntityScope,
+    resource: EntityScope,
+}
+
+impl IndexedPolicy {
+    fn matches(&self, request: &Request) -> bool {
+        self.principal.matches(request.principal()) && self.resource.matches(request.resource())
+    }
+}
+
+/// An index of the policies of a `PolicySet` by action, principal and resource scope.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+///
+/// use avp_local_agent::public::policy_set_slicer::PolicySetSlicer;
+/// use cedar_policy::{Context, EntityUid, PolicySet, Request};
+///
+/// let policy_set = PolicySet::from_str(
+///     r#"
+///     permit(principal == User::"alice", action == Action::"view", resource);
+///     permit(principal == User::"bob", action == Action::"view", resource);
+///     "#,
+/// )
+/// .unwrap();
+/// let slicer = PolicySetSlicer::new(&policy_set);
+///
+/// let request = Request::new(
+///     EntityUid::from_str(r#"User::"alice""#).unwrap(),
+///     EntityUid::from_str(r#"Action::"view""#).unwrap(),
+///     EntityUid::from_str(r#"Photo::"beach""#).unwrap(),
+///     Context::empty(),
+///     None,
+/// )
+/// .unwrap();
+/// assert_eq!(slicer.slice(&request).unwrap().policies().count(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PolicySetSlicer {
+    /// Policies constrained to a single action
+    by_action: HashMap<EntityUid, Vec<IndexedPolicy>>,
+    /// Policies that may apply to any action
+    any_action: Vec<IndexedPolicy>,
+    /// Templates of the indexed template-linked policies
+    policy_set: PolicySet,
+}
+
+impl PolicySetSlicer {
+    /// Indexes the static and template-linked policies of `policy_set`.
+    pub fn new(policy_set: &PolicySet) -> Self {
+        let mut by_action: HashMap<EntityUid, Vec<IndexedPolicy>> = HashMap::new();
+        let mut any_action = Vec::new();
+        for policy in policy_set.policies() {
+            let indexed = IndexedPolicy {
+                policy: policy.clone(),
+                principal: EntityScope::from(policy.principal_constraint()),
+                resource: EntityScope::from(policy.resource_constraint()),
+            };
+            match policy.action_constraint() {
+                ActionConstraint::Eq(action) => by_action.entry(action).or_default().push(indexed),
+                ActionConstraint::Any | ActionConstraint::In(_) => any_action.push(indexed),
+            }
+        }
+
+        Self {
+            by_action,
+            any_action,
+            policy_set: policy_set.clone(),
+        }
+    }
+
+    /// Returns the number of indexed policies.
+    pub fn len(&self) -> usize {
+        self.any_action.len() + self.by_action.values().map(Vec::len).sum::<usize>()
+    }
+
+    /// Returns true if no policies are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Builds a `PolicySet` with the policies that could apply to the `request`. Unknown request
+    /// variables match every policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `PolicySetError` if a template-linked policy cannot be linked in the slice.
+    #[allow(clippy::result_large_err)]
+    pub fn slice(&self, request: &Request) -> Result<PolicySet, PolicySetError> {
+        let candidates: Box<dyn Iterator<Item = &IndexedPolicy>> = match request.action() {
+            Some(action) => Box::new(
+                self.any_action
+                    .iter()
+                    .chain(self.by_action.get(action).into_iter().flatten()),
+            ),
+            None => Box::new(
+                self.any_action
+                    .iter()
+                    .chain(self.by_action.values().flatten()),
+            ),
+        };
+
+        let mut slice = PolicySet::new();
+        for indexed in candidates.filter(|indexed| indexed.matches(request)) {
+            self.add(&mut slice, &indexed.policy)?;
+        }
+        Ok(slice)
+    }
+
+    /// Adds a static policy, or links a template-linked policy along with its template.
+    #[allow(clippy::result_large_err)]
+    fn add(&self, slice: &mut PolicySet, policy: &Policy) -> Result<(), PolicySetError> {
+        let (Some(template_id), Some(links)) = (policy.template_id(), policy.template_links())
+        else {
+            return slice.add(policy.clone());
+        };
+
+        if slice.template(template_id).is_none() {
+            if let Some(template) = self.policy_set.template(template_id) {
+                slice.add_template(template.clone())?;
+            }
+        }
+        slice.link(template_id.clone(), PolicyId::new(policy.id()), links)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use cedar_policy::{Context, EntityUid, PolicyId, PolicySet, Request, SlotId};
+
+    use crate::public::policy_set_slicer::PolicySetSlicer;
+
+    const POLICIES: &str = r#"
+        @id("alice-view")
+        permit(principal == User::"alice", action == Action::"view", resource);
+        @id("bob-view")
+        permit(principal == User::"bob", action == Action::"view", resource);
+        @id("alice-edit")
+        permit(principal == User::"alice", action == Action::"edit", resource);
+        @id("admins")
+        permit(principal in Group::"admins", action, resource);
+        @id("photos")
+        forbid(principal, action in [Action::"delete"], resource is Photo);
+        @id("albums")
+        permit(principal, action == Action::"view", resource is Album);
+    "#;
+
+    fn uid(src: &str) -> EntityUid {
+        EntityUid::from_str(src).unwrap()
+    }
+
+    fn request(principal: &str, action: &str, resource: &str) -> Request {
+        Request::new(
+            uid(principal),
+            uid(action),
+            uid(resource),
+            Context::empty(),
+            None,
+        )
+        .unwrap()
+    }
+
+    fn policy_set() -> PolicySet {
+        let policy_set = PolicySet::from_str(POLICIES).unwrap();
+        let mut renamed = PolicySet::new();
+        for policy in policy_set.policies() {
+            let id = policy.annotation("id").unwrap().to_string();
+            renamed.add(policy.new_id(PolicyId::new(id))).unwrap();
+        }
+        renamed
+    }
+
+    fn slice_ids(slicer: &PolicySetSlicer, request: &Request) -> Vec<String> {
+        let mut ids = slicer
+            .slice(request)
+            .unwrap()
+            .policies()
+            .map(|policy| policy.id().to_string())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn slice_keeps_policies_matching_the_action_principal_and_resource() {
+        let slicer = PolicySetSlicer::new(&policy_set());
+        assert_eq!(slicer.len(), 6);
+
+        assert_eq!(
+            slice_ids(
+                &slicer,
+                &request(r#"User::"alice""#, r#"Action::"view""#, r#"Photo::"beach""#)
+            ),
+            vec!["admins", "alice-view", "photos"]
+        );
+        assert_eq!(
+            slice_ids(
+                &slicer,
+                &request(r#"User::"bob""#, r#"Action::"view""#, r#"Album::"trip""#)
+            ),
+            vec!["admins", "albums", "bob-view"]
+        );
+    }
+
+    #[test]
+    fn slice_keeps_policies_depending_on_the_entity_hierarchy() {
+        let slicer = PolicySetSlicer::new(&policy_set());
+
+        assert_eq!(
+            slice_ids(
+                &slicer,
+                &request(
+                    r#"User::"carol""#,
+                    r#"Action::"delete""#,
+                    r#"Photo::"beach""#
+                )
+            ),
+            vec!["admins", "photos"]
+        );
+        assert_eq!(
+            slice_ids(
+                &slicer,
+                &request(r#"User::"carol""#, r#"Action::"share""#, r#"Album::"trip""#)
+            ),
+            vec!["admins"]
+        );
+    }
+
+    #[test]
+    fn slice_links_template_linked_policies() {
+        let mut policy_set = PolicySet::from_str(
+            r#"@id("viewers") permit(principal == ?principal, action == Action::"view", resource);"#,
+        )
+        .unwrap();
+        let template_id = policy_set.templates().next().unwrap().id().clone();
+        for user in ["alice", "bob"] {
+            policy_set
+                .link(
+                    template_id.clone(),
+                    PolicyId::new(user),
+                    [(SlotId::principal(), uid(&format!(r#"User::"{user}""#)))].into(),
+                )
+                .unwrap();
+        }
+        let slicer = PolicySetSlicer::new(&policy_set);
+
+        let slice = slicer
+            .slice(&request(
+                r#"User::"bob""#,
+                r#"Action::"view""#,
+                r#"Photo::"beach""#,
+            ))
+            .unwrap();
+        assert_eq!(slice.policies().count(), 1);
+        assert!(slice.policy(&PolicyId::new("bob")).is_some());
+        assert!(slice.template(&template_id).is_some());
+    }
+}
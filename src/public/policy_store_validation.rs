@@ -0,0 +1,311 @@
+//! Applies the validation settings of an Amazon Verified Permissions policy store locally.
+//!
+//! The validation mode of the policy store is read with `GetPolicyStore`. In `STRICT` stores the
+//! served `PolicySet` and incoming requests are validated against the policy store schema, like
+//! Amazon Verified Permissions does when policies are created and requests are evaluated. The
+//! `Validator` is built once per refreshed schema and shared by every validation.
+use std::sync::Arc;
+
+use aws_sdk_verifiedpermissions::operation::get_policy_store::GetPolicyStoreOutput;
+use aws_sdk_verifiedpermissions::primitives::DateTime;
+use aws_sdk_verifiedpermissions::types::ValidationMode;
+use aws_sdk_verifiedpermissions::Client;
+use cedar_local_agent::public::UpdateProviderData;
+use cedar_policy::{PolicySet, Request, RequestValidationError, Schema, Validator};
+use thiserror::Error;
+use tokio::runtime::Handle;
+use tokio::sync::RwLock;
+use tokio::task;
+use tracing::{debug, info, instrument};
+
+use crate::private::sources::policy_store::error::PolicyStoreException;
+use crate::private::sources::policy_store::reader::GetPolicyStore;
+use crate::private::sources::Read;
+use crate::private::types::policy_selector::PolicySelector;
+
+use super::schema_provider::{ProviderError as SchemaProviderError, SchemaProvider};
+
+/// `PolicyStoreValidationError` occurs when the policy store settings cannot be read, or when
+/// policies or requests fail the validation of a `STRICT` policy store.
+#[derive(Error, Debug)]
+pub enum PolicyStoreValidationError {
+    /// Cannot retrieve the policy store from Amazon Verified Permissions
+    #[error("Failed to get the policy store from Amazon Verified Permissions: {0}")]
+    RetrieveException(#[from] PolicyStoreException),
+    /// Cannot retrieve the schema of the policy store
+    #[error("Failed to get the schema of the policy store: {0}")]
+    Schema(#[from] SchemaProviderError),
+    /// Cannot refresh the schema of the policy store
+    #[error("Failed to refresh the schema of the policy store: {0}")]
+    SchemaRefresh(String),
+    /// The policy store is `STRICT` but has no schema to validate against
+    #[error("The policy store validation mode is STRICT but the policy store has no schema")]
+    MissingSchema,
+    /// Policies do not conform to the schema
+    #[error("The policies do not conform to the policy store schema: {0}")]
+    InvalidPolicySet(String),
+    /// The request does not conform to the schema
+    #[error("The request does not conform to the policy store schema: {0}")]
+    InvalidRequest(#[source] Box<RequestValidationError>),
+}
+
+impl From<RequestValidationError> for PolicyStoreValidationError {
+    fn from(value: RequestValidationError) -> Self {
+        Self::InvalidRequest(Box::new(value))
+    }
+}
+
+/// The settings of an Amazon Verified Permissions policy store.
+///
+/// The supported SDK does not return the description of the policy store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyStoreSettings {
+    /// The id of the policy store
+    pub policy_store_id: String,
+    /// The ARN of the policy store
+    pub arn: String,
+    /// The validation mode of the policy store, `OFF` if it has no validation settings
+    pub validation_mode: ValidationMode,
+    /// When the policy store was created
+    pub created_date: DateTime,
+    /// When the policy store was last updated
+    pub last_updated_date: DateTime,
+}
+
+impl PolicyStoreSettings {
+    /// Returns true if policies and requests are validated against the schema.
+    pub fn is_strict(&self) -> bool {
+        self.validation_mode == ValidationMode::Strict
+    }
+}
+
+impl From<GetPolicyStoreOutput> for PolicyStoreSettings {
+    fn from(output: GetPolicyStoreOutput) -> Self {
+        Self {
+            policy_store_id: output.policy_store_id,
+            arn: output.arn,
+            validation_mode: output
+                .validation_settings
+                .map_or(ValidationMode::Off, |settings| settings.mode),
+            created_date: output.created_date,
+            last_updated_date: output.last_updated_date,
+        }
+    }
+}
+
+/// A `Validator` and the schema it was built from.
+#[derive(Debug)]
+struct SchemaValidator {
+    /// The schema returned by the `SchemaProvider`, to detect schema changes
+    schema: Arc<Schema>,
+    /// Validates policies against the schema
+    validator: Validator,
+}
+
+/// The settings read by the last refresh and the validator of the schema of `STRICT` stores.
+#[derive(Debug)]
+struct ValidationState {
+    /// The settings of the policy store
+    settings: PolicyStoreSettings,
+    /// The validator of the schema, `None` unless the store is `STRICT` and has a schema
+    validator: Option<Arc<SchemaValidator>>,
+}
+
+/// Reads the validation settings of a policy store and validates policies and requests in
+/// `STRICT` stores.
+#[derive(Debug)]
+pub(crate) struct PolicyStoreValidator {
+    /// The policy store to read the settings of
+    policy_selector: PolicySelector,
+    /// Reads the policy store from Amazon Verified Permissions
+    reader: GetPolicyStore,
+    /// The schema policies and requests are validated against
+    schema_provider: SchemaProvider,
+    /// The settings and validator of the last refresh
+    state: RwLock<ValidationState>,
+}
+
+impl PolicyStoreValidator {
+    /// Reads the settings and the schema of the policy store.
+    #[instrument(skip(client), err(Debug))]
+    pub(crate) fn from_client(
+        policy_selector: PolicySelector,
+        client: Client,
+    ) -> Result<Self, PolicyStoreValidationError> {
+        let reader = GetPolicyStore::from(client.clone());
+        let policy_selector_clone = policy_selector.clone();
+        let settings = task::block_in_place(|| {
+            Handle::current().block_on(async { reader.read(policy_selector_clone).await })
+        })?;
+        let schema_provider =
+            SchemaProvider::from_client(policy_selector.id().to_string(), client)?;
+
+        let settings = PolicyStoreSettings::from(settings);
+        info!(
+            "Read policy store settings: validation_mode={:?}",
+            settings.validation_mode
+        );
+        let validator = task::block_in_place(|| {
+            Handle::current().block_on(validator(&schema_provider, &settings, None))
+        });
+        Ok(Self {
+            policy_selector,
+            reader,
+            schema_provider,
+            state: RwLock::new(ValidationState {
+                settings,
+                validator,
+            }),
+        })
+    }
+
+    /// Returns the settings read by the last refresh.
+    pub(crate) async fn settings(&self) -> PolicyStoreSettings {
+        self.state.read().await.settings.clone()
+    }
+
+    /// Returns the validator of a `STRICT` policy store, `None` if the store is not `STRICT`.
+    async fn strict_validator(
+        &self,
+    ) -> Result<Option<Arc<SchemaValidator>>, PolicyStoreValidationError> {
+        let state = self.state.read().await;
+        if !state.settings.is_strict() {
+            return Ok(None);
+        }
+        state
+            .validator
+            .clone()
+            .map(Some)
+            .ok_or(PolicyStoreValidationError::MissingSchema)
+    }
+
+    /// Reads the settings of the policy store again, and refreshes the schema of `STRICT` stores.
+    #[instrument(skip(self), err(Debug))]
+    pub(crate) async fn refresh(&self) -> Result<(), PolicyStoreValidationError> {
+        let settings =
+            PolicyStoreSettings::from(self.reader.read(self.policy_selector.clone()).await?);
+        if settings.is_strict() {
+            self.schema_provider
+                .update_provider_data()
+                .await
+                .map_err(|e| PolicyStoreValidationError::SchemaRefresh(e.to_string()))?;
+        }
+        debug!(
+            "Refreshed policy store settings: validation_mode={:?}",
+            settings.validation_mode
+        );
+        let previous = self.state.read().await.validator.clone();
+        let validator = validator(&self.schema_provider, &settings, previous).await;
+        *self.state.write().await = ValidationState {
+            settings,
+            validator,
+        };
+        Ok(())
+    }
+
+    /// Validates the policies of a `STRICT` policy store against its schema.
+    pub(crate) async fn validate_policy_set(
+        &self,
+        policy_set: &PolicySet,
+    ) -> Result<(), PolicyStoreValidationError> {
+        let Some(validator) = self.strict_validator().await? else {
+            return Ok(());
+        };
+
+        let result = validator
+            .validator
+            .validate(policy_set, cedar_policy::ValidationMode::Strict);
+        if result.validation_passed() {
+            return Ok(());
+        }
+        Err(PolicyStoreValidationError::InvalidPolicySet(
+            result
+                .validation_errors()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; "),
+        ))
+    }
+
+    /// Validates a request to a `STRICT` policy store against its schema. Requests with unknown
+    /// principals, actions, resources or context are not validated.
+    pub(crate) async fn validate_request(
+        &self,
+        request: &Request,
+    ) -> Result<(), PolicyStoreValidationError> {
+        let Some(validator) = self.strict_validator().await? else {
+            return Ok(());
+        };
+
+        if let (Some(principal), Some(action), Some(resource), Some(context)) = (
+            request.principal(),
+            request.action(),
+            request.resource(),
+            request.context(),
+        ) {
+            Request::new(
+                principal.clone(),
+                action.clone(),
+                resource.clone(),
+                context.clone(),
+                Some(&validator.schema),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the validator of the schema of a `STRICT` policy store, reusing `previous` while the
+/// `SchemaProvider` returns the same schema.
+async fn validator(
+    schema_provider: &SchemaProvider,
+    settings: &PolicyStoreSettings,
+    previous: Option<Arc<SchemaValidator>>,
+) -> Option<Arc<SchemaValidator>> {
+    if !settings.is_strict() {
+        return None;
+    }
+    let schema = schema_provider.get_schema().await?;
+    match previous {
+        Some(previous) if Arc::ptr_eq(&previous.schema, &schema) => Some(previous),
+        _ => Some(Arc::new(SchemaValidator {
+            validator: Validator::new(schema.as_ref().clone()),
+            schema,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aws_sdk_verifiedpermissions::operation::get_policy_store::GetPolicyStoreOutput;
+    use aws_sdk_verifiedpermissions::primitives::DateTime;
+    use aws_sdk_verifiedpermissions::types::{ValidationMode, ValidationSettings};
+
+    use super::PolicyStoreSettings;
+
+    fn build_output(validation_settings: Option<ValidationSettings>) -> GetPolicyStoreOutput {
+        GetPolicyStoreOutput::builder()
+            .policy_store_id("ps-1")
+            .arn("arn:aws:verifiedpermissions::123456789012:policy-store/ps-1")
+            .set_validation_settings(validation_settings)
+            .created_date(DateTime::from_secs(0))
+            .last_updated_date(DateTime::from_secs(0))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn policy_store_settings_read_the_validation_mode() {
+        let strict = PolicyStoreSettings::from(build_output(Some(
+            ValidationSettings::builder()
+                .mode(ValidationMode::Strict)
+                .build()
+                .unwrap(),
+        )));
+        assert!(strict.is_strict());
+
+        let unset = PolicyStoreSettings::from(build_output(None));
+        assert_eq!(unset.validation_mode, ValidationMode::Off);
+        assert!(!unset.is_strict());
+    }
+}
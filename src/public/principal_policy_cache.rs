@@ -0,0 +1,285 @@
+//! Lazily loads the template-linked policies of each principal for policy stores too large to be
+//! loaded entirely.
+//!
+//! When enabled, the `PolicySetProvider` eagerly loads the templates, the static policies and the
+//! policies linked to templates whose principal scope is not `principal == ?principal`. On
+//! `get_policy_set` the policies linked to the request principal are listed with a
+//! `ListPolicies` principal filter and cached in a bounded LRU with a time to live, then linked
+//! into a copy of the served `PolicySet`. Concurrent requests for the same principal share a
+//! single listing. Only the policies of each principal are cached, so memory grows with
+//! `capacity` times the policies of a principal rather than the size of the served `PolicySet`,
+//! at the cost of copying the served `PolicySet` on each request.
+//!
+//! Cached policies are listed again once another generation of the `PolicySet` is served.
+//! Changes to the policies of a principal alone do not publish a new generation and are
+//! picked up when their entry expires.
+//!
+//! Only the principal of the `Request` is known to `get_policy_set`, not the groups it belongs to.
+//! Policies linked with `principal in ?principal`, typically to a group, and policies of templates
+//! without a principal slot are therefore always loaded with the static policies, so that a
+//! template-linked `forbid` on a group of the principal is never missed. Their linked policies
+//! are listed per template on each refresh.
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use aws_sdk_verifiedpermissions::types::PolicyType;
+use aws_sdk_verifiedpermissions::Client;
+use cedar_policy::{EntityUid, PolicyId, PolicySet, TemplatePrincipalConstraint};
+use lru::LruCache;
+use tokio::sync::Mutex;
+use tracing::{debug, instrument};
+
+use crate::private::sources::policy::core::{PolicySource, VerifiedPermissionsPolicySource};
+use crate::private::translator::avp_to_cedar::{Policy, Template};
+use crate::private::types::policy_id;
+use crate::private::types::policy_selector::PolicySelector;
+use crate::private::types::policy_store_filter::PolicyStoreFilter;
+use crate::private::types::template_id::TemplateId;
+
+use super::policy_set_provider::{add_policy, ProviderError};
+
+/// The default number of principals whose policies are cached.
+pub const DEFAULT_PRINCIPAL_CACHE_CAPACITY: usize = 1024;
+
+/// The default time the policies of a principal are cached before being listed again.
+// `Duration::from_mins` is only const since Rust 1.91.
+#[allow(clippy::duration_suboptimal_units)]
+pub const DEFAULT_PRINCIPAL_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Settings for loading the template-linked policies of each principal on demand.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use avp_local_agent::public::principal_policy_cache::PrincipalLoading;
+///
+/// let loading = PrincipalLoading {
+///     capacity: 10_000,
+///     ttl: Duration::from_secs(60),
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrincipalLoading {
+    /// The maximum number of principals whose template-linked policies are cached
+    pub capacity: usize,
+    /// The time the policies of a principal are cached before being listed again
+    pub ttl: Duration,
+}
+
+impl Default for PrincipalLoading {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_PRINCIPAL_CACHE_CAPACITY,
+            ttl: DEFAULT_PRINCIPAL_CACHE_TTL,
+        }
+    }
+}
+
+/// The cached template-linked policies of a principal.
+#[derive(Debug)]
+struct PrincipalPolicies {
+    /// When the policies were listed from Amazon Verified Permissions
+    loaded_at: Instant,
+    /// The generation of the `PolicySet` served when the policies were listed
+    generation: u64,
+    /// The template-linked policies of the principal
+    policies: Arc<HashMap<policy_id::PolicyId, Policy>>,
+}
+
+/// Returns true if the policies linked to `template` can be selected by their principal alone.
+fn is_principal_template(template: &Template) -> bool {
+    matches!(
+        template.0.principal_constraint(),
+        TemplatePrincipalConstraint::Eq(None)
+    )
+}
+
+/// A bounded LRU of the template-linked policies of each principal.
+#[derive(Debug)]
+pub(crate) struct PrincipalPolicyCache {
+    /// Lists and reads the policies of a principal, shared by concurrent loads
+    policy_source: VerifiedPermissionsPolicySource,
+    /// Caches the policies linked to each template loaded with the static policies
+    template_sources: Mutex<HashMap<TemplateId, VerifiedPermissionsPolicySource>>,
+    /// Client used to build the sources of the templates loaded with the static policies
+    client: Client,
+    /// Selects the template-linked policies of the policy store
+    policy_selector: PolicySelector,
+    /// The time entries are fresh
+    ttl: Duration,
+    /// Cached policies by principal
+    entries: Mutex<LruCache<EntityUid, PrincipalPolicies>>,
+    /// The principals being loaded, so that concurrent misses wait for a single load
+    loading: Mutex<HashMap<EntityUid, Arc<Mutex<()>>>>,
+}
+
+impl PrincipalPolicyCache {
+    /// Creates a cache loading the template-linked policies selected by `policy_selector`.
+    pub(crate) fn new(
+        client: Client,
+        policy_selector: &PolicySelector,
+        loading: PrincipalLoading,
+    ) -> Result<Self, ProviderError> {
+        let filter = PolicyStoreFilter::narrow_policy_type(
+            policy_selector.filters().cloned(),
+            PolicyType::TemplateLinked,
+        )?;
+        let capacity = NonZeroUsize::new(loading.capacity).unwrap_or(NonZeroUsize::MIN);
+        Ok(Self {
+            policy_source: VerifiedPermissionsPolicySource::from(client.clone()),
+            template_sources: Mutex::new(HashMap::new()),
+            client,
+            policy_selector: policy_selector.clone().with_filters(Some(filter)),
+            ttl: loading.ttl,
+            entries: Mutex::new(LruCache::new(capacity)),
+            loading: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Fetches the policies linked to the `templates` that cannot be selected by principal, to
+    /// be served with the static policies.
+    #[instrument(skip_all, err(Debug))]
+    pub(crate) async fn fetch_shared(
+        &self,
+        templates: &HashMap<TemplateId, Template>,
+    ) -> Result<HashMap<policy_id::PolicyId, Policy>, ProviderError> {
+        let mut template_sources = self.template_sources.lock().await;
+        template_sources.retain(|template_id, _| {
+            templates
+                .get(template_id)
+                .is_some_and(|template| !is_principal_template(template))
+        });
+
+        let mut policies = HashMap::new();
+        for (template_id, template) in templates {
+            if is_principal_template(template) {
+                continue;
+            }
+            let filter = PolicyStoreFilter::narrow_policy_template(
+                self.policy_selector.filters().cloned(),
+                &template_id.to_string(),
+            )?;
+            let policy_selector = self.policy_selector.clone().with_filters(Some(filter));
+            let template_policies = template_sources
+                .entry(template_id.clone())
+                .or_insert_with(|| VerifiedPermissionsPolicySource::from(self.client.clone()))
+                .fetch(policy_selector)
+                .await?;
+            debug!(
+                "Loaded Template Policies: template_id={template_id}, policies={}",
+                template_policies.len()
+            );
+            policies.extend(template_policies);
+        }
+        drop(template_sources);
+        Ok(policies)
+    }
+
+    /// Returns the template-linked policies of `principal`, listing them from Amazon Verified
+    /// Permissions when they are not cached, have expired or were loaded while another
+    /// `generation` was served.
+    #[instrument(skip(self), err(Debug))]
+    pub(crate) async fn get(
+        &self,
+        principal: &EntityUid,
+        generation: u64,
+    ) -> Result<Arc<HashMap<policy_id::PolicyId, Policy>>, ProviderError> {
+        if let Some(policies) = self.cached(principal, generation).await {
+            return Ok(policies);
+        }
+
+        let load_lock = self
+            .loading
+            .lock()
+            .await
+            .entry(principal.clone())
+            .or_default()
+            .clone();
+        let load_guard = load_lock.lock().await;
+        let result = self.cached_or_load(principal, generation).await;
+        drop(load_guard);
+        self.loading.lock().await.remove(principal);
+        result
+    }
+
+    /// Returns the cached policies of `principal`, which a concurrent load may have added, or
+    /// loads them.
+    async fn cached_or_load(
+        &self,
+        principal: &EntityUid,
+        generation: u64,
+    ) -> Result<Arc<HashMap<policy_id::PolicyId, Policy>>, ProviderError> {
+        if let Some(policies) = self.cached(principal, generation).await {
+            return Ok(policies);
+        }
+        let policies = Arc::new(self.load(principal).await?);
+        self.entries.lock().await.put(
+            principal.clone(),
+            PrincipalPolicies {
+                loaded_at: Instant::now(),
+                generation,
+                policies: policies.clone(),
+            },
+        );
+        Ok(policies)
+    }
+
+    /// Returns the cached policies of `principal` if they are fresh and were loaded while
+    /// `generation` was served.
+    async fn cached(
+        &self,
+        principal: &EntityUid,
+        generation: u64,
+    ) -> Option<Arc<HashMap<policy_id::PolicyId, Policy>>> {
+        self.entries
+            .lock()
+            .await
+            .get(principal)
+            .filter(|entry| entry.generation == generation && entry.loaded_at.elapsed() < self.ttl)
+            .map(|entry| entry.policies.clone())
+    }
+
+    /// Drops the cached policies of every principal.
+    pub(crate) async fn clear(&self) {
+        self.entries.lock().await.clear();
+    }
+
+    /// Lists and reads the template-linked policies of `principal`.
+    async fn load(
+        &self,
+        principal: &EntityUid,
+    ) -> Result<HashMap<policy_id::PolicyId, Policy>, ProviderError> {
+        let filter = PolicyStoreFilter::narrow_principal(
+            self.policy_selector.filters().cloned(),
+            &principal.type_name().to_string(),
+            principal.id().unescaped(),
+        )?;
+        let policy_selector = self.policy_selector.clone().with_filters(Some(filter));
+
+        let policies = self.policy_source.load(policy_selector).await?;
+        debug!(
+            "Loaded Principal Policies: principal={principal}, policies={}",
+            policies.len()
+        );
+        Ok(policies)
+    }
+}
+
+/// Returns `served` with the template-linked `policies` of a principal linked, skipping those
+/// already served.
+pub(crate) fn link(
+    served: &PolicySet,
+    policies: &HashMap<policy_id::PolicyId, Policy>,
+) -> Result<PolicySet, ProviderError> {
+    let mut policy_set = served.clone();
+    for (policy_id, policy) in policies {
+        if served.policy(&PolicyId::new(&policy_id.0)).is_none() {
+            add_policy(&mut policy_set, policy.clone())?;
+        }
+    }
+    Ok(policy_set)
+}
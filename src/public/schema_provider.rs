@@ -0,0 +1,285 @@
+//! Provides the Amazon Verified Permissions schema of a policy store as a Cedar `Schema`.
+//!
+//! The `SchemaProvider` keeps the latest schema of the policy store and refreshes it through
+//! `UpdateProviderData`, like the other providers. An `EntityProvider` built with
+//! `EntityProvider::from_schema_provider` derives its action entities from the same fetch.
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::Client;
+use cedar_local_agent::public::{UpdateProviderData, UpdateProviderDataError};
+use cedar_policy::Schema;
+use derive_builder::Builder;
+use thiserror::Error;
+use tokio::runtime::Handle;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task;
+use tracing::{debug, error, info, instrument};
+
+use crate::private::sources::schema::core::{CachedSchema, VerifiedPermissionsSchemaSource};
+use crate::private::sources::schema::error::SchemaException;
+use crate::private::sources::Read;
+use crate::private::translator::avp_to_cedar::Schema as TranslatorSchema;
+use crate::private::translator::error::TranslatorException;
+use crate::private::types::policy_selector::PolicySelector;
+
+/// `ProviderError` can occur during construction or refresh of the `SchemaProvider`
+#[derive(Error, Debug)]
+pub enum ProviderError {
+    /// Configuration error
+    #[error("The configuration didn't build: {0}")]
+    Configuration(String),
+    /// Cannot retrieve the schema from Amazon Verified Permissions
+    #[error("Failed to get the schema from Amazon Verified Permissions: {0}")]
+    RetrieveException(#[from] SchemaException),
+    /// The schema cannot be parsed in its JSON or Cedar format
+    #[error("The schema failed to be parsed: {0}")]
+    ParseSchema(#[from] TranslatorException),
+}
+
+impl From<ConfigBuilderError> for ProviderError {
+    fn from(value: ConfigBuilderError) -> Self {
+        Self::Configuration(value.to_string())
+    }
+}
+
+/// Configuration for the Schema Provider used internally for constructing the `SchemaProvider`
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+struct Config {
+    /// Retrieves Schema from Amazon Verified Permissions
+    pub schema_source: VerifiedPermissionsSchemaSource,
+    /// The policy store id to retrieve the schema for
+    pub policy_selector: PolicySelector,
+}
+
+/// `SchemaProvider` keeps the latest schema of an Amazon Verified Permissions policy store.
+#[derive(Debug)]
+pub struct SchemaProvider {
+    /// The policy store the schema is retrieved for
+    policy_selector: PolicySelector,
+    /// Schema Source
+    schema_source: Arc<Mutex<VerifiedPermissionsSchemaSource>>,
+    /// The schema can be updated through a background thread, `None` if the policy store has no
+    /// schema.
+    schema: RwLock<Option<Arc<Schema>>>,
+}
+
+impl SchemaProvider {
+    /// The `from_client` provides a useful method for building the Amazon Verified Permissions
+    /// `SchemaProvider`.
+    ///
+    /// # Errors
+    ///
+    /// Can error if the builder is incorrect or if the `new` constructor fails to gather the
+    /// applicable data on initialization.
+    #[instrument(skip(verified_permissions_client), err(Debug))]
+    pub fn from_client(
+        policy_store_id: String,
+        verified_permissions_client: Client,
+    ) -> Result<Self, ProviderError> {
+        Self::new(
+            ConfigBuilder::default()
+                .policy_selector(PolicySelector::from(policy_store_id))
+                .schema_source(VerifiedPermissionsSchemaSource::from(
+                    verified_permissions_client,
+                ))
+                .build()?,
+        )
+    }
+
+    #[instrument(skip(config), err(Debug))]
+    fn new(config: Config) -> Result<Self, ProviderError> {
+        let Config {
+            policy_selector,
+            schema_source,
+        } = config;
+
+        let schema_source = Arc::new(Mutex::new(schema_source));
+        let schema_source_ref = schema_source.clone();
+        let policy_selector_clone = policy_selector.clone();
+        let schema = task::block_in_place(move || {
+            Handle::current()
+                .block_on(async move { fetch(&schema_source_ref, policy_selector_clone).await })
+        })
+        .inspect_err(|error| error!("Failed to get the schema on initialization: {error:?}"))?;
+
+        Ok(Self {
+            policy_selector,
+            schema_source,
+            schema: RwLock::new(schema),
+        })
+    }
+
+    /// Returns the latest schema of the policy store, or `None` if the policy store has no schema.
+    pub async fn get_schema(&self) -> Option<Arc<Schema>> {
+        self.schema.read().await.clone()
+    }
+}
+
+/// Fetches the schema of the policy store, `None` if the policy store has no schema. The schema
+/// is only parsed when its `last_updated_date` differs from the cached one, otherwise the cached
+/// `Arc` is returned.
+async fn fetch(
+    schema_source: &Mutex<VerifiedPermissionsSchemaSource>,
+    policy_selector: PolicySelector,
+) -> Result<Option<Arc<Schema>>, ProviderError> {
+    let mut schema_source = schema_source.lock().await;
+    let fetch_schema_result = schema_source.reader.read(policy_selector).await;
+
+    let get_schema_output = match fetch_schema_result {
+        Ok(get_schema_output) => get_schema_output,
+        Err(SchemaException::ResourceNotFound(_)) => {
+            schema_source.cache = None;
+            return Ok(None);
+        }
+        Err(error) => return Err(ProviderError::RetrieveException(error)),
+    };
+
+    if let Some(cached) = schema_source
+        .cache
+        .as_ref()
+        .filter(|cached| cached.last_updated_date == get_schema_output.last_updated_date)
+    {
+        debug!(
+            "Schema is unchanged, skipping parsing: last_updated_date={}",
+            cached.last_updated_date
+        );
+        return Ok(Some(cached.schema.clone()));
+    }
+
+    let TranslatorSchema(schema) = TranslatorSchema::try_from(get_schema_output.schema.as_str())?;
+    let schema = Arc::new(schema);
+    schema_source.cache = Some(CachedSchema {
+        last_updated_date: get_schema_output.last_updated_date,
+        schema: schema.clone(),
+    });
+    drop(schema_source);
+    Ok(Some(schema))
+}
+
+#[async_trait]
+impl UpdateProviderData for SchemaProvider {
+    #[instrument(skip(self), err(Debug))]
+    async fn update_provider_data(&self) -> Result<(), UpdateProviderDataError> {
+        let schema = fetch(&self.schema_source, self.policy_selector.clone())
+            .await
+            .map_err(|e| UpdateProviderDataError::General(Box::new(e)))?;
+
+        {
+            let mut schema_data = self.schema.write().await;
+            *schema_data = schema;
+        }
+        info!("Updated Schema Provider");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::sync::Arc;
+
+    use aws_smithy_runtime::client::http::test_util::ReplayEvent;
+    use cedar_local_agent::public::UpdateProviderData;
+    use serde::{Deserialize, Serialize};
+
+    use crate::private::sources::test::{build_client, build_event, StatusCode};
+
+    use super::SchemaProvider;
+
+    pub const POLICY_STORE_ID: &str = "ps-1";
+    pub const SCHEMA: &str = "
+        entity User = { name: String };
+        entity Photo;
+        action view appliesTo { principal: [User], resource: [Photo] };
+    ";
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct GetSchemaRequest {
+        #[serde(rename = "policyStoreId")]
+        policy_store_id: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct GetSchemaResponse {
+        #[serde(rename = "createdDate")]
+        created_date: String,
+        #[serde(rename = "lastUpdatedDate")]
+        last_updated_date: String,
+        #[serde(rename = "policyStoreId")]
+        policy_store_id: String,
+        schema: String,
+    }
+
+    /// Builds a `GetSchema` event of `POLICY_STORE_ID` returning the given schema.
+    pub fn build_schema_event(schema: &str, last_updated_date: &str) -> ReplayEvent {
+        let request = GetSchemaRequest {
+            policy_store_id: POLICY_STORE_ID.to_string(),
+        };
+        let response = GetSchemaResponse {
+            created_date: "2024-01-01T00:00:00Z".to_string(),
+            last_updated_date: last_updated_date.to_string(),
+            policy_store_id: POLICY_STORE_ID.to_string(),
+            schema: schema.to_string(),
+        };
+        build_event(&request, &response, StatusCode::OK)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn from_client_loads_the_schema() {
+        let client = build_client(vec![build_schema_event(SCHEMA, "2024-01-01T00:00:00Z")]);
+
+        let provider = SchemaProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+
+        let schema = provider.get_schema().await.unwrap();
+        assert_eq!(schema.entity_types().count(), 2);
+        assert_eq!(schema.actions().count(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn update_provider_data_replaces_the_schema() {
+        let client = build_client(vec![
+            build_schema_event(SCHEMA, "2024-01-01T00:00:00Z"),
+            build_schema_event(
+                &format!(
+                    "{SCHEMA} action edit appliesTo {{ principal: [User], resource: [Photo] }};"
+                ),
+                "2024-01-02T00:00:00Z",
+            ),
+        ]);
+        let provider = SchemaProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+
+        provider.update_provider_data().await.unwrap();
+
+        assert_eq!(provider.get_schema().await.unwrap().actions().count(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn update_provider_data_reuses_the_schema_when_unchanged() {
+        let client = build_client(vec![
+            build_schema_event(SCHEMA, "2024-01-01T00:00:00Z"),
+            build_schema_event("not a schema", "2024-01-01T00:00:00Z"),
+        ]);
+        let provider = SchemaProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+        let before = provider.get_schema().await.unwrap();
+
+        provider.update_provider_data().await.unwrap();
+
+        assert!(Arc::ptr_eq(&before, &provider.get_schema().await.unwrap()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn from_client_loads_a_json_schema() {
+        let client = build_client(vec![build_schema_event(
+            r#"{"": {"entityTypes": {"User": {}}, "actions": {"view": {}}}}"#,
+            "2024-01-01T00:00:00Z",
+        )]);
+
+        let provider = SchemaProvider::from_client(POLICY_STORE_ID.to_string(), client).unwrap();
+
+        let schema = provider.get_schema().await.unwrap();
+        assert_eq!(schema.entity_types().count(), 1);
+        assert_eq!(schema.actions().count(), 1);
+    }
+}
@@ -0,0 +1,764 @@
+//! An HTTP server evaluating Amazon Verified Permissions `IsAuthorized` and `BatchIsAuthorized`
+//! requests locally, so that applications written in any language can use the agent as a
+//! sidecar.
+//!
+//! The sidecar serves the providers of one policy store over TCP or a Unix domain socket:
+//!
+//! * `POST /` accepts the AWS JSON 1.0 protocol and dispatches on the `X-Amz-Target` header, so
+//!   an AWS SDK client can use the sidecar by overriding its endpoint URL.
+//! * `POST /is-authorized` and `POST /batch-is-authorized` accept the same JSON bodies without the
+//!   header.
+//! * `GET /health` reports the state of the `PolicySetProvider` as JSON.
+//! * `GET /metrics` reports request, decision and refresh counters in the Prometheus text format.
+//! * `POST /admin/refresh` refreshes the providers, protected by a bearer token. The endpoint is
+//!   disabled unless a token is configured.
+//!
+//! Requests for another policy store are rejected with a `ResourceNotFoundException`.
+use std::fmt::{Debug, Write};
+use std::io;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use aws_sdk_verifiedpermissions::operation::batch_is_authorized::BatchIsAuthorizedInput;
+use aws_sdk_verifiedpermissions::operation::is_authorized::IsAuthorizedInput;
+use aws_sdk_verifiedpermissions::types::Decision;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::serve::Listener;
+use axum::Router;
+use cedar_local_agent::public::simple::{Authorizer, AuthorizerConfigBuilder};
+use derive_builder::Builder;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tracing::{debug, info, instrument, warn};
+
+use crate::private::translator::avp_json::{
+    BatchIsAuthorizedRequestJson, BatchIsAuthorizedResponseJson, IsAuthorizedRequestJson,
+    IsAuthorizedResponseJson,
+};
+
+use super::agent_config::PolicyStoreProviders;
+use super::avp_authorizer::{AvpAuthorizerError, AvpLocalAuthorizer};
+use super::policy_set_provider::PolicySetProvider;
+
+/// The `X-Amz-Target` of the `IsAuthorized` operation.
+const IS_AUTHORIZED_TARGET: &str = "VerifiedPermissions.IsAuthorized";
+/// The `X-Amz-Target` of the `BatchIsAuthorized` operation.
+const BATCH_IS_AUTHORIZED_TARGET: &str = "VerifiedPermissions.BatchIsAuthorized";
+/// The content type of AWS JSON 1.0 responses.
+const AWS_JSON_CONTENT_TYPE: &str = "application/x-amz-json-1.0";
+
+/// `SidecarError` can occur when building or running a `Sidecar`.
+#[derive(Error, Debug)]
+pub enum SidecarError {
+    /// The authorizer of the sidecar cannot be built
+    #[error("Invalid sidecar configuration: {0}")]
+    Configuration(String),
+    /// The listener cannot be bound or the server failed
+    #[error("The sidecar server failed: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// An error returned to the caller in the Amazon Verified Permissions error shape.
+#[derive(Error, Debug)]
+enum ServiceException {
+    /// The request is malformed or cannot be translated to a Cedar request
+    #[error("{0}")]
+    Validation(String),
+    /// The request targets a policy store the sidecar does not serve
+    #[error("Policy store {0} is not served by this sidecar")]
+    ResourceNotFound(String),
+    /// The `X-Amz-Target` is not a supported operation
+    #[error("Unsupported operation: {0}")]
+    UnknownOperation(String),
+    /// The admin token is missing or invalid
+    #[error("The admin token is missing or invalid")]
+    AccessDenied,
+    /// The providers failed to evaluate or refresh
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl From<AvpAuthorizerError> for ServiceException {
+    fn from(error: AvpAuthorizerError) -> Self {
+        match error {
+            AvpAuthorizerError::Translate(_)
+            | AvpAuthorizerError::Token(_)
+            | AvpAuthorizerError::NoTokenVerifier => Self::Validation(error.to_string()),
+            AvpAuthorizerError::Authorizer(_) | AvpAuthorizerError::Output(_) => {
+                Self::Internal(error.to_string())
+            }
+        }
+    }
+}
+
+impl IntoResponse for ServiceException {
+    fn into_response(self) -> Response {
+        let message = self.to_string();
+        let (status, error_type, body) = match &self {
+            Self::Validation(_) => (
+                StatusCode::BAD_REQUEST,
+                "ValidationException",
+                json!({ "message": message }),
+            ),
+            Self::ResourceNotFound(policy_store_id) => (
+                StatusCode::NOT_FOUND,
+                "ResourceNotFoundException",
+                json!({
+                    "message": message,
+                    "resourceId": policy_store_id,
+                    "resourceType": "POLICY_STORE",
+                }),
+            ),
+            Self::UnknownOperation(_) => (
+                StatusCode::BAD_REQUEST,
+                "UnknownOperationException",
+                json!({ "message": message }),
+            ),
+            Self::AccessDenied => (
+                StatusCode::FORBIDDEN,
+                "AccessDeniedException",
+                json!({ "message": message }),
+            ),
+            Self::Internal(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "InternalServerException",
+                json!({ "message": message }),
+            ),
+        };
+        let mut body = body;
+        body["__type"] = json!(error_type);
+
+        let mut response = aws_json(status, &body);
+        response
+            .headers_mut()
+            .insert("x-amzn-errortype", HeaderValue::from_static(error_type));
+        response
+    }
+}
+
+/// Serializes `body` as an AWS JSON 1.0 response.
+fn aws_json<T: Serialize>(status: StatusCode, body: &T) -> Response {
+    match serde_json::to_vec(body) {
+        Ok(body) => (
+            status,
+            [(header::CONTENT_TYPE, AWS_JSON_CONTENT_TYPE)],
+            body,
+        )
+            .into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+    }
+}
+
+/// Options of a `Sidecar`.
+///
+/// ```
+/// use avp_local_agent::public::sidecar::SidecarOptionsBuilder;
+///
+/// let options = SidecarOptionsBuilder::default()
+///     .admin_token("change-me")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Debug, Clone, Default)]
+#[builder(pattern = "owned")]
+pub struct SidecarOptions {
+    /// The bearer token required by `POST /admin/refresh`, the endpoint is disabled if not set
+    #[builder(default, setter(into, strip_option))]
+    pub admin_token: Option<String>,
+}
+
+/// The counters reported by `GET /metrics`.
+#[derive(Debug, Default)]
+struct Metrics {
+    /// Authorization requests received, a batch counts once
+    requests: AtomicU64,
+    /// Evaluated requests that were allowed
+    allowed: AtomicU64,
+    /// Evaluated requests that were denied
+    denied: AtomicU64,
+    /// Authorization requests that were rejected or failed
+    errors: AtomicU64,
+    /// Admin refreshes that succeeded
+    refreshes: AtomicU64,
+    /// Admin refreshes that failed
+    refresh_failures: AtomicU64,
+}
+
+impl Metrics {
+    /// Counts an evaluated decision.
+    fn decision(&self, decision: &Decision) {
+        match decision {
+            Decision::Allow => self.allowed.fetch_add(1, Ordering::Relaxed),
+            _ => self.denied.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+}
+
+/// The state shared by the handlers of the sidecar.
+#[derive(Debug)]
+struct SidecarState {
+    /// The providers of the served policy store
+    providers: PolicyStoreProviders,
+    /// Evaluates the requests with the providers
+    authorizer: AvpLocalAuthorizer<PolicySetProvider, PolicyStoreProviders>,
+    /// The options of the sidecar
+    options: SidecarOptions,
+    /// The counters reported by `GET /metrics`
+    metrics: Metrics,
+}
+
+/// An HTTP server evaluating authorization requests against the providers of one policy store.
+///
+/// # Examples
+///
+/// ```no_run
+/// use avp_local_agent::public::agent_config::{AgentConfig, AgentProviders};
+/// use avp_local_agent::public::sidecar::{Sidecar, SidecarOptions};
+///
+/// # async fn example() {
+/// let config = AgentConfig::from_file("agent.toml").unwrap();
+/// let providers = AgentProviders::from_config(&config).await.unwrap();
+/// providers.spawn_refresh_tasks();
+///
+/// let sidecar = Sidecar::new(
+///     providers.policy_stores()[0].clone(),
+///     SidecarOptions::default(),
+/// )
+/// .unwrap();
+/// sidecar.serve_tcp("127.0.0.1:8180").await.unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sidecar {
+    /// The state shared by the handlers
+    state: Arc<SidecarState>,
+}
+
+impl Sidecar {
+    /// Creates a `Sidecar` serving the policy store of `providers`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SidecarError::Configuration` if the authorizer cannot be built.
+    pub fn new(
+        providers: PolicyStoreProviders,
+        options: SidecarOptions,
+    ) -> Result<Self, SidecarError> {
+        let authorizer = Authorizer::new(
+            AuthorizerConfigBuilder::default()
+                .policy_set_provider(providers.policy_set_provider.clone())
+                .entity_provider(Arc::new(providers.clone()))
+                .build()
+                .map_err(|e| SidecarError::Configuration(e.to_string()))?,
+        );
+
+        Ok(Self {
+            state: Arc::new(SidecarState {
+                providers,
+                authorizer: AvpLocalAuthorizer::new(authorizer),
+                options,
+                metrics: Metrics::default(),
+            }),
+        })
+    }
+
+    /// Returns the routes of the sidecar, to be served or nested in another `Router`.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/", post(aws_json_target))
+            .route("/is-authorized", post(is_authorized))
+            .route("/batch-is-authorized", post(batch_is_authorized))
+            .route("/health", get(health))
+            .route("/metrics", get(metrics))
+            .route("/admin/refresh", post(refresh))
+            .with_state(self.state.clone())
+    }
+
+    /// Serves the sidecar on `listener` until the server fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SidecarError::Io` if the server fails.
+    pub async fn serve<L>(&self, listener: L) -> Result<(), SidecarError>
+    where
+        L: Listener,
+        L::Addr: Debug,
+    {
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+
+    /// Binds a TCP listener to `addr` and serves the sidecar on it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SidecarError::Io` if the address cannot be bound or the server fails.
+    #[instrument(skip_all, err(Debug))]
+    pub async fn serve_tcp<A: ToSocketAddrs>(&self, addr: A) -> Result<(), SidecarError> {
+        let listener = TcpListener::bind(addr).await?;
+        info!("Serving the sidecar: address={:?}", listener.local_addr()?);
+        self.serve(listener).await
+    }
+
+    /// Binds a Unix domain socket at `path` and serves the sidecar on it. The socket file must
+    /// not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SidecarError::Io` if the socket cannot be bound or the server fails.
+    #[cfg(unix)]
+    #[instrument(skip_all, err(Debug))]
+    pub async fn serve_unix<P: AsRef<Path>>(&self, path: P) -> Result<(), SidecarError> {
+        let listener = UnixListener::bind(path.as_ref())?;
+        info!("Serving the sidecar: path={}", path.as_ref().display());
+        self.serve(listener).await
+    }
+}
+
+/// Parses the JSON body of a request.
+fn parse<T: DeserializeOwned>(body: &Bytes) -> Result<T, ServiceException> {
+    serde_json::from_slice(body).map_err(|e| ServiceException::Validation(e.to_string()))
+}
+
+/// Rejects requests for a policy store the sidecar does not serve.
+fn check_policy_store(state: &SidecarState, policy_store_id: &str) -> Result<(), ServiceException> {
+    if policy_store_id == state.providers.policy_store_id {
+        Ok(())
+    } else {
+        Err(ServiceException::ResourceNotFound(
+            policy_store_id.to_string(),
+        ))
+    }
+}
+
+/// Dispatches an AWS JSON 1.0 request on its `X-Amz-Target` header.
+async fn aws_json_target(
+    State(state): State<Arc<SidecarState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let target = headers
+        .get("x-amz-target")
+        .and_then(|target| target.to_str().ok())
+        .unwrap_or_default();
+    match target {
+        IS_AUTHORIZED_TARGET => is_authorized(State(state), body).await,
+        BATCH_IS_AUTHORIZED_TARGET => batch_is_authorized(State(state), body).await,
+        _ => {
+            state.metrics.errors.fetch_add(1, Ordering::Relaxed);
+            ServiceException::UnknownOperation(target.to_string()).into_response()
+        }
+    }
+}
+
+/// Evaluates an `IsAuthorized` request.
+async fn is_authorized(State(state): State<Arc<SidecarState>>, body: Bytes) -> Response {
+    state.metrics.requests.fetch_add(1, Ordering::Relaxed);
+    let result = async {
+        let request: IsAuthorizedRequestJson = parse(&body)?;
+        check_policy_store(&state, &request.policy_store_id)?;
+        let input = IsAuthorizedInput::try_from(&request)
+            .map_err(|e| ServiceException::Validation(e.to_string()))?;
+        let output = state.authorizer.is_authorized(&input).await?;
+        state.metrics.decision(output.decision());
+        Ok::<_, ServiceException>(IsAuthorizedResponseJson::from(&output))
+    }
+    .await;
+
+    match result {
+        Ok(response) => aws_json(StatusCode::OK, &response),
+        Err(error) => {
+            debug!("Rejected IsAuthorized request: {error}");
+            state.metrics.errors.fetch_add(1, Ordering::Relaxed);
+            error.into_response()
+        }
+    }
+}
+
+/// Evaluates a `BatchIsAuthorized` request.
+async fn batch_is_authorized(State(state): State<Arc<SidecarState>>, body: Bytes) -> Response {
+    state.metrics.requests.fetch_add(1, Ordering::Relaxed);
+    let result = async {
+        let request: BatchIsAuthorizedRequestJson = parse(&body)?;
+        check_policy_store(&state, &request.policy_store_id)?;
+        let input = BatchIsAuthorizedInput::try_from(&request)
+            .map_err(|e| ServiceException::Validation(e.to_string()))?;
+        let output = state.authorizer.batch_is_authorized(&input).await?;
+        for result in output.results() {
+            state.metrics.decision(result.decision());
+        }
+        Ok::<_, ServiceException>(BatchIsAuthorizedResponseJson::from(&output))
+    }
+    .await;
+
+    match result {
+        Ok(response) => aws_json(StatusCode::OK, &response),
+        Err(error) => {
+            debug!("Rejected BatchIsAuthorized request: {error}");
+            state.metrics.errors.fetch_add(1, Ordering::Relaxed);
+            error.into_response()
+        }
+    }
+}
+
+/// Reports the state of the `PolicySetProvider`.
+async fn health(State(state): State<Arc<SidecarState>>) -> Response {
+    let health = state.providers.policy_set_provider.health().await;
+    let body = json!({
+        "status": "ok",
+        "policyStoreId": state.providers.policy_store_id,
+        "generation": health.generation,
+        "frozen": health.frozen,
+        "policies": health.policies,
+        "templates": health.templates,
+    });
+    (StatusCode::OK, axum::Json(body)).into_response()
+}
+
+/// Reports the counters and the served `PolicySet` in the Prometheus text format.
+async fn metrics(State(state): State<Arc<SidecarState>>) -> Response {
+    let health = state.providers.policy_set_provider.health().await;
+    let metrics = &state.metrics;
+    let counters = [
+        (
+            "avp_local_agent_requests_total",
+            "counter",
+            "Authorization requests received.",
+            vec![("", metrics.requests.load(Ordering::Relaxed))],
+        ),
+        (
+            "avp_local_agent_decisions_total",
+            "counter",
+            "Evaluated authorization decisions.",
+            vec![
+                (
+                    "decision=\"allow\"",
+                    metrics.allowed.load(Ordering::Relaxed),
+                ),
+                ("decision=\"deny\"", metrics.denied.load(Ordering::Relaxed)),
+            ],
+        ),
+        (
+            "avp_local_agent_request_errors_total",
+            "counter",
+            "Authorization requests that were rejected or failed.",
+            vec![("", metrics.errors.load(Ordering::Relaxed))],
+        ),
+        (
+            "avp_local_agent_refreshes_total",
+            "counter",
+            "Admin refreshes of the providers.",
+            vec![
+                (
+                    "result=\"success\"",
+                    metrics.refreshes.load(Ordering::Relaxed),
+                ),
+                (
+                    "result=\"failure\"",
+                    metrics.refresh_failures.load(Ordering::Relaxed),
+                ),
+            ],
+        ),
+        (
+            "avp_local_agent_policies",
+            "gauge",
+            "Policies in the served policy set.",
+            vec![("", health.policies as u64)],
+        ),
+        (
+            "avp_local_agent_policy_set_generation",
+            "gauge",
+            "Generation of the served policy set.",
+            vec![("", health.generation)],
+        ),
+    ];
+
+    let mut body = String::new();
+    for (name, kind, help, samples) in counters {
+        let _ = writeln!(body, "# HELP {name} {help}");
+        let _ = writeln!(body, "# TYPE {name} {kind}");
+        for (labels, value) in samples {
+            if labels.is_empty() {
+                let _ = writeln!(body, "{name} {value}");
+            } else {
+                let _ = writeln!(body, "{name}{{{labels}}} {value}");
+            }
+        }
+    }
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    )
+        .into_response()
+}
+
+/// Refreshes the providers of the policy store.
+async fn refresh(State(state): State<Arc<SidecarState>>, headers: HeaderMap) -> Response {
+    let Some(admin_token) = &state.options.admin_token else {
+        warn!("Rejected admin refresh: no admin token is configured");
+        return ServiceException::AccessDenied.into_response();
+    };
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let authorized =
+        bearer.is_some_and(|bearer| bool::from(bearer.as_bytes().ct_eq(admin_token.as_bytes())));
+    if !authorized {
+        return ServiceException::AccessDenied.into_response();
+    }
+
+    match state.providers.refresh().await {
+        Ok(()) => {
+            state.metrics.refreshes.fetch_add(1, Ordering::Relaxed);
+            let generation = state
+                .providers
+                .policy_set_provider
+                .current_generation()
+                .await;
+            (
+                StatusCode::OK,
+                axum::Json(json!({ "generation": generation })),
+            )
+                .into_response()
+        }
+        Err(error) => {
+            state
+                .metrics
+                .refresh_failures
+                .fetch_add(1, Ordering::Relaxed);
+            ServiceException::Internal(error.to_string()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use aws_credential_types::provider::SharedCredentialsProvider;
+    use aws_credential_types::Credentials;
+    use aws_sdk_verifiedpermissions::types::{
+        ActionIdentifier, BatchIsAuthorizedInputItem, Decision, EntityIdentifier,
+    };
+    use aws_types::region::Region;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    #[cfg(unix)]
+    use tokio::net::{UnixListener, UnixStream};
+
+    use crate::public::client::{verified_permissions_with_options, ClientOptionsBuilder};
+    use crate::public::policy_set_provider::test::{
+        build_policy_store_providers, POLICY_ID, POLICY_STORE_ID, STATIC_POLICY,
+    };
+
+    use super::{Sidecar, SidecarOptions, SidecarOptionsBuilder};
+
+    fn sidecar(options: SidecarOptions) -> Sidecar {
+        Sidecar::new(build_policy_store_providers(STATIC_POLICY), options).unwrap()
+    }
+
+    /// Sends a raw HTTP/1.1 request and returns the status line and the body of the response.
+    async fn send<S>(mut stream: S, request: &str) -> (String, String)
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+
+    fn entity(entity_type: &str, entity_id: &str) -> EntityIdentifier {
+        EntityIdentifier::builder()
+            .entity_type(entity_type)
+            .entity_id(entity_id)
+            .build()
+            .unwrap()
+    }
+
+    fn action() -> ActionIdentifier {
+        ActionIdentifier::builder()
+            .action_type("Action")
+            .action_id("view")
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn sdk_client_is_authorized_against_the_sidecar() {
+        let sidecar = sidecar(SidecarOptions::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { sidecar.serve(listener).await });
+
+        let client = verified_permissions_with_options(
+            Region::new("us-east-1"),
+            SharedCredentialsProvider::new(Credentials::for_tests()),
+            &ClientOptionsBuilder::default()
+                .endpoint_url(format!("http://{addr}"))
+                .max_attempts(1)
+                .build()
+                .unwrap(),
+        );
+
+        let output = client
+            .is_authorized()
+            .policy_store_id(POLICY_STORE_ID)
+            .principal(entity("User", "alice"))
+            .action(action())
+            .resource(entity("Photo", "beach"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(output.decision(), &Decision::Allow);
+        assert_eq!(output.determining_policies()[0].policy_id(), POLICY_ID);
+
+        let output = client
+            .batch_is_authorized()
+            .policy_store_id(POLICY_STORE_ID)
+            .requests(
+                BatchIsAuthorizedInputItem::builder()
+                    .principal(entity("User", "alice"))
+                    .action(action())
+                    .resource(entity("Photo", "beach"))
+                    .build(),
+            )
+            .requests(
+                BatchIsAuthorizedInputItem::builder()
+                    .principal(entity("User", "bob"))
+                    .action(action())
+                    .resource(entity("Photo", "beach"))
+                    .build(),
+            )
+            .send()
+            .await
+            .unwrap();
+        let results = output.results();
+        assert_eq!(results[0].decision(), &Decision::Allow);
+        assert_eq!(results[1].decision(), &Decision::Deny);
+        assert_eq!(
+            results[1]
+                .request()
+                .unwrap()
+                .principal()
+                .unwrap()
+                .entity_id(),
+            "bob"
+        );
+
+        let error = client
+            .is_authorized()
+            .policy_store_id("other-policy-store")
+            .send()
+            .await
+            .unwrap_err();
+        assert!(error.into_service_error().is_resource_not_found_exception());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn health_and_metrics_over_a_unix_socket() {
+        let sidecar = sidecar(SidecarOptions::default());
+        let path = std::env::temp_dir().join(format!(
+            "avp-local-agent-sidecar-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move { sidecar.serve(listener).await });
+
+        let body = format!(
+            r#"{{"policyStoreId":"{POLICY_STORE_ID}","principal":{{"entityType":"User","entityId":"bob"}},"action":{{"actionType":"Action","actionId":"view"}},"resource":{{"entityType":"Photo","entityId":"beach"}}}}"#
+        );
+        let (status, response) = send(
+            UnixStream::connect(&path).await.unwrap(),
+            &format!(
+                "POST /is-authorized HTTP/1.1\r\nHost: sidecar\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            ),
+        )
+        .await;
+        assert!(status.ends_with("200 OK"));
+        assert_eq!(
+            response,
+            r#"{"decision":"DENY","determiningPolicies":[],"errors":[]}"#
+        );
+
+        let (status, response) = send(
+            UnixStream::connect(&path).await.unwrap(),
+            "GET /health HTTP/1.1\r\nHost: sidecar\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(status.ends_with("200 OK"));
+        let health: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(health["policyStoreId"], POLICY_STORE_ID);
+        assert_eq!(health["policies"], 1);
+
+        let (_, response) = send(
+            UnixStream::connect(&path).await.unwrap(),
+            "GET /metrics HTTP/1.1\r\nHost: sidecar\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        assert!(response.contains("avp_local_agent_requests_total 1\n"));
+        assert!(response.contains("avp_local_agent_decisions_total{decision=\"deny\"} 1\n"));
+        assert!(response.contains("avp_local_agent_policies 1\n"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn admin_refresh_requires_the_token() {
+        let sidecar = sidecar(
+            SidecarOptionsBuilder::default()
+                .admin_token("secret")
+                .build()
+                .unwrap(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { sidecar.serve(listener).await });
+
+        let (status, response) = send(
+            TcpStream::connect(addr).await.unwrap(),
+            "POST /admin/refresh HTTP/1.1\r\nHost: sidecar\r\nConnection: close\r\nAuthorization: Bearer wrong\r\nContent-Length: 0\r\n\r\n",
+        )
+        .await;
+        assert!(status.ends_with("403 Forbidden"));
+        assert!(response.contains("AccessDeniedException"));
+
+        let (status, _) = send(
+            TcpStream::connect(addr).await.unwrap(),
+            "POST / HTTP/1.1\r\nHost: sidecar\r\nConnection: close\r\nX-Amz-Target: VerifiedPermissions.GetPolicy\r\nContent-Length: 2\r\n\r\n{}",
+        )
+        .await;
+        assert!(status.ends_with("400 Bad Request"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn admin_refresh_is_disabled_without_a_token() {
+        let sidecar = sidecar(SidecarOptions::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { sidecar.serve(listener).await });
+
+        let (status, response) = send(
+            TcpStream::connect(addr).await.unwrap(),
+            "POST /admin/refresh HTTP/1.1\r\nHost: sidecar\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        )
+        .await;
+        assert!(status.ends_with("403 Forbidden"));
+        assert!(response.contains("AccessDeniedException"));
+    }
+}
@@ -0,0 +1,697 @@
+//! Routes authorization requests to per-tenant Amazon Verified Permissions policy stores.
+//!
+//! A `TenantRouter` extracts a tenant key from each `Request`, resolves it to the policy store of
+//! the tenant, with an optional filter selecting its policies, and serves the `PolicySet` of a
+//! `PolicySetProvider` created lazily for that store. Concurrent first requests of a tenant share
+//! a single `PolicySetProvider`. Loaded tenants are bounded and evicted when idle.
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::Client;
+use cedar_local_agent::public::{
+    PolicySetProviderError, SimplePolicySetProvider, UpdateProviderData, UpdateProviderDataError,
+};
+use cedar_policy::{EvalResult, PolicySet, Request};
+use derive_builder::Builder;
+use lru::LruCache;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio::task;
+use tracing::{debug, info, instrument, warn};
+
+use super::policy_set_filter::OwnedPolicySetFilter;
+use super::policy_set_provider::{PolicySetProvider, ProviderError, ProviderOptions};
+
+/// The default maximum number of tenants with a loaded `PolicySetProvider`.
+pub const DEFAULT_MAX_TENANTS: usize = 100;
+
+/// The default time a tenant stays loaded without receiving requests.
+// Written in seconds, `Duration::from_mins` needs Rust 1.91.
+#[allow(clippy::duration_suboptimal_units)]
+pub const DEFAULT_TENANT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Extracts a tenant key from a `Request`.
+pub type TenantKeyFn = dyn Fn(&Request) -> Option<String> + Send + Sync;
+
+/// Resolves a tenant key to the policy store of the tenant.
+pub type PolicyStoreResolverFn = dyn Fn(&str) -> Option<TenantPolicyStore> + Send + Sync;
+
+/// `TenantRouterError` occurs when a request cannot be routed to a tenant.
+#[derive(Error, Debug)]
+pub enum TenantRouterError {
+    /// The request does not carry a tenant key
+    #[error("No tenant key could be extracted from the request")]
+    MissingTenantKey,
+    /// The tenant key does not resolve to a policy store
+    #[error("No policy store is configured for tenant: {0}")]
+    UnknownTenant(String),
+    /// The `PolicySetProvider` of the tenant could not be created
+    #[error("Cannot load the policies of tenant {0}: {1}")]
+    Provider(String, ProviderError),
+    /// The task creating the `PolicySetProvider` of the tenant failed
+    #[error("The task loading tenant {0} failed: {1}")]
+    Task(String, task::JoinError),
+    /// The policies of one or more loaded tenants could not be refreshed
+    #[error("Cannot refresh the policies of tenants: {}", tenants(.0))]
+    Refresh(Vec<(String, UpdateProviderDataError)>),
+}
+
+/// Joins the tenants of refresh failures for display.
+fn tenants(failures: &[(String, UpdateProviderDataError)]) -> String {
+    failures
+        .iter()
+        .map(|(tenant, _)| tenant.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// How the tenant key is extracted from a `Request`.
+#[derive(Clone)]
+pub enum TenantKey {
+    /// The first namespace component of the principal entity type, `TenantA` in
+    /// `TenantA::User::"alice"`
+    PrincipalNamespace,
+    /// A string or entity id context attribute
+    ContextAttribute(String),
+    /// A caller-supplied function
+    Custom(Arc<TenantKeyFn>),
+}
+
+impl Debug for TenantKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PrincipalNamespace => f.write_str("PrincipalNamespace"),
+            Self::ContextAttribute(attribute) => {
+                f.debug_tuple("ContextAttribute").field(attribute).finish()
+            }
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl TenantKey {
+    /// Extracts the tenant key from the `request`.
+    pub fn extract(&self, request: &Request) -> Option<String> {
+        match self {
+            Self::PrincipalNamespace => request.principal().and_then(|principal| {
+                principal
+                    .type_name()
+                    .namespace_components()
+                    .next()
+                    .map(ToString::to_string)
+            }),
+            Self::ContextAttribute(attribute) => match request.context()?.get(attribute)? {
+                EvalResult::String(value) => Some(value),
+                EvalResult::EntityUid(uid) => Some(uid.id().unescaped().to_string()),
+                _ => None,
+            },
+            Self::Custom(extract) => extract(request),
+        }
+    }
+}
+
+/// The policy store of a tenant and the filter selecting its policies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantPolicyStore {
+    /// The id of the policy store
+    pub policy_store_id: String,
+    /// Loads only the policies matching the filter
+    pub filter: Option<OwnedPolicySetFilter>,
+}
+
+impl From<String> for TenantPolicyStore {
+    fn from(policy_store_id: String) -> Self {
+        Self {
+            policy_store_id,
+            filter: None,
+        }
+    }
+}
+
+impl From<&str> for TenantPolicyStore {
+    fn from(policy_store_id: &str) -> Self {
+        Self::from(policy_store_id.to_string())
+    }
+}
+
+/// Maps tenant keys to their policy stores.
+#[derive(Clone)]
+pub enum PolicyStoreResolver {
+    /// A fixed map of tenant keys to policy stores
+    Map(HashMap<String, TenantPolicyStore>),
+    /// A caller-supplied function
+    Custom(Arc<PolicyStoreResolverFn>),
+}
+
+impl Debug for PolicyStoreResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Map(map) => f.debug_tuple("Map").field(map).finish(),
+            Self::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl PolicyStoreResolver {
+    /// Returns the policy store of the `tenant`.
+    pub fn resolve(&self, tenant: &str) -> Option<TenantPolicyStore> {
+        match self {
+            Self::Map(map) => map.get(tenant).cloned(),
+            Self::Custom(resolve) => resolve(tenant),
+        }
+    }
+}
+
+/// Optional settings for the `TenantRouter`.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use avp_local_agent::public::tenant_router::TenantRouterOptionsBuilder;
+///
+/// let options = TenantRouterOptionsBuilder::default()
+///     .max_tenants(10)
+///     .idle_timeout(Duration::from_secs(600))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned")]
+pub struct TenantRouterOptions {
+    /// The maximum number of loaded tenants, the least recently used is evicted beyond it
+    #[builder(default = "DEFAULT_MAX_TENANTS")]
+    pub max_tenants: usize,
+    /// Tenants without requests for this long are evicted on the next refresh
+    #[builder(default = "DEFAULT_TENANT_IDLE_TIMEOUT")]
+    pub idle_timeout: Duration,
+    /// The options of each tenant `PolicySetProvider`
+    #[builder(default)]
+    pub provider_options: ProviderOptions,
+}
+
+impl Default for TenantRouterOptions {
+    fn default() -> Self {
+        Self {
+            max_tenants: DEFAULT_MAX_TENANTS,
+            idle_timeout: DEFAULT_TENANT_IDLE_TIMEOUT,
+            provider_options: ProviderOptions::default(),
+        }
+    }
+}
+
+impl From<TenantRouterOptionsBuilderError> for ProviderError {
+    fn from(value: TenantRouterOptionsBuilderError) -> Self {
+        Self::Configuration(value.to_string())
+    }
+}
+
+/// A loaded tenant.
+#[derive(Debug)]
+struct Tenant {
+    provider: Arc<PolicySetProvider>,
+    last_used: Instant,
+}
+
+/// A `SimplePolicySetProvider` serving the policies of the tenant of each request from its own
+/// Amazon Verified Permissions policy store.
+#[derive(Debug)]
+pub struct TenantRouter {
+    /// Client shared by the tenant providers
+    client: Client,
+    /// Extracts the tenant of a request
+    tenant_key: TenantKey,
+    /// Resolves the policy store of a tenant
+    resolver: PolicyStoreResolver,
+    /// Router settings
+    options: TenantRouterOptions,
+    /// Loaded tenants ordered by recent use
+    tenants: Mutex<LruCache<String, Tenant>>,
+    /// The tenants being loaded, so that concurrent first requests wait for a single load
+    loading: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl TenantRouter {
+    /// Creates a router without loading any tenant.
+    pub fn new(
+        client: Client,
+        tenant_key: TenantKey,
+        resolver: PolicyStoreResolver,
+        options: TenantRouterOptions,
+    ) -> Self {
+        let capacity = NonZeroUsize::new(options.max_tenants).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            client,
+            tenant_key,
+            resolver,
+            options,
+            tenants: Mutex::new(LruCache::new(capacity)),
+            loading: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the `PolicySetProvider` of `tenant`, loading it from its policy store if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenantRouterError` if the tenant has no policy store or its policies cannot be
+    /// loaded.
+    #[instrument(skip(self), err(Debug))]
+    pub async fn provider(
+        &self,
+        tenant: &str,
+    ) -> Result<Arc<PolicySetProvider>, TenantRouterError> {
+        if let Some(provider) = self.loaded(tenant).await {
+            return Ok(provider);
+        }
+
+        let load_lock = self
+            .loading
+            .lock()
+            .await
+            .entry(tenant.to_string())
+            .or_default()
+            .clone();
+        let load_guard = load_lock.lock().await;
+        let result = match self.loaded(tenant).await {
+            Some(provider) => Ok(provider),
+            None => self.load(tenant).await,
+        };
+        drop(load_guard);
+        self.loading.lock().await.remove(tenant);
+        result
+    }
+
+    /// Returns the `PolicySetProvider` of `tenant` if it is loaded, marking it as used.
+    async fn loaded(&self, tenant: &str) -> Option<Arc<PolicySetProvider>> {
+        self.tenants.lock().await.get_mut(tenant).map(|loaded| {
+            loaded.last_used = Instant::now();
+            loaded.provider.clone()
+        })
+    }
+
+    /// Creates the `PolicySetProvider` of `tenant` from its policy store.
+    async fn load(&self, tenant: &str) -> Result<Arc<PolicySetProvider>, TenantRouterError> {
+        let policy_store = self
+            .resolver
+            .resolve(tenant)
+            .ok_or_else(|| TenantRouterError::UnknownTenant(tenant.to_string()))?;
+        let client = self.client.clone();
+        let options = self.options.provider_options.clone();
+        let provider = task::spawn_blocking(move || {
+            PolicySetProvider::from_client_with_options(
+                policy_store.policy_store_id,
+                policy_store
+                    .filter
+                    .as_ref()
+                    .map(OwnedPolicySetFilter::to_policy_set_filter),
+                client,
+                options,
+            )
+        })
+        .await
+        .map_err(|e| TenantRouterError::Task(tenant.to_string(), e))?
+        .map_err(|e| TenantRouterError::Provider(tenant.to_string(), e))?;
+
+        let mut tenants = self.tenants.lock().await;
+        let loaded = tenants.get_or_insert_mut(tenant.to_string(), || Tenant {
+            provider: Arc::new(provider),
+            last_used: Instant::now(),
+        });
+        let provider = loaded.provider.clone();
+        drop(tenants);
+        info!("Loaded tenant: tenant={tenant}");
+        Ok(provider)
+    }
+
+    /// Returns the keys of the loaded tenants, most recently used first.
+    pub async fn loaded_tenants(&self) -> Vec<String> {
+        self.tenants
+            .lock()
+            .await
+            .iter()
+            .map(|(tenant, _)| tenant.clone())
+            .collect()
+    }
+
+    /// Evicts the tenants without requests for longer than the idle timeout.
+    async fn evict_idle(&self) {
+        let mut tenants = self.tenants.lock().await;
+        let idle = tenants
+            .iter()
+            .filter(|(_, loaded)| loaded.last_used.elapsed() >= self.options.idle_timeout)
+            .map(|(tenant, _)| tenant.clone())
+            .collect::<Vec<_>>();
+        for tenant in &idle {
+            tenants.pop(tenant);
+        }
+        drop(tenants);
+        if !idle.is_empty() {
+            info!("Evicted idle tenants: tenants={idle:?}");
+        }
+    }
+}
+
+#[async_trait]
+impl SimplePolicySetProvider for TenantRouter {
+    #[instrument(skip_all, err(Debug))]
+    async fn get_policy_set(
+        &self,
+        request: &Request,
+    ) -> Result<Arc<PolicySet>, PolicySetProviderError> {
+        let tenant = self
+            .tenant_key
+            .extract(request)
+            .ok_or(TenantRouterError::MissingTenantKey)
+            .map_err(|e| PolicySetProviderError::General(Box::new(e)))?;
+        debug!("Routing request to tenant: tenant={tenant}");
+        let provider = self
+            .provider(&tenant)
+            .await
+            .map_err(|e| PolicySetProviderError::General(Box::new(e)))?;
+        provider.get_policy_set(request).await
+    }
+}
+
+#[async_trait]
+impl UpdateProviderData for TenantRouter {
+    /// Evicts idle tenants and refreshes the policies of the remaining ones. A tenant failing to
+    /// refresh keeps serving its previous policies and does not prevent the other tenants from
+    /// refreshing; the failures are returned together as a `TenantRouterError::Refresh`.
+    #[instrument(skip(self), err(Debug))]
+    async fn update_provider_data(&self) -> Result<(), UpdateProviderDataError> {
+        self.evict_idle().await;
+
+        let providers = self
+            .tenants
+            .lock()
+            .await
+            .iter()
+            .map(|(tenant, loaded)| (tenant.clone(), loaded.provider.clone()))
+            .collect::<Vec<_>>();
+        let mut failures = Vec::new();
+        for (tenant, provider) in providers {
+            match provider.update_provider_data().await {
+                Ok(()) => debug!("Refreshed tenant: tenant={tenant}"),
+                Err(e) => {
+                    warn!("Failed to refresh tenant: tenant={tenant}, error={e}");
+                    failures.push((tenant, e));
+                }
+            }
+        }
+        if !failures.is_empty() {
+            return Err(UpdateProviderDataError::General(Box::new(
+                TenantRouterError::Refresh(failures),
+            )));
+        }
+        info!("Updated Tenant Router");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use cedar_local_agent::public::{
+        SimplePolicySetProvider, UpdateProviderData, UpdateProviderDataError,
+    };
+    use cedar_policy::{Context, EntityUid, Request};
+
+    use crate::private::sources::policy::core::test::{
+        build_entity_identifier, build_get_policy_response, build_policy_item, GetPolicyRequest,
+        ListPoliciesRequest, ListPoliciesResponse, PolicyDefinitionDetailRaw,
+        StaticPolicyDefinitionDetailRaw,
+    };
+    use crate::private::sources::template::core::test::{
+        ListPolicyTemplatesRequest, ListPolicyTemplatesResponse,
+    };
+    use crate::private::sources::test::{build_client, build_event, StatusCode};
+    use crate::private::types::policy_id::PolicyId;
+    use crate::private::types::policy_selector::PolicySelector;
+    use crate::public::policy_set_filter::OwnedPolicySetFilter;
+    use crate::public::tenant_router::{
+        PolicyStoreResolver, TenantKey, TenantPolicyStore, TenantRouter, TenantRouterError,
+        TenantRouterOptionsBuilder,
+    };
+    use aws_smithy_runtime::client::http::test_util::ReplayEvent;
+
+    const POLICY_STORE_ID: &str = "ps-1";
+    const POLICY_ID: &str = "policy-1";
+
+    fn request(principal: &str, context: Context) -> Request {
+        Request::new(
+            EntityUid::from_str(principal).unwrap(),
+            EntityUid::from_str(r#"Action::"view""#).unwrap(),
+            EntityUid::from_str(r#"Photo::"beach""#).unwrap(),
+            context,
+            None,
+        )
+        .unwrap()
+    }
+
+    fn load_tenant_events() -> Vec<ReplayEvent> {
+        let policy_selector = PolicySelector::from(POLICY_STORE_ID.to_string());
+        let policy_id = PolicyId(POLICY_ID.to_string());
+        vec![
+            build_event(
+                &ListPolicyTemplatesRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    next_token: None,
+                    max_results: 1,
+                },
+                &ListPolicyTemplatesResponse {
+                    next_token: None,
+                    policy_templates: None,
+                },
+                StatusCode::OK,
+            ),
+            build_event(
+                &ListPoliciesRequest {
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                    next_token: None,
+                    max_results: 1,
+                    filter: None,
+                },
+                &ListPoliciesResponse {
+                    policies: Some(vec![build_policy_item(
+                        &policy_id,
+                        &policy_selector,
+                        Some("STATIC".to_string()),
+                        None,
+                        None,
+                        None,
+                    )]),
+                    next_token: None,
+                },
+                StatusCode::OK,
+            ),
+            build_event(
+                &GetPolicyRequest {
+                    policy_id: POLICY_ID.to_string(),
+                    policy_store_id: POLICY_STORE_ID.to_string(),
+                },
+                &build_get_policy_response(
+                    &policy_id,
+                    &policy_selector,
+                    "STATIC",
+                    build_entity_identifier("User", "alice"),
+                    build_entity_identifier("Photo", "beach"),
+                    PolicyDefinitionDetailRaw::Static(StaticPolicyDefinitionDetailRaw {
+                        description: None,
+                        statement: Some("permit(principal, action, resource);".to_string()),
+                    }),
+                ),
+                StatusCode::OK,
+            ),
+        ]
+    }
+
+    fn resolver() -> PolicyStoreResolver {
+        PolicyStoreResolver::Map(HashMap::from([
+            ("TenantA".to_string(), POLICY_STORE_ID.into()),
+            ("TenantB".to_string(), POLICY_STORE_ID.into()),
+        ]))
+    }
+
+    #[test]
+    fn tenant_key_from_principal_namespace() {
+        assert_eq!(
+            TenantKey::PrincipalNamespace
+                .extract(&request(r#"TenantA::User::"alice""#, Context::empty())),
+            Some("TenantA".to_string())
+        );
+        assert_eq!(
+            TenantKey::PrincipalNamespace.extract(&request(r#"User::"alice""#, Context::empty())),
+            None
+        );
+    }
+
+    #[test]
+    fn tenant_key_from_context_attribute() {
+        let context = Context::from_json_str(r#"{"tenant": "TenantB", "count": 1}"#, None).unwrap();
+        let request = request(r#"User::"alice""#, context);
+
+        assert_eq!(
+            TenantKey::ContextAttribute("tenant".to_string()).extract(&request),
+            Some("TenantB".to_string())
+        );
+        assert_eq!(
+            TenantKey::ContextAttribute("count".to_string()).extract(&request),
+            None
+        );
+    }
+
+    #[test]
+    fn tenant_key_from_custom_function() {
+        let tenant_key = TenantKey::Custom(Arc::new(|request: &Request| {
+            request
+                .resource()
+                .map(|resource| resource.id().unescaped().to_string())
+        }));
+        assert_eq!(
+            tenant_key.extract(&request(r#"User::"alice""#, Context::empty())),
+            Some("beach".to_string())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn router_loads_tenants_lazily_and_evicts_the_least_recently_used() {
+        let mut events = load_tenant_events();
+        events.extend(load_tenant_events());
+        let options = TenantRouterOptionsBuilder::default()
+            .max_tenants(1)
+            .build()
+            .unwrap();
+        let router = TenantRouter::new(
+            build_client(events),
+            TenantKey::PrincipalNamespace,
+            resolver(),
+            options,
+        );
+        assert!(router.loaded_tenants().await.is_empty());
+
+        for _ in 0..2 {
+            let policy_set = router
+                .get_policy_set(&request(r#"TenantA::User::"alice""#, Context::empty()))
+                .await
+                .unwrap();
+            assert_eq!(policy_set.policies().count(), 1);
+        }
+        assert_eq!(router.loaded_tenants().await, vec!["TenantA"]);
+
+        router
+            .get_policy_set(&request(r#"TenantB::User::"bob""#, Context::empty()))
+            .await
+            .unwrap();
+        assert_eq!(router.loaded_tenants().await, vec!["TenantB"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn router_rejects_requests_without_a_known_tenant() {
+        let router = TenantRouter::new(
+            build_client(vec![]),
+            TenantKey::PrincipalNamespace,
+            resolver(),
+            TenantRouterOptionsBuilder::default().build().unwrap(),
+        );
+
+        assert!(router
+            .get_policy_set(&request(r#"User::"alice""#, Context::empty()))
+            .await
+            .is_err());
+        assert!(matches!(
+            router.provider("TenantC").await,
+            Err(TenantRouterError::UnknownTenant(tenant)) if tenant == "TenantC"
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn refresh_evicts_idle_tenants() {
+        let options = TenantRouterOptionsBuilder::default()
+            .idle_timeout(Duration::ZERO)
+            .build()
+            .unwrap();
+        let router = TenantRouter::new(
+            build_client(load_tenant_events()),
+            TenantKey::PrincipalNamespace,
+            resolver(),
+            options,
+        );
+        router.provider("TenantA").await.unwrap();
+
+        router.update_provider_data().await.unwrap();
+        assert!(router.loaded_tenants().await.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn concurrent_first_requests_share_one_provider() {
+        let router = TenantRouter::new(
+            build_client(load_tenant_events()),
+            TenantKey::PrincipalNamespace,
+            resolver(),
+            TenantRouterOptionsBuilder::default().build().unwrap(),
+        );
+
+        let (first, second) = tokio::join!(router.provider("TenantA"), router.provider("TenantA"));
+        assert!(Arc::ptr_eq(&first.unwrap(), &second.unwrap()));
+        assert_eq!(router.loaded_tenants().await, vec!["TenantA"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn router_loads_tenants_with_the_resolved_filter() {
+        let resolver = PolicyStoreResolver::Custom(Arc::new(|_: &str| {
+            Some(TenantPolicyStore {
+                policy_store_id: POLICY_STORE_ID.to_string(),
+                filter: Some(OwnedPolicySetFilter::Cli("policyTemplateId=".to_string())),
+            })
+        }));
+        let router = TenantRouter::new(
+            build_client(vec![]),
+            TenantKey::PrincipalNamespace,
+            resolver,
+            TenantRouterOptionsBuilder::default().build().unwrap(),
+        );
+
+        assert!(matches!(
+            router.provider("TenantA").await,
+            Err(TenantRouterError::Provider(tenant, _)) if tenant == "TenantA"
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn refresh_reports_every_failing_tenant() {
+        let mut events = load_tenant_events();
+        events.extend(load_tenant_events());
+        let router = TenantRouter::new(
+            build_client(events),
+            TenantKey::PrincipalNamespace,
+            resolver(),
+            TenantRouterOptionsBuilder::default().build().unwrap(),
+        );
+        router.provider("TenantA").await.unwrap();
+        router.provider("TenantB").await.unwrap();
+
+        let error = router.update_provider_data().await.unwrap_err();
+        let UpdateProviderDataError::General(error) = error;
+        let Some(TenantRouterError::Refresh(failures)) = error.downcast_ref::<TenantRouterError>()
+        else {
+            panic!("unexpected error: {error}");
+        };
+        let mut tenants = failures
+            .iter()
+            .map(|(tenant, _)| tenant.as_str())
+            .collect::<Vec<_>>();
+        tenants.sort_unstable();
+        assert_eq!(tenants, vec!["TenantA", "TenantB"]);
+        assert_eq!(router.loaded_tenants().await.len(), 2);
+    }
+}
@@ -0,0 +1,840 @@
+//! Verifies the identity and access tokens issued for the identity sources of a policy store.
+//!
+//! The principal entity, its group memberships and the context are derived from the token claims
+//! like `IsAuthorizedWithToken` does.
+//! The identity sources are read with `ListIdentitySources` and `GetIdentitySource` and cached
+//! between refreshes. Tokens are validated against the JSON Web Key Set of their issuer, which is
+//! provided by a `JwksFetcher` such as the `FileJwksFetcher` and cached for
+//! `TokenVerifierOptions::jwks_ttl`. A token with an unknown key id refetches the key set at most
+//! once per `TokenVerifierOptions::jwks_refetch_interval`. Tokens must be signed with the `alg` of
+//! their key, or `RS256` for keys without one, whatever algorithm their header claims.
+//!
+//! The supported SDK only describes Amazon Cognito user pool identity sources and has no group
+//! configuration, so other identity sources are skipped and the group entity type is set with
+//! `TokenVerifierOptions::group_entity_type`.
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use aws_sdk_verifiedpermissions::error::BuildError;
+use aws_sdk_verifiedpermissions::operation::get_identity_source::GetIdentitySourceOutput;
+use aws_sdk_verifiedpermissions::types::{AttributeValue, EntityIdentifier, EntityItem};
+use aws_sdk_verifiedpermissions::Client;
+use derive_builder::Builder;
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde_json::{Map, Value};
+use thiserror::Error;
+use tokio::runtime::Handle;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task;
+use tracing::{debug, info, instrument, warn};
+
+use cedar_local_agent::public::{UpdateProviderData, UpdateProviderDataError};
+
+use crate::private::sources::identity_source::core::VerifiedPermissionsIdentitySource;
+use crate::private::sources::identity_source::error::IdentitySourceException;
+use crate::private::types::policy_selector::PolicySelector;
+
+pub use jsonwebtoken::jwk::JwkSet;
+
+/// The default time a fetched JSON Web Key Set is used before being fetched again.
+// The durations are given in seconds, `Duration::from_hours` and `from_mins` need Rust 1.91.
+#[allow(clippy::duration_suboptimal_units)]
+pub const DEFAULT_JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The default minimum time between fetches of a JSON Web Key Set missing the key of a token.
+#[allow(clippy::duration_suboptimal_units)]
+pub const DEFAULT_JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The claim Amazon Cognito lists the groups of a user in.
+const COGNITO_GROUPS_CLAIM: &str = "cognito:groups";
+
+/// `TokenError` can occur when reading the identity sources or verifying a token.
+#[derive(Error, Debug)]
+pub enum TokenError {
+    /// Cannot retrieve the identity sources from Amazon Verified Permissions
+    #[error("Failed to get the identity sources from Amazon Verified Permissions: {0}")]
+    RetrieveException(#[from] IdentitySourceException),
+    /// The identity source is not an Amazon Cognito user pool identity source
+    #[error("The identity source {0} is not a supported Amazon Cognito user pool")]
+    UnsupportedIdentitySource(String),
+    /// The JSON Web Key Set of an issuer cannot be fetched
+    #[error("Failed to fetch the JSON Web Key Set of {0}: {1}")]
+    Jwks(String, String),
+    /// Neither an identity nor an access token was provided
+    #[error("An identity token or an access token is required")]
+    MissingToken,
+    /// No identity source of the policy store issues tokens for the issuer
+    #[error("No identity source of the policy store has the issuer {0}")]
+    UnknownIssuer(String),
+    /// The JSON Web Key Set of the issuer has no key with the key id of the token
+    #[error("The JSON Web Key Set of {0} has no key with the key id {1:?}")]
+    UnknownKey(String, Option<String>),
+    /// The token is malformed, expired or its signature is invalid
+    #[error("The token is invalid: {0}")]
+    InvalidToken(#[from] jsonwebtoken::errors::Error),
+    /// The token is valid but cannot be used with the identity source
+    #[error("The token was rejected: {0}")]
+    Rejected(String),
+    /// The identity and access tokens are not for the same principal
+    #[error("The identity token and access token are for different principals")]
+    PrincipalMismatch,
+    /// The principal entity failed to be built
+    #[error("The principal failed to be built: {0}")]
+    Principal(#[from] BuildError),
+}
+
+/// Fetches the JSON Web Key Set used to verify the tokens of an issuer.
+///
+/// Implement this trait to fetch the keys from the `jwks_uri` of the issuer, or from any other
+/// location.
+#[async_trait]
+pub trait JwksFetcher: Debug + Send + Sync {
+    /// Fetches the JSON Web Key Set of `issuer`.
+    async fn fetch(&self, issuer: &str) -> Result<JwkSet, TokenError>;
+}
+
+/// A `JwksFetcher` reading the JSON Web Key Set of every issuer from a local file.
+#[derive(Debug, Clone)]
+pub struct FileJwksFetcher {
+    /// The path of the JSON Web Key Set file
+    path: PathBuf,
+}
+
+impl FileJwksFetcher {
+    /// Creates a `FileJwksFetcher` reading the JSON Web Key Set at `path` on every fetch.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl JwksFetcher for FileJwksFetcher {
+    #[instrument(skip(self), err(Debug))]
+    async fn fetch(&self, issuer: &str) -> Result<JwkSet, TokenError> {
+        let jwks = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| TokenError::Jwks(issuer.to_string(), e.to_string()))?;
+        serde_json::from_str(&jwks).map_err(|e| TokenError::Jwks(issuer.to_string(), e.to_string()))
+    }
+}
+
+/// Options for mapping tokens to principals.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use avp_local_agent::public::token_verifier::TokenVerifierOptionsBuilder;
+///
+/// let options = TokenVerifierOptionsBuilder::default()
+///     .group_entity_type("MyApp::UserGroup")
+///     .jwks_ttl(Duration::from_secs(600))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned")]
+pub struct TokenVerifierOptions {
+    /// The entity type of the groups of the principal, groups are not mapped when unset
+    #[builder(setter(into, strip_option), default)]
+    pub group_entity_type: Option<String>,
+    /// The time a fetched JSON Web Key Set is used before being fetched again
+    #[builder(default = "DEFAULT_JWKS_TTL")]
+    pub jwks_ttl: Duration,
+    /// The minimum time before a JSON Web Key Set without the key id of a token is fetched again
+    #[builder(default = "DEFAULT_JWKS_REFETCH_INTERVAL")]
+    pub jwks_refetch_interval: Duration,
+}
+
+impl Default for TokenVerifierOptions {
+    fn default() -> Self {
+        Self {
+            group_entity_type: None,
+            jwks_ttl: DEFAULT_JWKS_TTL,
+            jwks_refetch_interval: DEFAULT_JWKS_REFETCH_INTERVAL,
+        }
+    }
+}
+
+/// The principal, group memberships and context derived from verified tokens.
+#[derive(Debug, Clone)]
+pub struct VerifiedToken {
+    /// The principal the tokens were issued to
+    pub principal: EntityIdentifier,
+    /// The principal entity, with the identity token claims as attributes and its groups as
+    /// parents
+    pub entities: Vec<EntityItem>,
+    /// The access token claims
+    pub context: HashMap<String, AttributeValue>,
+}
+
+/// The kind of token, checked against the `token_use` claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenUse {
+    Id,
+    Access,
+}
+
+impl TokenUse {
+    const fn claim(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Access => "access",
+        }
+    }
+
+    /// The claim holding the client id the token was issued to.
+    const fn client_id_claim(self) -> &'static str {
+        match self {
+            Self::Id => "aud",
+            Self::Access => "client_id",
+        }
+    }
+}
+
+/// An Amazon Cognito user pool identity source.
+#[derive(Debug, Clone)]
+struct CognitoIdentitySource {
+    identity_source_id: String,
+    user_pool_id: String,
+    issuer: String,
+    client_ids: Vec<String>,
+    principal_entity_type: String,
+}
+
+impl TryFrom<&GetIdentitySourceOutput> for CognitoIdentitySource {
+    type Error = TokenError;
+
+    fn try_from(output: &GetIdentitySourceOutput) -> Result<Self, Self::Error> {
+        let unsupported =
+            || TokenError::UnsupportedIdentitySource(output.identity_source_id().to_string());
+        let details = output.details().ok_or_else(unsupported)?;
+
+        // arn:aws:cognito-idp:<region>:<account id>:userpool/<user pool id>
+        let arn = details
+            .user_pool_arn()
+            .ok_or_else(unsupported)?
+            .split(':')
+            .collect::<Vec<_>>();
+        let [_, _, "cognito-idp", region, _, resource] = arn.as_slice() else {
+            return Err(unsupported());
+        };
+        let user_pool_id = resource.strip_prefix("userpool/").ok_or_else(unsupported)?;
+
+        Ok(Self {
+            identity_source_id: output.identity_source_id().to_string(),
+            user_pool_id: user_pool_id.to_string(),
+            issuer: format!("https://cognito-idp.{region}.amazonaws.com/{user_pool_id}"),
+            client_ids: details.client_ids().to_vec(),
+            principal_entity_type: output.principal_entity_type().to_string(),
+        })
+    }
+}
+
+/// A JSON Web Key Set and when it was fetched.
+#[derive(Debug)]
+struct CachedJwks {
+    fetched_at: Instant,
+    jwks: JwkSet,
+}
+
+/// Verifies tokens issued for the identity sources of a policy store.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use avp_local_agent::public::token_verifier::{
+///     FileJwksFetcher, TokenVerifier, TokenVerifierOptions,
+/// };
+/// use aws_sdk_verifiedpermissions::Client;
+///
+/// # async fn example(client: Client, identity_token: &str) {
+/// let verifier = TokenVerifier::from_client(
+///     "policy-store-id".to_string(),
+///     client,
+///     Arc::new(FileJwksFetcher::new("jwks.json")),
+///     TokenVerifierOptions::default(),
+/// )
+/// .unwrap();
+///
+/// let verified = verifier.verify(Some(identity_token), None).await.unwrap();
+/// println!("{:?}", verified.principal);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TokenVerifier {
+    /// The policy store of the identity sources
+    policy_selector: PolicySelector,
+    /// Reads and caches the identity sources
+    identity_source: Mutex<VerifiedPermissionsIdentitySource>,
+    /// The supported identity sources by issuer
+    issuers: RwLock<HashMap<String, CognitoIdentitySource>>,
+    /// Fetches the JSON Web Key Set of an issuer
+    jwks_fetcher: Arc<dyn JwksFetcher>,
+    /// The fetched JSON Web Key Sets by issuer
+    jwks: RwLock<HashMap<String, CachedJwks>>,
+    /// Held while fetching a JSON Web Key Set, so that concurrent misses share a single fetch
+    jwks_fetching: Mutex<()>,
+    /// Options for mapping tokens to principals
+    options: TokenVerifierOptions,
+}
+
+impl TokenVerifier {
+    /// Builds a `TokenVerifier` for the identity sources of the policy store.
+    ///
+    /// # Errors
+    ///
+    /// Can error if the identity sources cannot be read on initialization.
+    #[instrument(skip(verified_permissions_client, jwks_fetcher), err(Debug))]
+    pub fn from_client(
+        policy_store_id: String,
+        verified_permissions_client: Client,
+        jwks_fetcher: Arc<dyn JwksFetcher>,
+        options: TokenVerifierOptions,
+    ) -> Result<Self, TokenError> {
+        let verifier = Self {
+            policy_selector: PolicySelector::from(policy_store_id),
+            identity_source: Mutex::new(VerifiedPermissionsIdentitySource::from(
+                verified_permissions_client,
+            )),
+            issuers: RwLock::new(HashMap::new()),
+            jwks_fetcher,
+            jwks: RwLock::new(HashMap::new()),
+            jwks_fetching: Mutex::new(()),
+            options,
+        };
+        task::block_in_place(|| Handle::current().block_on(verifier.refresh()))?;
+        Ok(verifier)
+    }
+
+    /// Verifies the identity token, the access token or both, and derives the principal, its
+    /// groups and the context from their claims.
+    ///
+    /// The principal id is `<user pool id>|<sub>` and each group in the `cognito:groups` claim is
+    /// a parent with the id `<user pool id>|<group>`. The identity token claims are the principal
+    /// attributes and the access token claims are the context.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TokenError` if no token is provided, a token is invalid, or the tokens are not
+    /// for the same principal.
+    #[instrument(skip_all, err(Debug))]
+    pub async fn verify(
+        &self,
+        identity_token: Option<&str>,
+        access_token: Option<&str>,
+    ) -> Result<VerifiedToken, TokenError> {
+        let identity = match identity_token {
+            Some(token) => Some(self.validate(token, TokenUse::Id).await?),
+            None => None,
+        };
+        let access = match access_token {
+            Some(token) => Some(self.validate(token, TokenUse::Access).await?),
+            None => None,
+        };
+
+        let (source, claims) = match (&identity, &access) {
+            (Some(identity), Some(access)) => {
+                if identity.0.issuer != access.0.issuer
+                    || identity.1.get("sub") != access.1.get("sub")
+                {
+                    return Err(TokenError::PrincipalMismatch);
+                }
+                identity
+            }
+            (Some(token), None) | (None, Some(token)) => token,
+            (None, None) => return Err(TokenError::MissingToken),
+        };
+        let sub = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .ok_or_else(|| TokenError::Rejected("the sub claim is not a string".to_string()))?;
+        let principal = EntityIdentifier::builder()
+            .entity_type(&source.principal_entity_type)
+            .entity_id(format!("{}|{sub}", source.user_pool_id))
+            .build()?;
+
+        let parents = match &self.options.group_entity_type {
+            Some(group_entity_type) => identity
+                .iter()
+                .chain(access.iter())
+                .filter_map(|(_, claims)| claims.get(COGNITO_GROUPS_CLAIM)?.as_array())
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|group| {
+                    EntityIdentifier::builder()
+                        .entity_type(group_entity_type)
+                        .entity_id(format!("{}|{group}", source.user_pool_id))
+                        .build()
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        let attributes = identity
+            .as_ref()
+            .map(|(_, claims)| claim_values(claims))
+            .unwrap_or_default();
+        let context = access
+            .as_ref()
+            .map(|(_, claims)| claim_values(claims))
+            .unwrap_or_default();
+
+        debug!(
+            "Verified Token: principal={principal:?}, groups={}",
+            parents.len()
+        );
+        Ok(VerifiedToken {
+            entities: vec![EntityItem::builder()
+                .identifier(principal.clone())
+                .set_attributes(Some(attributes))
+                .set_parents(Some(parents))
+                .build()],
+            principal,
+            context,
+        })
+    }
+
+    /// Reads the identity sources of the policy store again.
+    async fn refresh(&self) -> Result<(), TokenError> {
+        let identity_sources = self
+            .identity_source
+            .lock()
+            .await
+            .fetch(self.policy_selector.clone())
+            .await?;
+
+        let mut issuers = HashMap::new();
+        for output in identity_sources.values() {
+            match CognitoIdentitySource::try_from(output) {
+                Ok(source) => {
+                    issuers.insert(source.issuer.clone(), source);
+                }
+                Err(error) => warn!("Skipping Identity Source: {error}"),
+            }
+        }
+        info!("Loaded Identity Sources: issuers={:?}", issuers.keys());
+        *self.issuers.write().await = issuers;
+        Ok(())
+    }
+
+    /// Validates the signature, expiry, issuer, `token_use` and client id of a token and returns
+    /// its identity source and claims.
+    async fn validate(
+        &self,
+        token: &str,
+        token_use: TokenUse,
+    ) -> Result<(CognitoIdentitySource, Map<String, Value>), TokenError> {
+        let header = decode_header(token)?;
+        let issuer = unverified_issuer(token)?;
+        let source = self
+            .issuers
+            .read()
+            .await
+            .get(&issuer)
+            .cloned()
+            .ok_or_else(|| TokenError::UnknownIssuer(issuer.clone()))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| TokenError::UnknownKey(issuer.clone(), None))?;
+        let jwk = self.key(&issuer, &kid).await?;
+        let algorithm = key_algorithm(&jwk)?;
+        if header.alg != algorithm {
+            return Err(TokenError::Rejected(format!(
+                "the token is signed with {:?} instead of the {algorithm:?} algorithm of its key",
+                header.alg
+            )));
+        }
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[&issuer]);
+        validation.set_required_spec_claims(&["exp", "iss", "sub"]);
+        validation.validate_aud = false;
+        let claims =
+            decode::<Map<String, Value>>(token, &DecodingKey::from_jwk(&jwk)?, &validation)?.claims;
+
+        if claims.get("token_use").and_then(Value::as_str) != Some(token_use.claim()) {
+            return Err(TokenError::Rejected(format!(
+                "the token_use claim is not {}",
+                token_use.claim()
+            )));
+        }
+        if !source.client_ids.is_empty() {
+            let client_id_claim = token_use.client_id_claim();
+            let allowed = match claims.get(client_id_claim) {
+                Some(Value::String(client_id)) => source.client_ids.contains(client_id),
+                Some(Value::Array(client_ids)) => client_ids
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|client_id| source.client_ids.iter().any(|c| c == client_id)),
+                _ => false,
+            };
+            if !allowed {
+                return Err(TokenError::Rejected(format!(
+                    "the {client_id_claim} claim is not a client id of identity source {}",
+                    source.identity_source_id
+                )));
+            }
+        }
+        Ok((source, claims))
+    }
+
+    /// Returns the key `kid` of the issuer, fetching the JSON Web Key Set when it expired or does
+    /// not have the key, for example after a key rotation.
+    async fn key(&self, issuer: &str, kid: &str) -> Result<Jwk, TokenError> {
+        if let Some(jwk) = self.cached_key(issuer, kid).await {
+            return jwk;
+        }
+
+        let fetching = self.jwks_fetching.lock().await;
+        if let Some(jwk) = self.cached_key(issuer, kid).await {
+            return jwk;
+        }
+        let jwks = self.jwks_fetcher.fetch(issuer).await?;
+        let jwk = jwks.find(kid).cloned();
+        self.jwks.write().await.insert(
+            issuer.to_string(),
+            CachedJwks {
+                fetched_at: Instant::now(),
+                jwks,
+            },
+        );
+        drop(fetching);
+        jwk.ok_or_else(|| TokenError::UnknownKey(issuer.to_string(), Some(kid.to_string())))
+    }
+
+    /// Looks up the key `kid` in the cached JSON Web Key Set of the issuer. Returns `None` when
+    /// the key set must be fetched, because it expired, or it misses the key and was fetched longer
+    /// than the refetch interval ago.
+    async fn cached_key(&self, issuer: &str, kid: &str) -> Option<Result<Jwk, TokenError>> {
+        let (age, jwk) = self
+            .jwks
+            .read()
+            .await
+            .get(issuer)
+            .map(|cached| (cached.fetched_at.elapsed(), cached.jwks.find(kid).cloned()))?;
+        if age >= self.options.jwks_ttl {
+            return None;
+        }
+        match jwk {
+            Some(jwk) => Some(Ok(jwk)),
+            None if age < self.options.jwks_refetch_interval => Some(Err(TokenError::UnknownKey(
+                issuer.to_string(),
+                Some(kid.to_string()),
+            ))),
+            None => None,
+        }
+    }
+}
+
+#[async_trait]
+impl UpdateProviderData for TokenVerifier {
+    #[instrument(skip(self), err(Debug))]
+    async fn update_provider_data(&self) -> Result<(), UpdateProviderDataError> {
+        self.refresh()
+            .await
+            .map_err(|e| UpdateProviderDataError::General(Box::new(e)))
+    }
+}
+
+/// Returns the algorithm tokens signed with `jwk` must use, `RS256` when the key has no `alg`.
+fn key_algorithm(jwk: &Jwk) -> Result<Algorithm, TokenError> {
+    jwk.common
+        .key_algorithm
+        .map_or(Ok(Algorithm::RS256), |key_algorithm| {
+            key_algorithm.to_string().parse().map_err(|_| {
+                TokenError::Rejected(format!(
+                    "the {key_algorithm} algorithm of the key is not a signature algorithm"
+                ))
+            })
+        })
+}
+
+/// Reads the issuer of a token before its signature can be verified with the key of the issuer.
+fn unverified_issuer(token: &str) -> Result<String, TokenError> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims.clear();
+
+    decode::<Map<String, Value>>(token, &DecodingKey::from_secret(&[]), &validation)?
+        .claims
+        .get("iss")
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+        .ok_or_else(|| TokenError::Rejected("the token has no iss claim".to_string()))
+}
+
+/// Maps the claims that have a Cedar representation to attribute values.
+fn claim_values(claims: &Map<String, Value>) -> HashMap<String, AttributeValue> {
+    claims
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), claim_value(value)?)))
+        .collect()
+}
+
+/// Maps a claim to an attribute value. Null and fractional claims have no Cedar representation.
+fn claim_value(value: &Value) -> Option<AttributeValue> {
+    match value {
+        Value::Bool(value) => Some(AttributeValue::Boolean(*value)),
+        Value::Number(value) => value.as_i64().map(AttributeValue::Long),
+        Value::String(value) => Some(AttributeValue::String(value.clone())),
+        Value::Array(values) => Some(AttributeValue::Set(
+            values.iter().filter_map(claim_value).collect(),
+        )),
+        Value::Object(values) => Some(AttributeValue::Record(claim_values(values))),
+        Value::Null => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use aws_sdk_verifiedpermissions::types::AttributeValue;
+    use chrono::Utc;
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::{json, Value};
+
+    use crate::private::sources::identity_source::core::test::build_identity_source_events;
+    use crate::private::sources::test::build_client;
+    use crate::private::types::identity_source_id::IdentitySourceId;
+    use crate::private::types::policy_selector::PolicySelector;
+    use crate::public::token_verifier::{
+        FileJwksFetcher, JwkSet, JwksFetcher, TokenError, TokenVerifier,
+        TokenVerifierOptionsBuilder, DEFAULT_JWKS_REFETCH_INTERVAL,
+    };
+
+    pub const POLICY_STORE_ID: &str = "ps-1";
+    pub const ISSUER: &str = "https://cognito-idp.us-east-1.amazonaws.com/us-east-1_example";
+    const SIGNING_KEY: &[u8] = include_bytes!("../../tests/data/token/signing-key.pem");
+    const JWKS_PATH: &str = "tests/data/token/jwks.json";
+
+    /// Signs the claims with the test key, adding the issuer, expiry and `token_use` claims.
+    pub fn sign(token_use: &str, claims: Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        sign_with_header(&header, token_use, claims)
+    }
+
+    /// Signs the claims with the test key and `header`.
+    fn sign_with_header(header: &Header, token_use: &str, mut claims: Value) -> String {
+        let claims_map = claims.as_object_mut().unwrap();
+        claims_map
+            .entry("iss")
+            .or_insert_with(|| Value::from(ISSUER));
+        claims_map
+            .entry("exp")
+            .or_insert_with(|| Value::from(Utc::now().timestamp() + 3600));
+        claims_map.insert("token_use".to_string(), Value::from(token_use));
+
+        encode(
+            header,
+            &claims,
+            &EncodingKey::from_rsa_pem(SIGNING_KEY).unwrap(),
+        )
+        .unwrap()
+    }
+
+    /// Counts the fetches of the test JSON Web Key Set.
+    #[derive(Debug, Default)]
+    struct CountingJwksFetcher(AtomicUsize);
+
+    #[async_trait]
+    impl JwksFetcher for CountingJwksFetcher {
+        async fn fetch(&self, issuer: &str) -> Result<JwkSet, TokenError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            FileJwksFetcher::new(JWKS_PATH).fetch(issuer).await
+        }
+    }
+
+    /// Builds a `TokenVerifier` for a policy store with a single Cognito identity source.
+    pub fn build_verifier() -> TokenVerifier {
+        build_verifier_with(
+            Arc::new(FileJwksFetcher::new(JWKS_PATH)),
+            TokenVerifierOptionsBuilder::default(),
+        )
+    }
+
+    fn build_verifier_with(
+        jwks_fetcher: Arc<dyn JwksFetcher>,
+        options: TokenVerifierOptionsBuilder,
+    ) -> TokenVerifier {
+        let policy_selector = PolicySelector::from(POLICY_STORE_ID.to_string());
+        let client = build_client(build_identity_source_events(
+            &policy_selector,
+            &IdentitySourceId("is-1".to_string()),
+        ));
+        TokenVerifier::from_client(
+            POLICY_STORE_ID.to_string(),
+            client,
+            jwks_fetcher,
+            options
+                .group_entity_type("MyApp::UserGroup")
+                .build()
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn verify_maps_identity_token_claims_to_the_principal_and_groups() {
+        let token_verifier = build_verifier();
+        let token = sign(
+            "id",
+            json!({
+                "sub": "alice",
+                "aud": "client-1",
+                "cognito:groups": ["admins", "staff"],
+                "email_verified": true,
+                "score": 0.5,
+            }),
+        );
+
+        let verified = token_verifier.verify(Some(&token), None).await.unwrap();
+        assert_eq!(verified.principal.entity_type(), "MyApp::User");
+        assert_eq!(verified.principal.entity_id(), "us-east-1_example|alice");
+
+        let principal = &verified.entities[0];
+        assert_eq!(principal.identifier(), Some(&verified.principal));
+        assert_eq!(
+            principal
+                .parents()
+                .iter()
+                .map(|parent| (parent.entity_type(), parent.entity_id()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("MyApp::UserGroup", "us-east-1_example|admins"),
+                ("MyApp::UserGroup", "us-east-1_example|staff"),
+            ]
+        );
+        let attributes = principal.attributes().unwrap();
+        assert_eq!(
+            attributes.get("email_verified"),
+            Some(&AttributeValue::Boolean(true))
+        );
+        assert!(!attributes.contains_key("score"));
+        assert!(verified.context.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn verify_maps_access_token_claims_to_the_context() {
+        let token_verifier = build_verifier();
+        let identity_token = sign("id", json!({ "sub": "alice", "aud": "client-1" }));
+        let access_token = sign(
+            "access",
+            json!({ "sub": "alice", "client_id": "client-1", "scope": "photos/read" }),
+        );
+
+        let verified = token_verifier
+            .verify(Some(&identity_token), Some(&access_token))
+            .await
+            .unwrap();
+        assert_eq!(verified.principal.entity_id(), "us-east-1_example|alice");
+        assert_eq!(
+            verified.context.get("scope"),
+            Some(&AttributeValue::String("photos/read".to_string()))
+        );
+
+        let other_access_token = sign("access", json!({ "sub": "bob", "client_id": "client-1" }));
+        assert!(matches!(
+            token_verifier
+                .verify(Some(&identity_token), Some(&other_access_token))
+                .await,
+            Err(TokenError::PrincipalMismatch)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn verify_rejects_invalid_tokens() {
+        let token_verifier = build_verifier();
+
+        let wrong_client = sign("id", json!({ "sub": "alice", "aud": "client-2" }));
+        assert!(matches!(
+            token_verifier.verify(Some(&wrong_client), None).await,
+            Err(TokenError::Rejected(_))
+        ));
+
+        let access_as_identity = sign("access", json!({ "sub": "alice", "client_id": "client-1" }));
+        assert!(matches!(
+            token_verifier.verify(Some(&access_as_identity), None).await,
+            Err(TokenError::Rejected(_))
+        ));
+
+        let expired = sign(
+            "id",
+            json!({ "sub": "alice", "aud": "client-1", "exp": Utc::now().timestamp() - 3600 }),
+        );
+        assert!(matches!(
+            token_verifier.verify(Some(&expired), None).await,
+            Err(TokenError::InvalidToken(_))
+        ));
+
+        let token = sign("id", json!({ "sub": "alice", "aud": "client-1" }));
+        let (message, signature) = token.rsplit_once('.').unwrap();
+        let tampered = format!("{message}.{}", signature.chars().rev().collect::<String>());
+        assert!(matches!(
+            token_verifier.verify(Some(&tampered), None).await,
+            Err(TokenError::InvalidToken(_))
+        ));
+
+        let unknown_issuer = sign(
+            "id",
+            json!({ "sub": "alice", "aud": "client-1", "iss": "https://issuer.example.com" }),
+        );
+        assert!(matches!(
+            token_verifier.verify(Some(&unknown_issuer), None).await,
+            Err(TokenError::UnknownIssuer(_))
+        ));
+
+        assert!(matches!(
+            token_verifier.verify(None, None).await,
+            Err(TokenError::MissingToken)
+        ));
+
+        let mut header = Header::new(Algorithm::RS384);
+        header.kid = Some("test-key".to_string());
+        let other_algorithm =
+            sign_with_header(&header, "id", json!({ "sub": "alice", "aud": "client-1" }));
+        assert!(matches!(
+            token_verifier.verify(Some(&other_algorithm), None).await,
+            Err(TokenError::Rejected(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn unknown_key_ids_refetch_the_key_set_once_per_interval() {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("rotated-key".to_string());
+        let token = sign_with_header(&header, "id", json!({ "sub": "alice", "aud": "client-1" }));
+
+        for (refetch_interval, fetches) in [(DEFAULT_JWKS_REFETCH_INTERVAL, 1), (Duration::ZERO, 3)]
+        {
+            let jwks_fetcher = Arc::new(CountingJwksFetcher::default());
+            let token_verifier = build_verifier_with(
+                jwks_fetcher.clone(),
+                TokenVerifierOptionsBuilder::default().jwks_refetch_interval(refetch_interval),
+            );
+            for _ in 0..3 {
+                assert!(matches!(
+                    token_verifier.verify(Some(&token), None).await,
+                    Err(TokenError::UnknownKey(_, Some(kid))) if kid == "rotated-key"
+                ));
+            }
+            assert_eq!(jwks_fetcher.0.load(Ordering::SeqCst), fetches);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Translates Amazon Verified Permissions `IsAuthorized` inputs to Cedar requests and entities, so
+//! that the same request can be evaluated remotely or locally.
+//!
+//! # Examples
+//!
+//! ```
+//! use aws_sdk_verifiedpermissions::operation::is_authorized::IsAuthorizedInput;
+//! use aws_sdk_verifiedpermissions::types::{ActionIdentifier, EntityIdentifier};
+//! use avp_local_agent::public::translator::CedarRequest;
+//!
+//! let input = IsAuthorizedInput::builder()
+//!     .principal(
+//!         EntityIdentifier::builder()
+//!             .entity_type("User")
+//!             .entity_id("alice")
+//!             .build()
+//!             .unwrap(),
+//!     )
+//!     .action(
+//!         ActionIdentifier::builder()
+//!             .action_type("Action")
+//!             .action_id("view")
+//!             .build()
+//!             .unwrap(),
+//!     )
+//!     .resource(
+//!         EntityIdentifier::builder()
+//!             .entity_type("Photo")
+//!             .entity_id("beach")
+//!             .build()
+//!             .unwrap(),
+//!     )
+//!     .build()
+//!     .unwrap();
+//!
+//! let cedar_request = CedarRequest::try_from(&input).unwrap();
+//! assert_eq!(
+//!     cedar_request.request.principal().unwrap().to_string(),
+//!     r#"User::"alice""#
+//! );
+//! ```
+pub use crate::private::translator::avp_to_cedar::{
+    action_uid, attribute_value, context, entities, entity_uid, request, CedarRequest, SchemaFormat,
+};
+pub use crate::private::translator::error::{StatementParseError, TranslatorException};